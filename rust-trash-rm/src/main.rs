@@ -1,228 +1,574 @@
+use std::collections::BTreeSet;
 use std::env;
-use std::ffi::CString;
-use std::collections::HashMap;
+use std::ffi::OsString;
 use std::fs::{self, read_dir, read_to_string};
-use std::io::{self, Write};
-use std::os::unix::fs::PermissionsExt;
+use std::io::{self, BufRead, Write};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use trash_cli_core::cli::{
+    self, load_config, trash_dir_arg, Arg, Complete, Nargs, Parsed, Parser, Shell, UsageError, DEBUG_ENV_ARG,
+    IGNORE_CASE_ARG, NULL_ARG, PRINT_COMPLETION_ARG, VERSION_ARG,
+};
+use trash_cli_core::exit_codes;
+use trash_cli_core::log;
+use trash_cli_core::trash_dirs::scan_all_users_trash_dirs;
+use trash_cli_core::{
+    describe_environment, entry_name, expand_path, format_datetime, list_mount_points, list_volumes,
+    parse_human_duration, print_size, printable_path, recursive_size, resolve_original_location, scan_trash_dirs,
+    stderr_progress, tr, tr_format, version_line, volume_of, CancellationToken, CommandContext, CommandKind, Config,
+    DisplayZone, Environment, ExitCodes, ExitStatusLike, FileSystem, GlobPattern, MatchOptions, NetworkFilter,
+    NoProgress, RealEnvironment, RealFileSystem, SystemUsers, TrashDirEvent, TrashIndex, TrashInfo,
+};
 
-type EnvVarMap = HashMap<String, String>;
+const USAGE: &str = "Usage:\n    trash-rm [-i] [-v] [--dry-run] [--full-path] [--ignore-case] [--older-than AGE] \
+     [--newer-than AGE] [--under DIR] [--trash-dir TRASH_DIR] [--all-users] [--include-network] \
+     [--files-from FILE [-0]] PATTERN [PATTERN ...]\n\nPlease specify PATTERN.\ntrash-rm matches patterns like \
+     fnmatch(3) (`*`, `?`, `[...]`); patterns starting with '/' match the full original path, others match the \
+     basename.";
+
+#[derive(Debug, Default)]
+struct Options {
+    patterns: Vec<String>,
+    /// `--full-path` and `--ignore-case`: `FNM_PATHNAME` and `FNM_CASEFOLD`.
+    match_options: MatchOptions,
+    /// `--files-from`: original locations to remove, matched exactly.
+    files_from: Option<PathBuf>,
+    /// `-0`: the `--files-from` list is NUL-separated.
+    null: bool,
+    interactive: bool,
+    verbose: u32,
+    dry_run: bool,
+    age: AgeFilter,
+    /// `--under DIR`: only items whose original path is inside DIR.
+    under: Option<PathBuf>,
+    trash_dirs: Vec<PathBuf>,
+    all_users: bool,
+    network: NetworkFilter,
+}
+
+/// Optional bounds on how long ago an item was trashed.
+#[derive(Debug, Default, Clone, Copy)]
+struct AgeFilter {
+    older_than: Option<Duration>,
+    newer_than: Option<Duration>,
+}
+
+impl AgeFilter {
+    fn is_active(&self) -> bool {
+        self.older_than.is_some() || self.newer_than.is_some()
+    }
+
+    /// Items without a parseable deletion date never pass an active filter.
+    fn accepts(&self, deleted_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+        if !self.is_active() {
+            return true;
+        }
+        let Some(age) = deleted_at.and_then(|date| (now - date).to_std().ok()) else {
+            return false;
+        };
+        self.older_than.is_none_or(|limit| age > limit) && self.newer_than.is_none_or(|limit| age < limit)
+    }
+}
+
+/// A trashinfo whose original location matched one of the patterns.
+#[derive(Debug)]
+struct Match {
+    original_location: PathBuf,
+    trashinfo_path: PathBuf,
+    backup_path: PathBuf,
+    pattern: String,
+    deleted_at: Option<DateTime<Utc>>,
+}
+
+impl Options {
+    fn into_context(self, args: Vec<OsString>) -> (CommandContext, Vec<String>) {
+        let context = CommandContext {
+            command: CommandKind::Remove,
+            args,
+            cwd: env::current_dir().unwrap_or_default(),
+            interactive: self.interactive,
+            dry_run: self.dry_run,
+        };
+        (context, self.patterns)
+    }
+}
+
+const ARGS: &[Arg] = &[
+    PRINT_COMPLETION_ARG,
+    VERSION_ARG,
+    DEBUG_ENV_ARG,
+    Arg::flag("interactive", &["-i", "--interactive"], "ask before removing each matched item"),
+    Arg::flag(
+        "verbose",
+        &["-v", "--verbose"],
+        "report each removed item; repeat to also report the trash dirs scanned and the items skipped",
+    ),
+    Arg::flag("dry_run", &["--dry-run"], "show which items would have been removed"),
+    Arg::flag(
+        "full_path",
+        &["--full-path"],
+        "match every PATTERN against the whole original path, with * and ? not matching '/'",
+    ),
+    IGNORE_CASE_ARG,
+    Arg::option(
+        "older_than",
+        &["--older-than"],
+        "AGE",
+        "only remove items trashed more than AGE ago: days, or a duration like 36h or 2w",
+    ),
+    Arg::option("newer_than", &["--newer-than"], "AGE", "only remove items trashed less than AGE ago"),
+    Arg::option(
+        "under",
+        &["--under"],
+        "DIR",
+        "only remove matching items whose original path is DIR or inside it, e.g. --under ~/project '*'",
+    )
+    .complete(Complete::Directory),
+    Arg::option("trash_dir", &["--trash-dir"], "TRASH_DIR", "specify the trash directory to use")
+        .complete(Complete::Directory),
+    Arg::flag("all_users", &["--all-users"], "remove matching items from the trash dirs of all the users"),
+    Arg::flag(
+        "include_network",
+        &["--include-network"],
+        "also scan network filesystems (NFS, CIFS, sshfs, ...) for trash dirs",
+    ),
+    Arg::option(
+        "files_from",
+        &["--files-from"],
+        "FILE",
+        "also remove the items whose original path is listed in FILE, one per line ('-' reads standard input), \
+         as printed by trash-list --output path",
+    )
+    .complete(Complete::File),
+    NULL_ARG,
+    Arg::positional("PATTERN", Nargs::ZeroOrMore, ""),
+];
+
+const EPILOG: &str = "\
+trash-rm matches patterns like fnmatch(3) (`*`, `?`, `[...]`); patterns starting
+with '/' match the full original path, others match the basename. With
+--full-path, every pattern matches the full path and wildcards stop at '/':
+'/home/me/build/*' removes the items trashed from build, not from its subdirs.
+
+Report bugs to https://github.com/andreafrancia/trash-cli/issues";
+
+fn parser() -> Parser {
+    Parser::new("trash-rm", "Remove trashed files whose original path matches PATTERN", ARGS).with_epilog(EPILOG)
+}
+
+/// The `--under` directory, absolute like the original paths it is compared
+/// to, and with `~` expanded.
+fn parse_under(found: &cli::Match) -> Result<PathBuf, UsageError> {
+    let dir = expand_path(Path::new(found.value.as_deref().unwrap_or_default()), &RealEnvironment);
+    std::path::absolute(&dir).map_err(|err| {
+        UsageError(tr_format("argument {}: invalid directory '{}': {}", &[&found.option, &dir.display(), &err]))
+    })
+}
+
+fn parse_age(found: &cli::Match) -> Result<Duration, UsageError> {
+    let value = found.text();
+    parse_human_duration(&value)
+        .ok_or_else(|| UsageError(tr_format("argument {}: invalid duration value: '{}'", &[&found.option, &value])))
+}
+
+enum Invocation {
+    Help,
+    PrintCompletion(Shell),
+    PrintVersion,
+    DebugEnv,
+    Remove(Options),
+}
+
+fn parse_args(config: &Config, args: &[OsString]) -> Result<Invocation, UsageError> {
+    let matches = match parser().parse_with_config(config, CommandKind::Remove, args)? {
+        Parsed::Help => return Ok(Invocation::Help),
+        Parsed::Args(matches) => matches,
+    };
+    if let Some(found) = matches.iter().find(|found| found.dest == "print_completion") {
+        let shell = Shell::parse(&found.text()).expect("checked against the choices");
+        return Ok(Invocation::PrintCompletion(shell));
+    }
+    if matches.iter().any(|found| found.dest == "version") {
+        return Ok(Invocation::PrintVersion);
+    }
+    if matches.iter().any(|found| found.dest == "debug_env") {
+        return Ok(Invocation::DebugEnv);
+    }
+    let mut options = Options::default();
+    for found in matches {
+        match found.dest {
+            "interactive" => options.interactive = true,
+            "verbose" => options.verbose += 1,
+            "dry_run" => options.dry_run = true,
+            "full_path" => options.match_options.pathname = true,
+            "ignore_case" => options.match_options.casefold = true,
+            "older_than" => options.age.older_than = Some(parse_age(&found)?),
+            "newer_than" => options.age.newer_than = Some(parse_age(&found)?),
+            "under" => options.under = Some(parse_under(&found)?),
+            "trash_dir" => options.trash_dirs.extend(found.value.map(trash_dir_arg)),
+            "all_users" => options.all_users = true,
+            "include_network" => options.network.include_all = true,
+            "PATTERN" => options.patterns.push(found.text()),
+            "files_from" => options.files_from = found.value.map(PathBuf::from),
+            "null" => options.null = true,
+            _ => {}
+        }
+    }
+    if options.null && options.files_from.is_none() {
+        return Err(UsageError("-0/--null only applies to --files-from".to_string()));
+    }
+    if options.interactive && options.files_from.as_deref() == Some(Path::new("-")) {
+        return Err(UsageError("--interactive cannot be used with --files-from -".to_string()));
+    }
+    if options.trash_dirs.is_empty() {
+        options.trash_dirs = config.trash_dirs.clone();
+    }
+    options.network.fstypes = config.include_network.clone();
+    Ok(Invocation::Remove(options))
+}
 
 fn main() {
     let mut stderr = io::stderr();
-    let uid = unsafe { libc::geteuid() };
-    let args: Vec<String> = env::args().collect();
+    let environ = RealEnvironment;
+    let uid = environ.uid();
+    let args: Vec<OsString> = env::args_os().skip(1).collect();
 
-    if args.len() <= 1 {
-        writeln!(
-            stderr,
-            "Usage:\n    trash-rm PATTERN\n\nPlease specify PATTERN.\ntrash-rm uses fnmatch.fnmatchcase to match patterns, see https://docs.python.org/3/library/fnmatch.html for more details."
-        )
-        .expect("unable to write usage to stderr");
-        std::process::exit(8);
+    let config = load_config("trash-rm");
+    let options = match parse_args(&config, &args) {
+        Ok(Invocation::Remove(options)) => options,
+        Ok(Invocation::Help) => {
+            println!("{}", parser().format_help());
+            return;
+        }
+        Ok(Invocation::PrintCompletion(shell)) => {
+            print!("{}", parser().format_completion(shell));
+            return;
+        }
+        Ok(Invocation::PrintVersion) => {
+            println!("{}", version_line("trash-rm"));
+            return;
+        }
+        Ok(Invocation::DebugEnv) => {
+            println!("{}", describe_environment(&environ, CommandKind::Remove));
+            return;
+        }
+        Err(error) => parser().exit_with_error(&error.0),
+    };
+    let verbose = options.verbose;
+    log::init("trash-rm", verbose);
+    let age = options.age;
+    let under = options.under.clone();
+    let paths = match &options.files_from {
+        Some(source) => match read_paths(source, if options.null { b'\0' } else { b'\n' }) {
+            Ok(paths) => paths,
+            Err(err) => {
+                writeln!(stderr, "{}", tr_format("trash-rm: cannot read {}: {}", &[&source.display(), &err]))
+                    .expect("unable to write error to stderr");
+                std::process::exit(ExitStatusLike::Error.as_code().into());
+            }
+        },
+        None => BTreeSet::new(),
+    };
+    let has_paths = options.files_from.is_some();
+    let trash_dirs = if options.all_users {
+        all_users_trash_dirs(&environ, &options.network)
+    } else if options.trash_dirs.is_empty() {
+        collect_trash_dirs(&environ, uid, &options.network)
+    } else {
+        user_specified_trash_dirs(&options.trash_dirs)
+    };
+    let match_options = options.match_options;
+    let (context, patterns) = options.into_context(args);
+    let patterns: Vec<GlobPattern> = patterns
+        .iter()
+        .map(|pattern| GlobPattern::new(pattern, match_options))
+        .collect();
+    if patterns.is_empty() && !has_paths {
+        writeln!(stderr, "{USAGE}").expect("unable to write usage to stderr");
+        std::process::exit(ExitCodes::of(CommandKind::Remove).missing_operand.into());
     }
-
-    let pattern = args[1].as_str();
-    let environ = env::vars().collect::<EnvVarMap>();
-    let mut had_error = false;
+    let mut summary = Summary::default();
     let stderr = io::stderr();
     let mut stderr = stderr.lock();
-    for item in list_trashinfo_matches(collect_trash_dirs(&environ, uid as u32), pattern) {
+    let now = DateTime::<Utc>::from(RealFileSystem.now());
+    let matches = list_trashinfo_matches(trash_dirs, &patterns, &paths, under.as_deref());
+    // A progress bar would be garbled by the prompts and per-item output.
+    let mut progress = match context.interactive || context.dry_run || verbose > 0 {
+        true => Box::new(NoProgress),
+        false => stderr_progress("trash-rm", "freed"),
+    };
+    progress.start(Some(matches.len() as u64), None);
+    // Trash dirs something was removed from, whose index needs pruning.
+    let mut touched: BTreeSet<PathBuf> = BTreeSet::new();
+    // Prompts would not notice an interrupt, so -i keeps the default action.
+    let cancel = match context.interactive {
+        true => CancellationToken::new(),
+        false => CancellationToken::on_signals(),
+    };
+    for item in matches {
+        if cancel.is_cancelled() {
+            break;
+        }
         match item {
-            Ok((_original_location, trashinfo_path, backup_path)) => {
+            Ok(Match {
+                original_location,
+                trashinfo_path,
+                backup_path,
+                pattern,
+                deleted_at,
+            }) => {
+                if !age.accepts(deleted_at, now) {
+                    log::debug(
+                        "rm",
+                        format_args!("skipping '{}': outside the age range", original_location.display()),
+                    );
+                    progress.advance(1, 0);
+                    continue;
+                }
+                summary.matched += 1;
+                if verbose > 0 {
+                    let shown = printable_path(&original_location, &stderr);
+                    writeln!(stderr, "{}", tr_format("trash-rm: '{}' matches '{}'", &[&shown.display(), &pattern]))
+                        .expect("unable to write match to stderr");
+                }
+                if context.interactive && !confirm_removal(&original_location, deleted_at, &backup_path) {
+                    summary.declined += 1;
+                    progress.advance(1, 0);
+                    continue;
+                }
+                if context.dry_run {
+                    if RealFileSystem.symlink_metadata(&backup_path).is_ok() {
+                        let shown = printable_path(&backup_path, &stderr);
+                        writeln!(stderr, "{}", tr_format("trash-rm: would remove {}", &[&shown.display()]))
+                            .expect("unable to write dry run to stderr");
+                    }
+                    let shown = printable_path(&trashinfo_path, &stderr);
+                    writeln!(stderr, "{}", tr_format("trash-rm: would remove {}", &[&shown.display()]))
+                        .expect("unable to write dry run to stderr");
+                    summary.would_remove += 1;
+                    progress.advance(1, 0);
+                    continue;
+                }
+                let size = match progress.is_visible() {
+                    true => recursive_size(&RealFileSystem, &backup_path).unwrap_or(0),
+                    false => 0,
+                };
+                let mut failed = false;
                 if let Err(err) = rm_file_if_exists(&backup_path) {
-                    had_error = true;
-                    writeln!(stderr, "trash-rm: failed to remove backup file {}: {}", backup_path.display(), err)
-                        .expect("unable to write removal error to stderr");
+                    failed = true;
+                    let shown = printable_path(&backup_path, &stderr);
+                    let message = tr_format("trash-rm: failed to remove backup file {}: {}", &[&shown.display(), &err]);
+                    writeln!(stderr, "{}", message).expect("unable to write removal error to stderr");
                 }
                 if let Err(err) = rm_file2(&trashinfo_path) {
-                    had_error = true;
-                    writeln!(stderr, "trash-rm: failed to remove trashinfo {}: {}", trashinfo_path.display(), err)
-                        .expect("unable to write removal error to stderr");
+                    failed = true;
+                    let shown = printable_path(&trashinfo_path, &stderr);
+                    let message = tr_format("trash-rm: failed to remove trashinfo {}: {}", &[&shown.display(), &err]);
+                    writeln!(stderr, "{}", message).expect("unable to write removal error to stderr");
                 }
+                if let Some(trash_dir) = trashinfo_path.parent().and_then(Path::parent) {
+                    touched.insert(trash_dir.to_path_buf());
+                }
+                if failed {
+                    summary.errors += 1;
+                } else {
+                    summary.removed += 1;
+                }
+                progress.advance(1, size);
             }
-            Err(info_path) => {
-                writeln!(
-                    stderr,
-                    "trash-rm: {}: unable to parse 'Path'",
-                    info_path.display()
-                )
-                .expect("unable to write error to stderr");
+            Err((info_path, reason)) => {
+                summary.errors += 1;
+                progress.advance(1, 0);
+                writeln!(stderr, "{}", tr_format("trash-rm: cannot parse {}: {}", &[&info_path.display(), &reason]))
+                    .expect("unable to write error to stderr");
             }
         }
     }
 
-    if had_error {
-        std::process::exit(1);
+    progress.finish();
+    for trash_dir in &touched {
+        TrashIndex::update(&RealFileSystem, trash_dir, |index| index.retain_existing(&RealFileSystem, trash_dir));
+    }
+    writeln!(stderr, "{}", summary.render(context.dry_run)).expect("unable to write summary to stderr");
+    if cancel.is_cancelled() {
+        writeln!(stderr, "{}", tr("trash-rm: interrupted")).expect("unable to write summary to stderr");
+        std::process::exit(exit_codes::INTERRUPTED.into());
     }
+    std::process::exit(summary.exit_status().as_code().into());
 }
 
-fn collect_trash_dirs(environ: &EnvVarMap, uid: u32) -> Vec<(PathBuf, String)> {
-    let xdg_data_home = environ.get("XDG_DATA_HOME").cloned();
-    let home_dir = environ.get("HOME").cloned();
-    let volumes_env = environ.get("TRASH_VOLUMES").cloned();
-
-    let mut trash_dirs = Vec::new();
-
-    if let Some(xdg) = xdg_data_home {
-        let mut xdg_trash = PathBuf::from(xdg);
-        xdg_trash.push("Trash");
-        trash_dirs.push((xdg_trash, "/".to_string()));
-    } else if let Some(home) = home_dir {
-        let mut default_trash = PathBuf::from(home);
-        default_trash.push(".local");
-        default_trash.push("share");
-        default_trash.push("Trash");
-        trash_dirs.push((default_trash, "/".to_string()));
+/// Per-run counters reported once all matches have been processed.
+#[derive(Debug, Default)]
+struct Summary {
+    matched: usize,
+    removed: usize,
+    /// Matches `--dry-run` only listed.
+    would_remove: usize,
+    declined: usize,
+    errors: usize,
+}
+
+impl Summary {
+    fn exit_status(&self) -> ExitStatusLike {
+        if self.matched == 0 && self.errors == 0 {
+            return ExitStatusLike::NoMatch;
+        }
+        ExitStatusLike::from_counts(self.removed + self.would_remove + self.declined, self.errors)
     }
 
-    let volumes = if let Some(volumes) = volumes_env {
-        if volumes.is_empty() {
-            Vec::new()
+    fn render(&self, dry_run: bool) -> String {
+        let mut line = if self.matched == 0 {
+            tr("trash-rm: no trashed items matched").to_string()
+        } else if dry_run {
+            tr_format("trash-rm: would remove {} of {} matched items", &[&self.would_remove, &self.matched])
         } else {
-            volumes
-                .split(':')
-                .filter(|path| !path.is_empty())
-                .map(PathBuf::from)
-                .collect()
+            tr_format("trash-rm: removed {} of {} matched items", &[&self.removed, &self.matched])
+        };
+        if self.declined > 0 {
+            line.push_str(&tr_format(", {} declined", &[&self.declined]));
         }
-    } else {
-        list_mount_points()
-    };
+        if self.errors > 0 {
+            line.push_str(&tr_format(", {} errors", &[&self.errors]));
+        }
+        line
+    }
+}
 
-    for volume in volumes {
-        let top_dir = volume.join(".Trash").join(uid.to_string());
-        let parent = top_dir.parent().unwrap_or_else(|| Path::new("/")).to_path_buf();
+/// Asks on stderr whether a match should be permanently removed.
+///
+/// Anything other than an explicit `y`/`yes` (including EOF) declines.
+fn confirm_removal(original_location: &Path, deleted_at: Option<DateTime<Utc>>, backup_path: &Path) -> bool {
+    let size = recursive_size(&RealFileSystem, backup_path)
+        .map(print_size)
+        .unwrap_or_else(|_| "? B".to_string());
+    let deleted_at = deleted_at
+        .map(|date| format_datetime(date, DisplayZone::Local))
+        .unwrap_or_else(|| "????-??-?? ??:??:??".to_string());
 
-        if top_dir.exists() && parent.is_dir() && is_sticky_dir(&parent) && !is_symlink(&parent) {
-            trash_dirs.push((top_dir, volume.to_string_lossy().to_string()));
-        }
+    let mut stderr = io::stderr();
+    let _ = write!(
+        stderr,
+        "{}",
+        tr_format(
+            "trash-rm: permanently remove '{}' ({}, deleted {})? [y/N] ",
+            &[&printable_path(original_location, &stderr).display(), &size, &deleted_at],
+        )
+    );
+    let _ = stderr.flush();
 
-        let alt_top_dir = volume.join(format!(".Trash-{uid}"));
-        if alt_top_dir.is_dir() {
-            trash_dirs.push((alt_top_dir, volume.to_string_lossy().to_string()));
-        }
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
     }
+    matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
 
-    trash_dirs
+/// The home trash and the top-dir trashes of `uid` that pass the checks of
+/// the specification, paired with their volume.
+fn collect_trash_dirs(environ: &dyn Environment, uid: u32, network: &NetworkFilter) -> Vec<(PathBuf, PathBuf)> {
+    found_trash_dirs(scan_trash_dirs(&RealFileSystem, environ, uid, &list_volumes(environ, network)))
+}
+
+/// The home and top-dir trash dirs of every user, paired with their volume.
+fn all_users_trash_dirs(environ: &dyn Environment, network: &NetworkFilter) -> Vec<(PathBuf, PathBuf)> {
+    found_trash_dirs(scan_all_users_trash_dirs(&RealFileSystem, &SystemUsers, &list_volumes(environ, network)))
+}
+
+fn found_trash_dirs(events: Vec<TrashDirEvent>) -> Vec<(PathBuf, PathBuf)> {
+    events
+        .into_iter()
+        .filter_map(|event| event.found().cloned())
+        .map(|trash_dir| {
+            let volume = trash_dir.mount_point.unwrap_or_else(|| PathBuf::from("/"));
+            (trash_dir.path, volume)
+        })
+        .collect()
 }
 
-fn list_mount_points() -> Vec<PathBuf> {
-    read_to_string("/proc/self/mounts")
-        .map(|content| {
-            content
-                .lines()
-                .filter_map(|line| {
-                    let fields: Vec<&str> = line.split_whitespace().collect();
-                    (fields.len() >= 2).then(|| {
-                        let mountpoint = unescape_mountpoint(fields[1]);
-                        PathBuf::from(mountpoint)
-                    })
-                })
-                .filter(|path| path.exists())
-                .collect()
+/// Pairs each `--trash-dir` with the mount point it lives on, so relative
+/// `Path=` entries of volume trash dirs resolve correctly.
+fn user_specified_trash_dirs(trash_dirs: &[PathBuf]) -> Vec<(PathBuf, PathBuf)> {
+    let mount_points = list_mount_points();
+    trash_dirs
+        .iter()
+        .map(|trash_dir| {
+            let volume = volume_of(trash_dir, &mount_points);
+            (trash_dir.clone(), volume)
         })
-        .unwrap_or_default()
+        .collect()
 }
 
-fn unescape_mountpoint(value: &str) -> String {
-    value
-        .replace("\\011", "\t")
-        .replace("\\012", "\n")
-        .replace("\\040", " ")
-        .replace("\\\\", "\\")
+/// The absolute paths listed in `source`, for exact matching.
+fn read_paths(source: &Path, separator: u8) -> io::Result<BTreeSet<PathBuf>> {
+    cli::files_from(source, separator)?.map(|path| std::path::absolute(path?)).collect()
 }
 
+/// Matches the items whose original location matches one of `patterns` or
+/// is one of `paths`, and lies in `under` when given. Paths are compared
+/// component by component, so `--under /home/me/project` does not take in
+/// `/home/me/project2`.
 fn list_trashinfo_matches(
-    trash_dirs: Vec<(PathBuf, String)>,
-    pattern: &str,
-) -> Vec<Result<(PathBuf, PathBuf, PathBuf), PathBuf>> {
+    trash_dirs: Vec<(PathBuf, PathBuf)>,
+    patterns: &[GlobPattern],
+    paths: &BTreeSet<PathBuf>,
+    under: Option<&Path>,
+) -> Vec<Result<Match, (PathBuf, String)>> {
     let mut matched = Vec::new();
     for (trash_dir, volume) in trash_dirs {
+        log::info("rm", format_args!("scanning {}", trash_dir.display()));
         let info_dir = trash_dir.join("info");
-        let entries = match read_dir(info_dir) {
+        let entries = match read_dir(&info_dir) {
             Ok(entries) => entries,
-            Err(_) => continue,
-        };
-
-        for entry in entries.flatten() {
-            let name = entry.file_name();
-            let name = name.to_string_lossy();
-            if !name.ends_with(".trashinfo") {
+            Err(err) => {
+                log::debug("rm", format_args!("cannot read {}: {}", info_dir.display(), err));
                 continue;
             }
+        };
 
+        for entry in entries.flatten() {
             let info_path = entry.path();
+            let Some(name) = entry_name(&info_path) else {
+                continue;
+            };
             match parse_trashinfo_path(&info_path) {
-                Ok(original_location) => {
-                    let complete_path = join_volume_and_path(&volume, &original_location);
-                    if path_matches(pattern, &complete_path) {
-                        let backup_path = backup_copy_for_trashinfo(&info_path);
-                        matched.push(Ok((PathBuf::from(original_location), info_path, backup_path)));
+                Ok((original_location, deleted_at)) => {
+                    let complete_path = resolve_original_location(&volume, &original_location);
+                    if under.is_some_and(|dir| !complete_path.starts_with(dir)) {
+                        log::trace("rm", format_args!("'{}' is outside --under", complete_path.display()));
+                        continue;
+                    }
+                    let pattern = match paths.contains(&complete_path) {
+                        true => Some(complete_path.display().to_string()),
+                        false => patterns
+                            .iter()
+                            .find(|pattern| pattern.matches_original_path(&complete_path))
+                            .map(|pattern| pattern.as_str().to_string()),
+                    };
+                    if let Some(pattern) = pattern {
+                        matched.push(Ok(Match {
+                            original_location: complete_path,
+                            trashinfo_path: info_path,
+                            backup_path: trash_dir.join("files").join(name),
+                            pattern,
+                            deleted_at,
+                        }));
+                    } else {
+                        log::trace("rm", format_args!("'{}' matches no pattern", complete_path.display()));
                     }
                 }
-                Err(_) => matched.push(Err(info_path)),
+                Err(reason) => matched.push(Err((info_path, reason))),
             }
         }
     }
     matched
 }
 
-fn parse_trashinfo_path(path: &Path) -> Result<String, ()> {
-    let content = read_to_string(path).map_err(|_| ())?;
-    for line in content.lines() {
-        if let Some(rest) = line.strip_prefix("Path=") {
-            return urlencoding::decode(rest).map_err(|_| ()).map(|path| path.into_owned());
-        }
-    }
-    Err(())
-}
-
-fn join_volume_and_path(volume: &str, original_location: &str) -> String {
-    if original_location.starts_with('/') {
-        original_location.to_string()
-    } else if volume == "/" {
-        format!("/{original_location}")
-    } else if volume.ends_with('/') {
-        format!("{volume}{original_location}")
-    } else {
-        format!("{volume}/{original_location}")
-    }
-}
-
-fn path_matches(pattern: &str, original_location: &str) -> bool {
-    let subject = if pattern.starts_with('/') {
-        original_location.to_string()
-    } else {
-        Path::new(original_location)
-            .file_name()
-            .and_then(|name| name.to_str())
-            .unwrap_or_default()
-            .to_string()
-    };
-
-    let pattern = CString::new(pattern).ok();
-    let subject = CString::new(subject).ok();
-    match (pattern, subject) {
-        (Some(pattern), Some(subject)) => unsafe {
-            libc::fnmatch(pattern.as_ptr(), subject.as_ptr(), 0) == 0
-        },
-        _ => false,
-    }
-}
-
-fn backup_copy_for_trashinfo(trashinfo_path: &Path) -> PathBuf {
-    let basename = trashinfo_path
-        .file_name()
-        .and_then(|name| name.to_str())
-        .unwrap_or_default()
-        .trim_end_matches(".trashinfo");
-    trashinfo_path
-        .parent()
-        .and_then(|parent| parent.parent())
-        .unwrap_or_else(|| Path::new(""))
-        .join("files")
-        .join(basename)
+fn parse_trashinfo_path(path: &Path) -> Result<(PathBuf, Option<DateTime<Utc>>), String> {
+    let content = read_to_string(path).map_err(|err| err.to_string())?;
+    let info = TrashInfo::parse(&content).map_err(|err| err.to_string())?;
+    let deleted_at = info.deleted_at();
+    Ok((info.path, deleted_at))
 }
 
 fn rm_file_if_exists(path: &Path) -> io::Result<()> {
@@ -235,22 +581,9 @@ fn rm_file_if_exists(path: &Path) -> io::Result<()> {
 fn rm_file2(path: &Path) -> io::Result<()> {
     match fs::remove_file(path) {
         Ok(()) => Ok(()),
-        Err(err) => match fs::remove_dir_all(path) {
+        Err(err) => match RealFileSystem.remove_dir_all_no_follow(path) {
             Ok(()) => Ok(()),
             Err(_) => Err(err),
         },
     }
 }
-
-fn is_symlink(path: &Path) -> bool {
-    fs::symlink_metadata(path)
-        .ok()
-        .is_some_and(|metadata| metadata.file_type().is_symlink())
-}
-
-fn is_sticky_dir(path: &Path) -> bool {
-    fs::metadata(path)
-        .ok()
-        .map(|metadata| metadata.is_dir() && metadata.permissions().mode() & 0o1000 == 0o1000)
-        .unwrap_or(false)
-}
@@ -0,0 +1,251 @@
+//! Entry points for applications that use the trash in-process (file
+//! managers, editors, TUIs) instead of running the commands.
+//!
+//! They follow the same specification-compliant logic as the binaries.
+//! This module is the stable surface of the crate: its functions only
+//! change signature with a major version, and the option structs are
+//! `#[non_exhaustive]`, so they are built from `Default` and new fields can
+//! be added in minor versions.
+//!
+//! ```no_run
+//! use trash_cli_core::api::{list_trashed_items, ListOptions};
+//! use trash_cli_core::RealEnvironment;
+//!
+//! for item in list_trashed_items(&RealEnvironment, &ListOptions::default()) {
+//!     match item {
+//!         Ok(item) => println!("{}", item.original_path.display()),
+//!         Err(err) => eprintln!("{}", err),
+//!     }
+//! }
+//! ```
+//!
+//! ```no_run
+//! use trash_cli_core::api::{trash, TrashOptions};
+//!
+//! let report = trash(["notes.txt", "build"], &TrashOptions::default());
+//! if report.failed() > 0 {
+//!     eprintln!("{} files could not be trashed", report.failed());
+//! }
+//! ```
+
+use crate::environment::{Environment, RealEnvironment};
+use crate::errors::CoreError;
+use crate::fs::{recursive_size, FileSystem, RealFileSystem};
+use crate::directory_sizes::forget_directory_size;
+use crate::index::TrashIndex;
+use crate::journal::{Journal, JournalItem, Operation, Record};
+use crate::log;
+use crate::models::{CommandKind, CommandOutcome, CommandOutput, OperationReport, TrashDirectory, TrashedItem};
+use crate::mounts::{list_mount_points, list_volumes, NetworkFilter};
+use crate::put::Trasher;
+use crate::restore::{Restored, Restorer};
+use crate::trash_dirs::{scan_all_users_trash_dirs, scan_trash_dirs, user_specified_trash_dirs, TrashDirEvent};
+use crate::trashinfo::{entry_name, resolve_original_location, TrashInfo};
+use crate::users::SystemUsers;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+pub use crate::put::TrashOptions;
+pub use crate::restore::{Conflict, RestoreOptions};
+
+/// The file system the entry points work on.
+const FS: &RealFileSystem = &RealFileSystem;
+
+/// What [`list_trashed_items`] reads.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct ListOptions {
+    /// Trash dirs to read; when empty, the home trash and the top-dir
+    /// trashes of every volume are discovered, as trash-list does.
+    pub trash_dirs: Vec<PathBuf>,
+    /// Discover the trash dirs of every user instead of the current one.
+    pub all_users: bool,
+    /// Network filesystems scanned by discovery.
+    pub network: NetworkFilter,
+    /// Read trashinfo files through the index of each trash dir (see
+    /// [`crate::index`]).
+    pub use_index: bool,
+    /// Fill in [`TrashedItem::size_bytes`], which walks trashed directories.
+    pub with_sizes: bool,
+}
+
+/// The items of the trash dirs selected by `options`, one trash dir after
+/// the other.
+///
+/// Trash dirs are only read as the iterator advances. A trashinfo that
+/// cannot be read or has no `Path=` yields an error and the iteration goes
+/// on with the next one; trash dirs that do not exist yield nothing.
+pub fn list_trashed_items<'a>(
+    environ: &'a dyn Environment,
+    options: &'a ListOptions,
+) -> impl Iterator<Item = crate::Result<TrashedItem>> + 'a {
+    trash_dirs(environ, options)
+        .into_iter()
+        .flat_map(move |trash_dir| items_of(trash_dir, options))
+}
+
+/// Moves `paths` to the trash, each into the first trash dir of its volume
+/// that accepts it, exactly as trash-put does.
+///
+/// The report holds one outcome per path, in order: a completed one with the
+/// messages trash-put would print, or a skipped one for a missing path with
+/// [`TrashOptions::force`]. With [`TrashOptions::print`], the stdout of a
+/// trashed file is its new location in the trash. Then
+/// [`TrashOptions::quota`] is enforced, each failure to purge adding a
+/// failed outcome.
+pub fn trash<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>, options: &TrashOptions) -> OperationReport {
+    let trasher = Trasher::new(FS, &RealEnvironment, options);
+    let mut report = OperationReport::new(CommandKind::Put);
+    for path in paths {
+        report.record(trasher.trash_outcome(path.as_ref()));
+    }
+    for err in trasher.enforce_quota().into_iter().filter_map(Result::err) {
+        report.record(CommandOutcome::Failed {
+            command: CommandKind::Put,
+            reason: err.to_string(),
+        });
+    }
+    report
+}
+
+/// Moves `item`, as listed by [`list_trashed_items`], back to its original
+/// location (or into [`RestoreOptions::to`]) and removes its trashinfo, as
+/// trash-restore does. Missing parent directories are recreated.
+///
+/// An existing destination is handled by [`RestoreOptions::conflict`]; with
+/// the default [`Conflict::Fail`] it is a [`CoreError::Conflict`].
+pub fn restore(item: &TrashedItem, options: &RestoreOptions) -> crate::Result<Restored> {
+    Restorer::new(FS, &RealEnvironment, options).restore(item)
+}
+
+/// Permanently removes `item` from the trash: its trashed copy, without
+/// following symlinks inside trashed directories, then its trashinfo.
+///
+/// A trashed copy that is already gone is not an error, so broken items can
+/// be purged too.
+pub fn purge(item: &TrashedItem) -> crate::Result<()> {
+    purge_with(FS, &Journal::open(&RealEnvironment), item)
+}
+
+/// [`purge`] on `fs`, recorded in `journal`.
+pub(crate) fn purge_with(fs: &dyn FileSystem, journal: &Journal, item: &TrashedItem) -> crate::Result<()> {
+    let trashinfo = fs.read_to_string(&item.info_path).unwrap_or_default();
+    if fs.symlink_metadata(&item.trashed_path).is_ok() {
+        fs.remove_dir_all_no_follow(&item.trashed_path)?;
+    }
+    fs.remove_file(&item.info_path)?;
+    let name = item.trashed_path.file_name().unwrap_or_default();
+    TrashIndex::update(fs, &item.trash_dir, |index| {
+        index.remove(name);
+    });
+    let _ = forget_directory_size(fs, &item.trash_dir, name);
+    journal.record(
+        fs,
+        &Record::Item(JournalItem {
+            operation: Operation::Empty,
+            trash_dir: std::path::absolute(&item.trash_dir).unwrap_or_else(|_| item.trash_dir.clone()),
+            name: name.to_os_string(),
+            path: PathBuf::new(),
+            trashinfo,
+        }),
+    );
+    Ok(())
+}
+
+/// Purges every item of the trash dirs selected by `options`, as
+/// trash-empty does without arguments.
+///
+/// The report holds one outcome per item: completed when it was purged,
+/// failed with the error otherwise, including trashinfo files that could
+/// not be read.
+pub fn empty(environ: &dyn Environment, options: &ListOptions) -> OperationReport {
+    let mut report = OperationReport::new(CommandKind::Empty);
+    for item in list_trashed_items(environ, options) {
+        let outcome = match item.and_then(|item| purge(&item)) {
+            Ok(()) => CommandOutcome::Completed(CommandOutput::success(CommandKind::Empty, Vec::new())),
+            Err(err) => CommandOutcome::Failed {
+                command: CommandKind::Empty,
+                reason: err.to_string(),
+            },
+        };
+        report.record(outcome);
+    }
+    report
+}
+
+/// The trash dirs `options` selects, as found on disk.
+fn trash_dirs(environ: &dyn Environment, options: &ListOptions) -> Vec<TrashDirectory> {
+    let events = if !options.trash_dirs.is_empty() {
+        user_specified_trash_dirs(&options.trash_dirs, &list_mount_points())
+    } else if options.all_users {
+        scan_all_users_trash_dirs(FS, &SystemUsers, &list_volumes(environ, &options.network))
+    } else {
+        scan_trash_dirs(FS, environ, environ.uid(), &list_volumes(environ, &options.network))
+    };
+    events
+        .into_iter()
+        .filter_map(|event| match event {
+            TrashDirEvent::Found(trash_dir) => Some(trash_dir),
+            TrashDirEvent::Skipped(..) => None,
+        })
+        .collect()
+}
+
+fn items_of(
+    trash_dir: TrashDirectory,
+    options: &ListOptions,
+) -> Box<dyn Iterator<Item = crate::Result<TrashedItem>> + '_> {
+    if !FS.exists(&trash_dir.info_dir) {
+        return Box::new(std::iter::empty());
+    }
+    log::debug("api", format_args!("listing {}", trash_dir.path.display()));
+    let trashinfos: Trashinfos = match options.use_index {
+        true => match TrashIndex::refresh(FS, &trash_dir.path) {
+            Ok(indexed) => Box::new(indexed.into_iter().map(|item| Ok((item.path, item.content)))),
+            Err(err) => return Box::new(std::iter::once(Err(err))),
+        },
+        false => match FS.read_dir_iter(&trash_dir.info_dir) {
+            Ok(entries) => Box::new(entries.map(|entry| {
+                let path = entry?.path;
+                let content = FS.read_to_string(&path);
+                Ok((path, content))
+            })),
+            Err(err) => return Box::new(std::iter::once(Err(err))),
+        },
+    };
+    Box::new(trashinfos.filter_map(move |entry| {
+        let (info_path, content) = match entry {
+            Ok(entry) => entry,
+            Err(err) => return Some(Err(err)),
+        };
+        let name = entry_name(&info_path)?;
+        Some(content.and_then(|content| item(&trash_dir, name, info_path, &content, options.with_sizes)))
+    }))
+}
+
+/// `.trashinfo` files and their contents.
+type Trashinfos = Box<dyn Iterator<Item = crate::Result<(PathBuf, crate::Result<String>)>>>;
+
+fn item(
+    trash_dir: &TrashDirectory,
+    name: OsString,
+    info_path: PathBuf,
+    content: &str,
+    with_sizes: bool,
+) -> crate::Result<TrashedItem> {
+    let info = TrashInfo::parse(content)
+        .map_err(|err| CoreError::invalid_input(format!("{}: {}", info_path.display(), err)))?;
+    let volume = trash_dir.mount_point.clone().unwrap_or_else(|| PathBuf::from("/"));
+    let trashed_path = trash_dir.files_dir.join(name);
+    let mut item = TrashedItem::new(
+        resolve_original_location(&volume, &info.path),
+        trashed_path,
+        info_path,
+        trash_dir.path.clone(),
+    );
+    item.deleted_at = info.deleted_at();
+    if with_sizes {
+        item.size_bytes = recursive_size(FS, &item.trashed_path).ok();
+    }
+    Ok(item)
+}
@@ -0,0 +1,121 @@
+//! Consistency check of a trash directory: every `files/NAME` should have
+//! an `info/NAME.trashinfo` and the other way round, and every trashinfo
+//! should be well formed. Crashes halfway through a put, rm or empty, and
+//! tools that touch the trash by hand, break these pairs.
+
+use crate::fs::FileSystem;
+use crate::helpers::{parse_trash_datetime, TRASHINFO_EXTENSION};
+use crate::trashinfo::{path_bytes, path_from_bytes, TrashInfo};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// A problem found in a trash directory.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AuditIssue {
+    /// `files/NAME` has no `info/NAME.trashinfo`.
+    OrphanBackup { backup: PathBuf },
+    /// `info/NAME.trashinfo` has no `files/NAME`.
+    MissingBackup { trashinfo: PathBuf, backup: PathBuf },
+    /// A trashinfo that cannot be read or does not follow the format.
+    Malformed { trashinfo: PathBuf, reason: String },
+}
+
+impl AuditIssue {
+    /// Short name of the kind of issue.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::OrphanBackup { .. } => "orphan-backup",
+            Self::MissingBackup { .. } => "missing-backup",
+            Self::Malformed { .. } => "malformed-trashinfo",
+        }
+    }
+
+    /// The file the issue is about.
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::OrphanBackup { backup } => backup,
+            Self::MissingBackup { trashinfo, .. } | Self::Malformed { trashinfo, .. } => trashinfo,
+        }
+    }
+}
+
+impl fmt::Display for AuditIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OrphanBackup { backup } => write!(f, "{}: no trashinfo", backup.display()),
+            Self::MissingBackup { trashinfo, backup } => {
+                write!(f, "{}: backup {} is missing", trashinfo.display(), backup.display())
+            }
+            Self::Malformed { trashinfo, reason } => write!(f, "{}: {}", trashinfo.display(), reason),
+        }
+    }
+}
+
+/// The issues of one trash directory.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct TrashAudit {
+    pub trash_dir: PathBuf,
+    pub issues: Vec<AuditIssue>,
+}
+
+impl TrashAudit {
+    /// Checks `trash_dir`; a missing `files/` or `info/` counts as empty.
+    pub fn run(fs: &dyn FileSystem, trash_dir: &Path) -> crate::Result<Self> {
+        let files_dir = trash_dir.join("files");
+        let info_dir = trash_dir.join("info");
+        let mut issues = Vec::new();
+
+        for trashinfo in list_sorted(fs, &info_dir)? {
+            let file_name = path_bytes(Path::new(trashinfo.file_name().unwrap_or_default())).into_owned();
+            let Some(name) = file_name.strip_suffix(TRASHINFO_EXTENSION.as_bytes()) else {
+                // Not a trashinfo, and nothing to pair with a backup.
+                continue;
+            };
+            let checked = fs.read_to_string(&trashinfo).map_err(|err| err.to_string());
+            if let Err(reason) = checked.and_then(|content| check(&content)) {
+                issues.push(AuditIssue::Malformed {
+                    trashinfo: trashinfo.clone(),
+                    reason,
+                });
+            }
+            let backup = files_dir.join(path_from_bytes(name.to_vec()));
+            if fs.symlink_metadata(&backup).is_err() {
+                issues.push(AuditIssue::MissingBackup { trashinfo, backup });
+            }
+        }
+        for backup in list_sorted(fs, &files_dir)? {
+            let mut file_name = backup.file_name().unwrap_or_default().to_os_string();
+            file_name.push(TRASHINFO_EXTENSION);
+            if fs.symlink_metadata(&info_dir.join(file_name)).is_err() {
+                issues.push(AuditIssue::OrphanBackup { backup });
+            }
+        }
+        Ok(Self {
+            trash_dir: trash_dir.to_path_buf(),
+            issues,
+        })
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+fn list_sorted(fs: &dyn FileSystem, dir: &Path) -> crate::Result<Vec<PathBuf>> {
+    if !fs.exists(dir) {
+        return Ok(Vec::new());
+    }
+    let mut paths = fs.list_dir(dir)?;
+    paths.sort();
+    Ok(paths)
+}
+
+/// Why `content` is not a valid trashinfo, if it is not.
+fn check(content: &str) -> Result<(), String> {
+    let info = TrashInfo::parse(content).map_err(|err| err.to_string())?;
+    let deletion_date = info.deletion_date.as_deref().ok_or("missing DeletionDate")?;
+    if parse_trash_datetime(deletion_date).is_none() {
+        return Err(format!("invalid DeletionDate '{}'", deletion_date));
+    }
+    Ok(())
+}
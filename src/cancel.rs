@@ -0,0 +1,75 @@
+//! Cooperative cancellation of long operations (trashing many files,
+//! emptying a large trash, copying a tree to another device).
+//!
+//! Operations check a [`CancellationToken`] between items and stop cleanly:
+//! the item at hand is either completed or rolled back, so an interrupt
+//! never leaves a trashinfo without its file. The commands cancel their
+//! token on `SIGINT`/`SIGTERM` and then exit with
+//! [`crate::exit_codes::INTERRUPTED`].
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Once};
+
+/// Set by the signal handler, read by tokens made by
+/// [`CancellationToken::on_signals`].
+static SIGNALLED: AtomicBool = AtomicBool::new(false);
+
+/// Asks the operations holding a clone of it to stop.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    signals: bool,
+}
+
+impl CancellationToken {
+    /// A token that is only cancelled through [`CancellationToken::cancel`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token that is also cancelled by `SIGINT` and `SIGTERM`, which no
+    /// longer terminate the process. A second signal does, as the handler
+    /// restores the default action once it has run.
+    pub fn on_signals() -> Self {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| {
+            for signal in [libc::SIGINT, libc::SIGTERM] {
+                // SAFETY: the handler only stores to an atomic and calls
+                // `signal`, which are async-signal-safe.
+                unsafe {
+                    libc::signal(signal, handle_signal as extern "C" fn(libc::c_int) as libc::sighandler_t);
+                }
+            }
+        });
+        CancellationToken {
+            cancelled: Arc::default(),
+            signals: true,
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst) || (self.signals && SIGNALLED.load(Ordering::SeqCst))
+    }
+
+    /// An [`io::ErrorKind::Interrupted`] error once cancelled, for
+    /// operations that stop with `?`.
+    pub fn check(&self) -> io::Result<()> {
+        match self.is_cancelled() {
+            true => Err(io::Error::new(io::ErrorKind::Interrupted, "interrupted")),
+            false => Ok(()),
+        }
+    }
+}
+
+extern "C" fn handle_signal(signal: libc::c_int) {
+    SIGNALLED.store(true, Ordering::SeqCst);
+    // SAFETY: restoring the default action is async-signal-safe.
+    unsafe {
+        libc::signal(signal, libc::SIG_DFL);
+    }
+}
@@ -0,0 +1,707 @@
+//! Command-line parsing shared by the commands, following Python's
+//! `argparse` closely enough that usage, help and error output match the
+//! Python implementation: `--` ends the options, long options may be
+//! abbreviated to a unique prefix, short flags can be clustered (`-vf`),
+//! values are given as `--opt VALUE`, `--opt=VALUE` or `-oVALUE`, and
+//! usage errors exit with status 2.
+//!
+//! Parsing only recognizes the arguments; each command folds the returned
+//! [`Match`]es into its own options, in command-line order.
+//!
+//! The same definitions drive `--print-completion` (see [`completion`]).
+
+pub mod completion;
+
+pub use completion::Shell;
+
+use crate::config::{ColorChoice, Config};
+use crate::environment::RealEnvironment;
+use crate::exit_codes;
+use crate::fs::RealFileSystem;
+use crate::helpers::{expand_path, parse_size, SizeFormat};
+use crate::i18n::{tr, tr_format};
+use crate::models::CommandKind;
+use crate::trashinfo::path_from_bytes;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// Exit status of a usage error, as with argparse.
+pub const USAGE_EXIT_CODE: i32 = exit_codes::USAGE as i32;
+
+/// Total width of usage and help text (argparse's default for an 80 column
+/// terminal).
+const TEXT_WIDTH: usize = 78;
+
+/// Column where option help starts when the invocations leave room for it.
+const MAX_HELP_POSITION: usize = 24;
+
+/// How many values a positional argument takes.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Nargs {
+    One,
+    /// `?`: zero or one.
+    Optional,
+    /// `*`
+    ZeroOrMore,
+    /// `+`
+    OneOrMore,
+}
+
+/// What the shell should offer for a value.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum Complete {
+    /// Nothing (free text such as a number or a pattern).
+    #[default]
+    Nothing,
+    File,
+    Directory,
+}
+
+/// One option or positional argument.
+#[derive(Debug, Clone, Copy)]
+pub struct Arg {
+    /// Name the command matches on; for positionals also the name shown.
+    pub dest: &'static str,
+    /// Option strings such as `-v` and `--verbose`; empty for a positional.
+    pub flags: &'static [&'static str],
+    /// Placeholder of the value, for options that take one.
+    pub metavar: Option<&'static str>,
+    /// Accepted values; shown as `{a,b}` instead of the metavar.
+    pub choices: &'static [&'static str],
+    pub nargs: Nargs,
+    pub help: &'static str,
+    /// Accepted but left out of usage and help, like `argparse.SUPPRESS`.
+    pub hidden: bool,
+    /// Consecutive options of one group are shown as `[--a | --b]`.
+    pub group: Option<u8>,
+    /// Completion of the value, unless there are `choices`.
+    pub complete: Complete,
+}
+
+impl Arg {
+    /// An option without a value.
+    pub const fn flag(dest: &'static str, flags: &'static [&'static str], help: &'static str) -> Self {
+        Arg {
+            dest,
+            flags,
+            metavar: None,
+            choices: &[],
+            nargs: Nargs::One,
+            help,
+            hidden: false,
+            group: None,
+            complete: Complete::Nothing,
+        }
+    }
+
+    /// An option taking one value.
+    pub const fn option(
+        dest: &'static str,
+        flags: &'static [&'static str],
+        metavar: &'static str,
+        help: &'static str,
+    ) -> Self {
+        Arg {
+            metavar: Some(metavar),
+            ..Arg::flag(dest, flags, help)
+        }
+    }
+
+    pub const fn positional(dest: &'static str, nargs: Nargs, help: &'static str) -> Self {
+        Arg {
+            nargs,
+            ..Arg::flag(dest, &[], help)
+        }
+    }
+
+    pub const fn choices(mut self, choices: &'static [&'static str]) -> Self {
+        self.choices = choices;
+        self
+    }
+
+    pub const fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+
+    pub const fn group(mut self, group: u8) -> Self {
+        self.group = Some(group);
+        self
+    }
+
+    pub const fn complete(mut self, complete: Complete) -> Self {
+        self.complete = complete;
+        self
+    }
+
+    fn is_positional(&self) -> bool {
+        self.flags.is_empty()
+    }
+
+    fn takes_value(&self) -> bool {
+        self.metavar.is_some()
+    }
+
+    /// Name used in error messages: `-t/--trash-dir`, or the positional's.
+    fn display_name(&self) -> String {
+        if self.is_positional() {
+            self.dest.to_string()
+        } else {
+            self.flags.join("/")
+        }
+    }
+
+    fn value_name(&self) -> String {
+        if self.choices.is_empty() {
+            self.metavar.unwrap_or(self.dest).to_string()
+        } else {
+            format!("{{{}}}", self.choices.join(","))
+        }
+    }
+
+    fn usage_part(&self) -> String {
+        let name = self.value_name();
+        match (self.is_positional(), self.nargs) {
+            (false, _) if self.takes_value() => format!("{} {}", self.flags[0], name),
+            (false, _) => self.flags[0].to_string(),
+            (true, Nargs::One) => name,
+            (true, Nargs::Optional) => format!("[{}]", name),
+            (true, Nargs::ZeroOrMore) => format!("[{} ...]", name),
+            (true, Nargs::OneOrMore) => format!("{0} [{0} ...]", name),
+        }
+    }
+
+    /// `-t TRASH_DIR, --trash-dir TRASH_DIR` as shown in the help.
+    fn invocation(&self) -> String {
+        if self.is_positional() {
+            return self.value_name();
+        }
+        self.flags
+            .iter()
+            .map(|flag| match self.takes_value() {
+                true => format!("{} {}", flag, self.value_name()),
+                false => flag.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// `--print-completion SHELL`, offered by every command.
+pub const PRINT_COMPLETION_ARG: Arg =
+    Arg::option("print_completion", &["--print-completion"], "SHELL", "print shell completion script")
+        .choices(&Shell::NAMES);
+
+/// `--ignore-case`, for commands that match original paths against glob
+/// patterns (see [`crate::glob::MatchOptions::casefold`]).
+pub const IGNORE_CASE_ARG: Arg =
+    Arg::flag("ignore_case", &["--ignore-case"], "match patterns regardless of the case of letters");
+
+/// `--color WHEN`, for commands with colored output (see
+/// [`crate::terminal`]); it overrides `color` in the config file.
+pub const COLOR_ARG: Arg = Arg::option(
+    "color",
+    &["--color"],
+    "WHEN",
+    "color the output: auto (on a terminal, unless NO_COLOR is set), always or never",
+)
+.choices(&ColorChoice::NAMES);
+
+/// `--version`, offered by every command (see [`crate::version`]).
+pub const VERSION_ARG: Arg = Arg::flag("version", &["--version"], "show program's version number and exit");
+
+/// `--debug-env`, offered by every command: prints the environment
+/// variables it honors (see [`crate::environment::ENV_VARS`]).
+pub const DEBUG_ENV_ARG: Arg = Arg::flag(
+    "debug_env",
+    &["--debug-env"],
+    "print the environment variables that affect this command and exit",
+);
+
+/// `--files-from FILE`, for commands that take many paths: reads them
+/// from FILE (see [`files_from`]) after those given as arguments.
+pub const FILES_FROM_ARG: Arg = Arg::option(
+    "files_from",
+    &["--files-from"],
+    "FILE",
+    "also read the files from FILE, one per line ('-' reads standard input)",
+)
+.complete(Complete::File);
+
+/// `-0, --null`, which goes with [`FILES_FROM_ARG`].
+pub const NULL_ARG: Arg = Arg::flag(
+    "null",
+    &["-0", "--null"],
+    "names in the --files-from list end with NUL instead of newline, as printed by find -print0",
+);
+
+/// `--si`, `-b/--bytes` and `--block-size SIZE` choose the
+/// [`SizeFormat`] of the sizes a command prints; see [`parse_block_size`].
+pub const SI_ARG: Arg = Arg::flag("si", &["--si"], "print sizes in powers of 1000, like 1.5 KB, instead of 1024");
+pub const BYTES_ARG: Arg = Arg::flag("bytes", &["-b", "--bytes"], "print sizes in bytes");
+pub const BLOCK_SIZE_ARG: Arg = Arg::option(
+    "block_size",
+    &["--block-size"],
+    "SIZE",
+    "print sizes as a number of SIZE-byte blocks (like 1K or 1M), rounded up as du does",
+);
+
+/// The value of [`BLOCK_SIZE_ARG`].
+pub fn parse_block_size(value: &str) -> Result<SizeFormat, UsageError> {
+    match parse_size(value) {
+        Some(block_size) if block_size > 0 => Ok(SizeFormat::Blocks(block_size)),
+        _ => Err(UsageError(format!("argument --block-size: invalid size value: '{}'", value))),
+    }
+}
+
+/// A `--trash-dir` value with `~` and `$VAR` expanded (see
+/// [`expand_path`]); shells leave them alone in `--trash-dir=~/Trash`.
+pub fn trash_dir_arg(value: OsString) -> PathBuf {
+    expand_path(Path::new(&value), &RealEnvironment)
+}
+
+/// The paths listed in `source` (`-` is stdin), each terminated by
+/// `separator` except maybe the last, read as the iterator advances. Empty
+/// entries are skipped; names are taken byte for byte.
+pub fn files_from(source: &Path, separator: u8) -> io::Result<impl Iterator<Item = io::Result<PathBuf>>> {
+    let reader: Box<dyn BufRead> = match source == Path::new("-") {
+        true => Box::new(io::stdin().lock()),
+        false => Box::new(BufReader::new(File::open(source)?)),
+    };
+    Ok(reader.split(separator).filter_map(|entry| match entry {
+        Ok(name) if name.is_empty() => None,
+        Ok(name) => Some(Ok(path_from_bytes(name))),
+        Err(err) => Some(Err(err)),
+    }))
+}
+
+/// An argument recognized on the command line.
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub dest: &'static str,
+    /// The option string it was given as (resolved from an abbreviation);
+    /// empty for positionals.
+    pub option: &'static str,
+    pub value: Option<OsString>,
+}
+
+impl Match {
+    /// The value as text, lossily converted.
+    pub fn text(&self) -> String {
+        self.value
+            .as_ref()
+            .map(|value| value.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+/// Result of a successful parse.
+#[derive(Debug, Clone)]
+pub enum Parsed {
+    /// `-h`/`--help` was given; nothing after it was looked at.
+    Help,
+    Args(Vec<Match>),
+}
+
+/// A usage error, reported as `prog: error: message` below the usage.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UsageError(pub String);
+
+impl std::fmt::Display for UsageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The definition of a command line: its arguments plus the texts around
+/// them in `--help`. `-h, --help` is always present.
+#[derive(Debug, Clone)]
+pub struct Parser {
+    pub prog: String,
+    pub description: &'static str,
+    /// Replaces the generated usage after `usage: `.
+    pub usage: Option<&'static str>,
+    pub args: Vec<Arg>,
+    /// Printed verbatim after the options.
+    pub epilog: &'static str,
+}
+
+const HELP_ARG: Arg = Arg::flag("help", &["-h", "--help"], "show this help message and exit");
+
+impl Parser {
+    pub fn new(prog: impl Into<String>, description: &'static str, args: &[Arg]) -> Self {
+        let mut all = vec![HELP_ARG];
+        all.extend_from_slice(args);
+        Parser {
+            prog: prog.into(),
+            description,
+            usage: None,
+            args: all,
+            epilog: "",
+        }
+    }
+
+    pub fn with_usage(mut self, usage: &'static str) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    pub fn with_epilog(mut self, epilog: &'static str) -> Self {
+        self.epilog = epilog;
+        self
+    }
+
+    /// Recognizes `args` (without the program name). Unknown options and
+    /// surplus positionals are reported together at the end, after missing
+    /// required positionals, as argparse does.
+    pub fn parse(&self, args: &[OsString]) -> Result<Parsed, UsageError> {
+        let mut matches = Vec::new();
+        let mut positionals: Vec<OsString> = Vec::new();
+        let mut extras: Vec<String> = Vec::new();
+        let mut only_positionals = false;
+        // As in argparse, a negative number is a value unless some option
+        // looks like one (`-0`).
+        let numeric_options = self.args.iter().flat_map(|arg| arg.flags).any(|flag| !is_option_like(flag, false));
+        let is_option_like = |text: &str| is_option_like(text, numeric_options);
+        let mut args = args.iter().peekable();
+        while let Some(arg) = args.next() {
+            let text = match arg.to_str() {
+                Some(text) if !only_positionals && is_option_like(text) => text,
+                _ => {
+                    positionals.push(arg.clone());
+                    continue;
+                }
+            };
+            if text == "--" {
+                only_positionals = true;
+                continue;
+            }
+
+            let mut next_value = || match args.peek() {
+                Some(next) if !next.to_str().is_some_and(&is_option_like) => args.next().cloned(),
+                _ => None,
+            };
+            if let Some(long) = text.strip_prefix("--") {
+                let (name, inline) = match long.split_once('=') {
+                    Some((name, value)) => (name, Some(OsString::from(value))),
+                    None => (long, None),
+                };
+                let Some((arg, option)) = self.find_long(name)? else {
+                    extras.push(text.to_string());
+                    continue;
+                };
+                if arg.dest == HELP_ARG.dest {
+                    return Ok(Parsed::Help);
+                }
+                let value = match (arg.takes_value(), inline) {
+                    (true, Some(value)) => Some(value),
+                    (true, None) => Some(next_value().ok_or_else(|| expected_one_argument(arg))?),
+                    (false, Some(value)) => {
+                        return Err(UsageError(tr_format(
+                            "argument {}: ignored explicit argument '{}'",
+                            &[&arg.display_name(), &value.to_string_lossy()],
+                        )))
+                    }
+                    (false, None) => None,
+                };
+                matches.push(self.checked(arg, option, value)?);
+                continue;
+            }
+
+            // A cluster of short flags; the first one taking a value takes
+            // the rest of the cluster (or the next argument) as its value.
+            let mut cluster = Vec::new();
+            let mut unknown = false;
+            for (offset, ch) in text.char_indices().skip(1) {
+                let Some((arg, option)) = self.find_short(ch) else {
+                    unknown = true;
+                    break;
+                };
+                if arg.dest == HELP_ARG.dest {
+                    return Ok(Parsed::Help);
+                }
+                if !arg.takes_value() {
+                    cluster.push((arg, option, None));
+                    continue;
+                }
+                let rest = &text[offset + ch.len_utf8()..];
+                let rest = rest.strip_prefix('=').unwrap_or(rest);
+                let value = match rest.is_empty() {
+                    true => next_value().ok_or_else(|| expected_one_argument(arg))?,
+                    false => OsString::from(rest),
+                };
+                cluster.push((arg, option, Some(value)));
+                break;
+            }
+            if unknown {
+                extras.push(text.to_string());
+                continue;
+            }
+            for (arg, option, value) in cluster {
+                matches.push(self.checked(arg, option, value)?);
+            }
+        }
+
+        let mut missing = Vec::new();
+        let mut positionals = positionals.into_iter().peekable();
+        for arg in self.args.iter().filter(|arg| arg.is_positional()) {
+            let taken: Vec<OsString> = match arg.nargs {
+                Nargs::One | Nargs::Optional => positionals.next().into_iter().collect(),
+                Nargs::ZeroOrMore | Nargs::OneOrMore => positionals.by_ref().collect(),
+            };
+            if taken.is_empty() && matches!(arg.nargs, Nargs::One | Nargs::OneOrMore) {
+                missing.push(arg.value_name());
+            }
+            for value in taken {
+                matches.push(self.checked(arg, "", Some(value))?);
+            }
+        }
+        if !missing.is_empty() {
+            return Err(UsageError(tr_format(
+                "the following arguments are required: {}",
+                &[&missing.join(", ")],
+            )));
+        }
+        extras.extend(positionals.map(|value| value.to_string_lossy().into_owned()));
+        if !extras.is_empty() {
+            return Err(UsageError(tr_format("unrecognized arguments: {}", &[&extras.join(" ")])));
+        }
+        Ok(Parsed::Args(matches))
+    }
+
+    /// Like [`Parser::parse`], with the `[command]` defaults of `config`
+    /// taken as given before `args`. An option given in `args` replaces all
+    /// of its defaults.
+    pub fn parse_with_config(
+        &self,
+        config: &Config,
+        command: CommandKind,
+        args: &[OsString],
+    ) -> Result<Parsed, UsageError> {
+        let defaults = match self.parse(&config.command_args(command)) {
+            Ok(Parsed::Args(matches)) => matches,
+            Ok(Parsed::Help) => Vec::new(),
+            Err(error) => {
+                let file = config.path.as_ref().map(|path| path.display().to_string()).unwrap_or_default();
+                return Err(UsageError(format!("{} [{}]: {}", file, command, error)));
+            }
+        };
+        let matches = match self.parse(args)? {
+            Parsed::Help => return Ok(Parsed::Help),
+            Parsed::Args(matches) => matches,
+        };
+        let mut all: Vec<Match> = defaults
+            .into_iter()
+            .filter(|default| !matches.iter().any(|found| found.dest == default.dest))
+            .collect();
+        all.extend(matches);
+        Ok(Parsed::Args(all))
+    }
+
+    /// The option whose long name is `name` or, failing that, the only one
+    /// it abbreviates.
+    fn find_long(&self, name: &str) -> Result<Option<(&Arg, &'static str)>, UsageError> {
+        let longs = || {
+            self.args
+                .iter()
+                .flat_map(|arg| arg.flags.iter().map(move |flag| (arg, *flag)))
+                .filter(|(_, flag)| flag.starts_with("--"))
+        };
+        if let Some(found) = longs().find(|(_, flag)| flag[2..] == *name) {
+            return Ok(Some(found));
+        }
+        let candidates: Vec<(&Arg, &'static str)> = longs().filter(|(_, flag)| flag[2..].starts_with(name)).collect();
+        match candidates.as_slice() {
+            [] => Ok(None),
+            [found] => Ok(Some(*found)),
+            _ => Err(UsageError(tr_format(
+                "ambiguous option: --{} could match {}",
+                &[&name, &candidates.iter().map(|(_, flag)| *flag).collect::<Vec<_>>().join(", ")],
+            ))),
+        }
+    }
+
+    fn find_short(&self, ch: char) -> Option<(&Arg, &'static str)> {
+        self.args.iter().find_map(|arg| {
+            arg.flags
+                .iter()
+                .find(|flag| flag.len() == 2 && flag.starts_with('-') && flag[1..].starts_with(ch))
+                .map(|flag| (arg, *flag))
+        })
+    }
+
+    fn checked(&self, arg: &Arg, option: &'static str, value: Option<OsString>) -> Result<Match, UsageError> {
+        if let Some(value) = &value {
+            let text = value.to_string_lossy();
+            if !arg.choices.is_empty() && !arg.choices.contains(&text.as_ref()) {
+                let choices = arg.choices.iter().map(|choice| format!("'{}'", choice)).collect::<Vec<_>>();
+                return Err(UsageError(tr_format(
+                    "argument {}: invalid choice: '{}' (choose from {})",
+                    &[&arg.display_name(), &text, &choices.join(", ")],
+                )));
+            }
+        }
+        Ok(Match {
+            dest: arg.dest,
+            option,
+            value,
+        })
+    }
+
+    /// The `usage:` line(s), wrapped like argparse.
+    pub fn format_usage(&self) -> String {
+        let prefix = tr("usage: ");
+        if let Some(usage) = self.usage {
+            return format!("{}{} {}", prefix, self.prog, usage);
+        }
+        let visible: Vec<&Arg> = self.args.iter().filter(|arg| !arg.hidden).collect();
+        let mut parts = Vec::new();
+        let options = visible.iter().filter(|arg| !arg.is_positional());
+        let mut previous_group = None;
+        for arg in options {
+            match arg.group {
+                Some(group) if previous_group == Some(group) => {
+                    let last: &mut String = parts.last_mut().expect("group continues a part");
+                    last.truncate(last.len() - 1);
+                    last.push_str(&format!(" | {}]", arg.usage_part()));
+                }
+                _ => parts.push(format!("[{}]", arg.usage_part())),
+            }
+            previous_group = arg.group;
+        }
+        parts.extend(visible.iter().filter(|arg| arg.is_positional()).map(|arg| arg.usage_part()));
+
+        let single_line = format!("{}{} {}", prefix, self.prog, parts.join(" "));
+        if single_line.len() <= TEXT_WIDTH {
+            return single_line;
+        }
+        // Continuation lines line up with the first part after the prog.
+        let mut lines = Vec::new();
+        let mut line = format!("{}{}", prefix, self.prog);
+        let mut line_has_part = false;
+        for part in parts {
+            if line_has_part && line.len() + 1 + part.len() > TEXT_WIDTH {
+                lines.push(line);
+                line = " ".repeat(prefix.chars().count() + self.prog.chars().count());
+            }
+            line.push(' ');
+            line.push_str(&part);
+            line_has_part = true;
+        }
+        lines.push(line);
+        lines.join("\n")
+    }
+
+    /// The full `--help` text.
+    pub fn format_help(&self) -> String {
+        let visible: Vec<&Arg> = self.args.iter().filter(|arg| !arg.hidden).collect();
+        let widest = visible.iter().map(|arg| arg.invocation().len() + 2).max().unwrap_or(0);
+        let help_position = (widest + 2).min(MAX_HELP_POSITION);
+
+        let mut sections = vec![self.format_usage()];
+        if !self.description.is_empty() {
+            sections.push(tr(self.description).to_string());
+        }
+        let positionals: Vec<&Arg> = visible.iter().copied().filter(|arg| arg.is_positional()).collect();
+        if !positionals.is_empty() {
+            sections.push(format_section(tr("positional arguments:"), &positionals, help_position));
+        }
+        let options: Vec<&Arg> = visible.iter().copied().filter(|arg| !arg.is_positional()).collect();
+        sections.push(format_section(tr("options:"), &options, help_position));
+        if !self.epilog.is_empty() {
+            sections.push(tr(self.epilog).to_string());
+        }
+        sections.join("\n\n")
+    }
+
+    /// The usage followed by `prog: error: message`, as printed on stderr.
+    pub fn format_error(&self, message: &str) -> String {
+        format!("{}\n{}", self.format_usage(), tr_format("{}: error: {}", &[&self.prog, &message]))
+    }
+
+    /// Prints [`Parser::format_error`] and exits with status 2.
+    pub fn exit_with_error(&self, message: &str) -> ! {
+        eprintln!("{}", self.format_error(message));
+        std::process::exit(USAGE_EXIT_CODE)
+    }
+}
+
+/// The user's [`Config`]; when it cannot be read, prints `prog: error: ...`
+/// and exits with the error's exit code.
+pub fn load_config(prog: &str) -> Config {
+    match Config::load(&RealFileSystem, &RealEnvironment) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{}: error: {}", prog, error);
+            std::process::exit(i32::from(error.exit_code()))
+        }
+    }
+}
+
+/// Whether `text` is an option rather than a value: it starts with `-`,
+/// is not `-` alone and, unless `numbers` is set, is not a negative number.
+fn is_option_like(text: &str, numbers: bool) -> bool {
+    if !text.starts_with('-') || text.len() == 1 {
+        return false;
+    }
+    if numbers {
+        return true;
+    }
+    let number = &text[1..];
+    let is_number = match number.split_once('.') {
+        Some((whole, fraction)) => {
+            whole.bytes().all(|byte| byte.is_ascii_digit())
+                && !fraction.is_empty()
+                && fraction.bytes().all(|byte| byte.is_ascii_digit())
+        }
+        None => number.bytes().all(|byte| byte.is_ascii_digit()),
+    };
+    !is_number
+}
+
+fn expected_one_argument(arg: &Arg) -> UsageError {
+    UsageError(tr_format("argument {}: expected one argument", &[&arg.display_name()]))
+}
+
+fn format_section(title: &str, args: &[&Arg], help_position: usize) -> String {
+    let action_width = help_position - 4;
+    let mut lines = vec![title.to_string()];
+    for arg in args {
+        let invocation = arg.invocation();
+        let help = wrap(tr(arg.help), TEXT_WIDTH - help_position);
+        let indent = " ".repeat(help_position);
+        let mut help = help.iter();
+        if arg.help.is_empty() {
+            lines.push(format!("  {}", invocation));
+        } else if invocation.len() <= action_width {
+            let first = help.next().map(String::as_str).unwrap_or_default();
+            lines.push(format!("  {:<width$}  {}", invocation, first, width = action_width));
+        } else {
+            lines.push(format!("  {}", invocation));
+        }
+        lines.extend(help.map(|line| format!("{}{}", indent, line)));
+    }
+    lines.join("\n")
+}
+
+/// Greedy word wrap of `text` (whitespace collapsed) into lines of at most
+/// `width` characters.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
@@ -0,0 +1,323 @@
+//! Completion scripts generated from a [`Parser`], printed by
+//! `--print-completion SHELL` in place of trash-cli's shtab output.
+//!
+//! Every visible option is offered; the value of an option (and the
+//! positionals) is completed from its `choices`, or as a file or directory
+//! according to [`Arg::complete`]. Hidden options are left out.
+
+use super::{Arg, Complete, Nargs, Parser};
+
+/// A shell `--print-completion` can generate a script for.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Tcsh,
+}
+
+impl Shell {
+    /// Names accepted on the command line, in help order.
+    pub const NAMES: [&'static str; 4] = ["bash", "zsh", "fish", "tcsh"];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "bash" => Some(Self::Bash),
+            "zsh" => Some(Self::Zsh),
+            "fish" => Some(Self::Fish),
+            "tcsh" => Some(Self::Tcsh),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Bash => "bash",
+            Self::Zsh => "zsh",
+            Self::Fish => "fish",
+            Self::Tcsh => "tcsh",
+        }
+    }
+}
+
+impl Parser {
+    /// The completion script of this command line for `shell`, ending with
+    /// a newline.
+    pub fn format_completion(&self, shell: Shell) -> String {
+        match shell {
+            Shell::Bash => self.bash_completion(),
+            Shell::Zsh => self.zsh_completion(),
+            Shell::Fish => self.fish_completion(),
+            Shell::Tcsh => self.tcsh_completion(),
+        }
+    }
+
+    fn visible_options(&self) -> impl Iterator<Item = &Arg> {
+        self.args.iter().filter(|arg| !arg.hidden && !arg.is_positional())
+    }
+
+    /// The first visible positional; its completion is used for every word
+    /// that is not an option or an option's value.
+    fn first_positional(&self) -> Option<&Arg> {
+        self.args.iter().find(|arg| !arg.hidden && arg.is_positional())
+    }
+
+    /// `_trash_put` for `trash-put`.
+    fn function_name(&self) -> String {
+        let name: String = self
+            .prog
+            .chars()
+            .map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '_' })
+            .collect();
+        format!("_{}", name)
+    }
+
+    fn bash_completion(&self) -> String {
+        let mut script = vec![
+            format!("# bash completion for {0}, generated by `{0} --print-completion bash`", self.prog),
+            format!("{}() {{", self.function_name()),
+            "    local cur prev".to_string(),
+            "    COMPREPLY=()".to_string(),
+            "    cur=\"${COMP_WORDS[COMP_CWORD]}\"".to_string(),
+            "    prev=\"${COMP_WORDS[COMP_CWORD-1]}\"".to_string(),
+            String::new(),
+            "    case \"$prev\" in".to_string(),
+        ];
+        for arg in self.visible_options().filter(|arg| arg.takes_value()) {
+            script.push(format!("        {})", arg.flags.join("|")));
+            script.extend(bash_reply(arg).map(|line| format!("            {}", line)));
+            script.push("            return 0".to_string());
+            script.push("            ;;".to_string());
+        }
+        script.push("    esac".to_string());
+        script.push(String::new());
+
+        let flags: Vec<&str> = self.visible_options().flat_map(|arg| arg.flags.iter().copied()).collect();
+        script.push("    if [[ \"$cur\" == -* ]]; then".to_string());
+        script.push(format!("        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))", flags.join(" ")));
+        script.push("        return 0".to_string());
+        script.push("    fi".to_string());
+        if let Some(positional) = self.first_positional() {
+            script.extend(bash_reply(positional).map(|line| format!("    {}", line)));
+        }
+        script.push("}".to_string());
+        script.push(String::new());
+        script.push(format!("complete -o filenames -F {} {}", self.function_name(), self.prog));
+        script.push(String::new());
+        script.join("\n")
+    }
+
+    fn zsh_completion(&self) -> String {
+        let mut specs = Vec::new();
+        for arg in self.visible_options() {
+            // An option excludes its other spellings and the rest of its
+            // group.
+            let excluded: Vec<&str> = self
+                .visible_options()
+                .filter(|other| other.dest == arg.dest || (arg.group.is_some() && other.group == arg.group))
+                .flat_map(|other| other.flags.iter().copied())
+                .collect();
+            let exclusions = match arg.dest == super::HELP_ARG.dest {
+                true => "(- *)".to_string(),
+                false if excluded.len() > 1 => format!("({})", excluded.join(" ")),
+                false => String::new(),
+            };
+            // `-t+`/`--trash-dir=`: the value may follow in the same word.
+            let flags: Vec<String> = arg
+                .flags
+                .iter()
+                .map(|flag| match (arg.takes_value(), flag.starts_with("--")) {
+                    (false, _) => flag.to_string(),
+                    (true, true) => format!("{}=", flag),
+                    (true, false) => format!("{}+", flag),
+                })
+                .collect();
+            let mut spec = match arg.help.is_empty() {
+                true => String::new(),
+                false => format!("[{}]", zsh_escape(&collapse(arg.help))),
+            };
+            if arg.takes_value() {
+                spec.push_str(&format!(":{}:{}", zsh_escape(arg.metavar.unwrap_or(arg.dest)), zsh_action(arg)));
+            }
+            specs.push(match flags.as_slice() {
+                [flag] => format!("'{}{}{}'", exclusions, flag, spec),
+                _ if exclusions.is_empty() => format!("{{{}}}'{}'", flags.join(","), spec),
+                _ => format!("'{}'{{{}}}'{}'", exclusions, flags.join(","), spec),
+            });
+        }
+        for arg in self.args.iter().filter(|arg| !arg.hidden && arg.is_positional()) {
+            let position = match arg.nargs {
+                Nargs::One => "",
+                Nargs::Optional => ":",
+                Nargs::ZeroOrMore | Nargs::OneOrMore => "*",
+            };
+            specs.push(format!(
+                "'{}:{}:{}'",
+                position,
+                zsh_escape(arg.metavar.unwrap_or(arg.dest)),
+                zsh_action(arg)
+            ));
+        }
+
+        let function = self.function_name();
+        let mut script = vec![
+            format!("#compdef {}", self.prog),
+            String::new(),
+            format!("# zsh completion for {0}, generated by `{0} --print-completion zsh`", self.prog),
+            format!("{}() {{", function),
+            "  _arguments -s -S \\".to_string(),
+        ];
+        let last = specs.len().saturating_sub(1);
+        for (index, spec) in specs.iter().enumerate() {
+            let continuation = if index == last { "" } else { " \\" };
+            script.push(format!("    {}{}", spec, continuation));
+        }
+        script.extend([
+            "}".to_string(),
+            String::new(),
+            "if [[ $zsh_eval_context[-1] == loadautofunc ]]; then".to_string(),
+            format!("  {} \"$@\"", function),
+            "else".to_string(),
+            format!("  compdef {} {}", function, self.prog),
+            "fi".to_string(),
+            String::new(),
+        ]);
+        script.join("\n")
+    }
+
+    fn fish_completion(&self) -> String {
+        let command = format!("complete -c {}", self.prog);
+        let mut script = vec![format!(
+            "# fish completion for {0}, generated by `{0} --print-completion fish`",
+            self.prog
+        )];
+        match self.first_positional() {
+            Some(arg) if !arg.choices.is_empty() || arg.complete == Complete::Directory => {
+                script.push(format!("{} -f -a {}", command, fish_values(arg)));
+            }
+            Some(arg) if arg.complete == Complete::File => {}
+            _ => script.push(format!("{} -f", command)),
+        }
+        for arg in self.visible_options() {
+            let mut line = command.clone();
+            for flag in arg.flags {
+                match flag.strip_prefix("--") {
+                    Some(long) => line.push_str(&format!(" -l {}", long)),
+                    None => line.push_str(&format!(" -s {}", &flag[1..])),
+                }
+            }
+            if arg.takes_value() {
+                match (arg.choices.is_empty(), arg.complete) {
+                    (true, Complete::File) => line.push_str(" -r -F"),
+                    (true, Complete::Nothing) => line.push_str(" -x"),
+                    _ => line.push_str(&format!(" -x -a {}", fish_values(arg))),
+                }
+            }
+            if !arg.help.is_empty() {
+                line.push_str(&format!(" -d {}", fish_quote(&collapse(arg.help))));
+            }
+            script.push(line);
+        }
+        script.push(String::new());
+        script.join("\n")
+    }
+
+    fn tcsh_completion(&self) -> String {
+        let longs: Vec<&str> = self
+            .visible_options()
+            .flat_map(|arg| arg.flags.iter().filter_map(|flag| flag.strip_prefix("--")))
+            .collect();
+        let mut shorts: Vec<&str> = self
+            .visible_options()
+            .flat_map(|arg| arg.flags.iter().filter(|flag| !flag.starts_with("--")).map(|flag| &flag[1..]))
+            .collect();
+        // `-` lets `--` go on to the long options.
+        shorts.push("-");
+
+        let mut words = vec![
+            format!("'c/--/({})/'", longs.join(" ")),
+            format!("'c/-/({})/'", shorts.join(" ")),
+        ];
+        for arg in self.visible_options().filter(|arg| arg.takes_value()) {
+            for flag in arg.flags {
+                words.push(format!("'n/{}/{}/'", flag, tcsh_action(arg)));
+            }
+        }
+        if let Some(positional) = self.first_positional() {
+            words.push(format!("'p/*/{}/'", tcsh_action(positional)));
+        }
+        format!(
+            "# tcsh completion for {0}, generated by `{0} --print-completion tcsh`\ncomplete {0} \\\n    {1}\n",
+            self.prog,
+            words.join(" \\\n    ")
+        )
+    }
+}
+
+/// The lines setting `COMPREPLY` for the value of `arg`.
+fn bash_reply(arg: &Arg) -> impl Iterator<Item = String> {
+    let lines = match (arg.choices.is_empty(), arg.complete) {
+        (false, _) => vec![format!("COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))", arg.choices.join(" "))],
+        (true, Complete::File) => vec![
+            "local IFS=$'\\n'".to_string(),
+            "COMPREPLY=($(compgen -f -- \"$cur\"))".to_string(),
+        ],
+        (true, Complete::Directory) => vec![
+            "local IFS=$'\\n'".to_string(),
+            "COMPREPLY=($(compgen -d -- \"$cur\"))".to_string(),
+        ],
+        (true, Complete::Nothing) => Vec::new(),
+    };
+    lines.into_iter()
+}
+
+fn zsh_action(arg: &Arg) -> String {
+    match (arg.choices.is_empty(), arg.complete) {
+        (false, _) => format!("({})", arg.choices.join(" ")),
+        (true, Complete::File) => "_files".to_string(),
+        (true, Complete::Directory) => "_files -/".to_string(),
+        (true, Complete::Nothing) => " ".to_string(),
+    }
+}
+
+/// Escapes `text` for a zsh `_arguments` spec inside single quotes.
+fn zsh_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\'' => escaped.push_str("'\\''"),
+            '[' | ']' | ':' | '\\' => {
+                escaped.push('\\');
+                escaped.push(ch);
+            }
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// The quoted `-a` argument offering the values of `arg`.
+fn fish_values(arg: &Arg) -> String {
+    match (arg.choices.is_empty(), arg.complete) {
+        (false, _) => fish_quote(&arg.choices.join(" ")),
+        _ => "'(__fish_complete_directories)'".to_string(),
+    }
+}
+
+fn fish_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn tcsh_action(arg: &Arg) -> String {
+    match (arg.choices.is_empty(), arg.complete) {
+        (false, _) => format!("({})", arg.choices.join(" ")),
+        (true, Complete::File) => "f".to_string(),
+        (true, Complete::Directory) => "d".to_string(),
+        (true, Complete::Nothing) => format!("x:<{}>", arg.metavar.unwrap_or(arg.dest)),
+    }
+}
+
+/// `text` on one line, with runs of whitespace collapsed.
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,553 @@
+//! User configuration read from `$XDG_CONFIG_HOME/trash-cli/config.toml`
+//! (`~/.config/trash-cli/config.toml` when unset). A missing file is the
+//! same as an empty one.
+//!
+//! ```toml
+//! # Trash dirs used by list, empty, restore and rm when no --trash-dir is given;
+//! # paths may start with ~ and use $VARIABLES (see helpers::expand_path).
+//! trash_dirs = ["/data/.Trash-1000", "~/.local/share/Trash"]
+//! # Paths trash-put refuses to trash without --force-protected, besides the built-in ones.
+//! protected_paths = ["/srv/www"]
+//! color = "auto"            # or "always", "never"
+//! index = true              # keep a per-trash-dir index for list and restore
+//! # Network filesystems scanned for trash dirs, which are skipped by default.
+//! include_network = ["nfs4", "fuse.sshfs"]
+//!
+//! [purge]                   # defaults of trash-empty
+//! days = 30                 # like `trash-empty 30`
+//! free = "10G"              # like --free 10G
+//! keep = 100                # like --keep 100
+//!
+//! [quota]                   # limits trash-put enforces, see crate::quota
+//! max_size = "10G"          # per trash dir
+//! max_age = "30d"
+//!
+//! [put]                     # per-command defaults: long option = value
+//! fsync = true
+//!
+//! [restore]
+//! sort = "path"
+//!
+//! [colors]                  # styles of --color, see crate::terminal
+//! missing = "bold red"
+//! ```
+//!
+//! Per-command tables hold long option names (`dry_run` or `dry-run`) with
+//! `true` for flags and a string, integer or array for options taking
+//! values. They act as if given before the command-line arguments, so
+//! options on the command line take precedence.
+//!
+//! Only the subset of TOML needed here is understood: tables, bare or
+//! quoted keys, strings, integers, booleans and arrays.
+
+use crate::environment::Environment;
+use crate::errors::{CoreError, ResultExt};
+use crate::fs::FileSystem;
+use crate::helpers::{expand_path, parse_human_duration, parse_size};
+use crate::models::CommandKind;
+use crate::quota::QuotaPolicy;
+use crate::terminal::{Role, Style, Theme};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// Location of the file below the config home.
+pub const CONFIG_FILE: &str = "trash-cli/config.toml";
+
+/// When to color output.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum ColorChoice {
+    /// Only when writing to a terminal.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Names accepted in the config file, in help order.
+    pub const NAMES: [&'static str; 3] = ["auto", "always", "never"];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "auto" => Some(Self::Auto),
+            "always" => Some(Self::Always),
+            "never" => Some(Self::Never),
+            _ => None,
+        }
+    }
+}
+
+/// Defaults for what trash-empty removes, from the `[purge]` table.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct PurgePolicy {
+    /// Only remove items trashed more than this many days ago.
+    pub days: Option<i64>,
+    /// Space to free on each volume, as given to `--free`.
+    pub free: Option<String>,
+    /// Most recently trashed items to keep in each trash dir.
+    pub keep: Option<usize>,
+}
+
+/// A value of the config file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Self::String(_) => "a string",
+            Self::Integer(_) => "an integer",
+            Self::Boolean(_) => "a boolean",
+            Self::Array(_) => "an array",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// File the configuration was read from, if there was one.
+    pub path: Option<PathBuf>,
+    pub trash_dirs: Vec<PathBuf>,
+    pub protected_paths: Vec<PathBuf>,
+    pub color: ColorChoice,
+    /// Styles of the colored output, from the `[colors]` table.
+    pub theme: Theme,
+    /// Whether list and restore read trashinfo files through the index of
+    /// each trash dir (see [`crate::index`]).
+    pub index: bool,
+    /// Network filesystem types scanned by trash dir discovery.
+    pub include_network: Vec<String>,
+    pub purge: PurgePolicy,
+    pub quota: QuotaPolicy,
+    /// Per-command option defaults, keyed by [`CommandKind::as_str`].
+    commands: BTreeMap<String, Vec<(String, Value)>>,
+}
+
+impl Config {
+    /// Path of the config file for `environ`, if a config home is known.
+    pub fn path_for(environ: &dyn Environment) -> Option<PathBuf> {
+        environ.config_home().map(|config_home| config_home.join(CONFIG_FILE))
+    }
+
+    /// Reads the user's config file; a missing file yields the defaults.
+    pub fn load(fs: &dyn FileSystem, environ: &dyn Environment) -> crate::Result<Self> {
+        let Some(path) = Self::path_for(environ) else {
+            return Ok(Self::default());
+        };
+        if !fs.exists(&path) {
+            return Ok(Self::default());
+        }
+        let mut config = fs
+            .read_to_string(&path)
+            .and_then(|text| Self::parse(&text))
+            .with_context(|| format!("in {}", path.display()))?;
+        config.path = Some(path);
+        for path in config.trash_dirs.iter_mut().chain(&mut config.protected_paths) {
+            *path = expand_path(path, environ);
+        }
+        Ok(config)
+    }
+
+    /// Parses the text of a config file.
+    pub fn parse(text: &str) -> crate::Result<Self> {
+        let mut config = Self::default();
+        for (table, key, value, line) in TomlParser::new(text).parse()? {
+            let at_line = |message: String| CoreError::invalid_input(format!("line {}: {}", line, message));
+            match table.as_deref() {
+                None => match key.as_str() {
+                    "trash_dirs" => config.trash_dirs = paths(&key, value).map_err(at_line)?,
+                    "protected_paths" => config.protected_paths = paths(&key, value).map_err(at_line)?,
+                    "color" => {
+                        let text = string(&key, value).map_err(at_line)?;
+                        config.color = ColorChoice::parse(&text).ok_or_else(|| {
+                            at_line(format!(
+                                "invalid color '{}' (choose from {})",
+                                text,
+                                ColorChoice::NAMES.join(", ")
+                            ))
+                        })?;
+                    }
+                    "index" => config.index = boolean(&key, value).map_err(at_line)?,
+                    "include_network" => config.include_network = strings(&key, value).map_err(at_line)?,
+                    _ => return Err(at_line(format!("unknown key '{}'", key))),
+                },
+                Some("purge") => match key.as_str() {
+                    "days" => config.purge.days = Some(integer(&key, value).map_err(at_line)?),
+                    "free" => {
+                        config.purge.free = Some(match value {
+                            Value::Integer(bytes) => bytes.to_string(),
+                            value => string(&key, value).map_err(at_line)?,
+                        });
+                    }
+                    "keep" => {
+                        let keep = integer(&key, value).map_err(at_line)?;
+                        let keep = usize::try_from(keep)
+                            .map_err(|_| at_line(format!("'keep' must not be negative, got {}", keep)))?;
+                        config.purge.keep = Some(keep);
+                    }
+                    _ => return Err(at_line(format!("unknown key '{}' in [purge]", key))),
+                },
+                Some("quota") => match key.as_str() {
+                    "max_size" => {
+                        let size = match value {
+                            Value::Integer(bytes) => u64::try_from(bytes).ok(),
+                            value => parse_size(&string(&key, value).map_err(at_line)?),
+                        };
+                        config.quota.max_size =
+                            Some(size.ok_or_else(|| at_line(format!("invalid size for '{}'", key)))?);
+                    }
+                    "max_age" => {
+                        let age = match value {
+                            Value::Integer(days) => u64::try_from(days).ok().map(|days| days.to_string()),
+                            value => Some(string(&key, value).map_err(at_line)?),
+                        };
+                        config.quota.max_age = Some(
+                            age.as_deref()
+                                .and_then(parse_human_duration)
+                                .ok_or_else(|| at_line(format!("invalid duration for '{}'", key)))?,
+                        );
+                    }
+                    _ => return Err(at_line(format!("unknown key '{}' in [quota]", key))),
+                },
+                Some("colors") => {
+                    let role = Role::parse(&key).ok_or_else(|| {
+                        at_line(format!("unknown key '{}' in [colors] (choose from {})", key, Role::NAMES.join(", ")))
+                    })?;
+                    let style = Style::parse(&string(&key, value).map_err(at_line)?).map_err(at_line)?;
+                    config.theme.set(role, style);
+                }
+                Some(command) if CommandKind::ALL.iter().any(|kind| kind.as_str() == command) => {
+                    check_option_value(&key, &value).map_err(at_line)?;
+                    config.commands.entry(command.to_string()).or_default().push((key, value));
+                }
+                Some(other) => return Err(at_line(format!("unknown table [{}]", other))),
+            }
+        }
+        Ok(config)
+    }
+
+    /// The `[command]` defaults as command-line arguments (`--sort=path`,
+    /// `--verbose`), to be parsed before the real ones.
+    pub fn command_args(&self, command: CommandKind) -> Vec<OsString> {
+        let mut args = Vec::new();
+        for (key, value) in self.commands.get(command.as_str()).into_iter().flatten() {
+            let option = format!("--{}", key.replace('_', "-"));
+            let values = match value {
+                Value::Array(values) => values.as_slice(),
+                value => std::slice::from_ref(value),
+            };
+            for value in values {
+                match value {
+                    Value::Boolean(true) => args.push(OsString::from(&option)),
+                    Value::Boolean(false) => {}
+                    Value::String(text) => args.push(OsString::from(format!("{}={}", option, text))),
+                    Value::Integer(number) => args.push(OsString::from(format!("{}={}", option, number))),
+                    Value::Array(_) => {}
+                }
+            }
+        }
+        args
+    }
+}
+
+fn string(key: &str, value: Value) -> Result<String, String> {
+    match value {
+        Value::String(text) => Ok(text),
+        other => Err(format!("'{}' must be a string, not {}", key, other.type_name())),
+    }
+}
+
+fn boolean(key: &str, value: Value) -> Result<bool, String> {
+    match value {
+        Value::Boolean(flag) => Ok(flag),
+        other => Err(format!("'{}' must be a boolean, not {}", key, other.type_name())),
+    }
+}
+
+fn integer(key: &str, value: Value) -> Result<i64, String> {
+    match value {
+        Value::Integer(number) => Ok(number),
+        other => Err(format!("'{}' must be an integer, not {}", key, other.type_name())),
+    }
+}
+
+/// A path or an array of paths.
+fn paths(key: &str, value: Value) -> Result<Vec<PathBuf>, String> {
+    strings(key, value).map(|values| values.into_iter().map(PathBuf::from).collect())
+}
+
+/// A string or an array of strings.
+fn strings(key: &str, value: Value) -> Result<Vec<String>, String> {
+    let values = match value {
+        Value::Array(values) => values,
+        value => vec![value],
+    };
+    values.into_iter().map(|value| string(key, value)).collect()
+}
+
+fn check_option_value(key: &str, value: &Value) -> Result<(), String> {
+    match value {
+        Value::Array(values) => match values.iter().find(|value| matches!(value, Value::Boolean(_) | Value::Array(_))) {
+            Some(value) => Err(format!("'{}' cannot hold {}", key, value.type_name())),
+            None => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// (table, key, value, line) of every assignment, in file order.
+type Assignments = Vec<(Option<String>, String, Value, usize)>;
+
+struct TomlParser {
+    chars: Vec<char>,
+    position: usize,
+    line: usize,
+}
+
+impl TomlParser {
+    fn new(text: &str) -> Self {
+        TomlParser {
+            chars: text.chars().collect(),
+            position: 0,
+            line: 1,
+        }
+    }
+
+    fn parse(mut self) -> crate::Result<Assignments> {
+        let mut assignments: Assignments = Vec::new();
+        let mut seen_tables = Vec::new();
+        let mut table: Option<String> = None;
+        loop {
+            self.skip_blank_lines();
+            let Some(ch) = self.peek() else {
+                return Ok(assignments);
+            };
+            if ch == '[' {
+                self.bump();
+                self.skip_spaces();
+                if self.peek() == Some('[') {
+                    return Err(self.error("arrays of tables are not supported"));
+                }
+                let name = self.key()?;
+                self.skip_spaces();
+                self.expect(']')?;
+                if seen_tables.contains(&name) {
+                    return Err(self.error(format!("table [{}] defined twice", name)));
+                }
+                seen_tables.push(name.clone());
+                table = Some(name);
+            } else {
+                let line = self.line;
+                let key = self.key()?;
+                self.skip_spaces();
+                if self.peek() == Some('.') {
+                    return Err(self.error("dotted keys are not supported"));
+                }
+                self.expect('=')?;
+                self.skip_spaces();
+                let value = self.value()?;
+                if assignments.iter().any(|(other_table, other_key, _, _)| *other_table == table && *other_key == key) {
+                    return Err(self.error(format!("key '{}' defined twice", key)));
+                }
+                assignments.push((table.clone(), key, value, line));
+            }
+            self.skip_spaces();
+            self.skip_comment();
+            match self.peek() {
+                None => {}
+                Some('\n') => self.bump(),
+                Some('\r') if self.chars.get(self.position + 1) == Some(&'\n') => {
+                    self.bump();
+                    self.bump();
+                }
+                Some(ch) => return Err(self.error(format!("unexpected '{}' at end of line", ch))),
+            }
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> CoreError {
+        CoreError::invalid_input(format!("line {}: {}", self.line, message.into()))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn bump(&mut self) {
+        if self.peek() == Some('\n') {
+            self.line += 1;
+        }
+        self.position += 1;
+    }
+
+    fn expect(&mut self, expected: char) -> crate::Result<()> {
+        match self.peek() {
+            Some(ch) if ch == expected => {
+                self.bump();
+                Ok(())
+            }
+            Some(ch) => Err(self.error(format!("expected '{}', found '{}'", expected, ch))),
+            None => Err(self.error(format!("expected '{}', found end of file", expected))),
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.bump();
+            }
+        }
+    }
+
+    fn skip_blank_lines(&mut self) {
+        loop {
+            self.skip_spaces();
+            self.skip_comment();
+            match self.peek() {
+                Some('\n' | '\r') => self.bump(),
+                _ => return,
+            }
+        }
+    }
+
+    fn key(&mut self) -> crate::Result<String> {
+        match self.peek() {
+            Some('"') => self.basic_string(),
+            Some('\'') => self.literal_string(),
+            _ => {
+                let start = self.position;
+                while matches!(self.peek(), Some(ch) if ch.is_ascii_alphanumeric() || ch == '_' || ch == '-') {
+                    self.bump();
+                }
+                if start == self.position {
+                    return Err(self.error("expected a key"));
+                }
+                Ok(self.chars[start..self.position].iter().collect())
+            }
+        }
+    }
+
+    fn value(&mut self) -> crate::Result<Value> {
+        match self.peek() {
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => self.array(),
+            Some('{') => Err(self.error("inline tables are not supported")),
+            Some(_) => {
+                let start = self.position;
+                while matches!(self.peek(), Some(ch) if ch.is_ascii_alphanumeric() || "+-_.:".contains(ch)) {
+                    self.bump();
+                }
+                let word: String = self.chars[start..self.position].iter().collect();
+                match word.as_str() {
+                    "true" => Ok(Value::Boolean(true)),
+                    "false" => Ok(Value::Boolean(false)),
+                    "" => Err(self.error("expected a value")),
+                    _ => word
+                        .replace('_', "")
+                        .parse::<i64>()
+                        .ok()
+                        .filter(|_| !word.starts_with('_') && !word.ends_with('_') && !word.contains("__"))
+                        .map(Value::Integer)
+                        .ok_or_else(|| self.error(format!("unsupported value '{}'", word))),
+                }
+            }
+            None => Err(self.error("expected a value")),
+        }
+    }
+
+    fn array(&mut self) -> crate::Result<Value> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+        loop {
+            self.skip_blank_lines();
+            if self.peek() == Some(']') {
+                self.bump();
+                return Ok(Value::Array(values));
+            }
+            values.push(self.value()?);
+            self.skip_blank_lines();
+            match self.peek() {
+                Some(',') => self.bump(),
+                Some(']') => {}
+                Some(ch) => return Err(self.error(format!("expected ',' or ']', found '{}'", ch))),
+                None => return Err(self.error("unterminated array")),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> crate::Result<String> {
+        self.expect('\'')?;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                Some('\'') => {
+                    self.bump();
+                    return Ok(text);
+                }
+                Some('\n') | None => return Err(self.error("unterminated string")),
+                Some(ch) => {
+                    text.push(ch);
+                    self.bump();
+                }
+            }
+        }
+    }
+
+    fn basic_string(&mut self) -> crate::Result<String> {
+        self.expect('"')?;
+        let mut text = String::new();
+        loop {
+            let Some(ch) = self.peek() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.bump();
+            match ch {
+                '"' => return Ok(text),
+                '\n' => return Err(self.error("unterminated string")),
+                '\\' => {
+                    let Some(escape) = self.peek() else {
+                        return Err(self.error("unterminated string"));
+                    };
+                    self.bump();
+                    match escape {
+                        'b' => text.push('\u{8}'),
+                        't' => text.push('\t'),
+                        'n' => text.push('\n'),
+                        'f' => text.push('\u{c}'),
+                        'r' => text.push('\r'),
+                        '"' => text.push('"'),
+                        '\\' => text.push('\\'),
+                        'u' => text.push(self.unicode_escape(4)?),
+                        'U' => text.push(self.unicode_escape(8)?),
+                        other => return Err(self.error(format!("invalid escape '\\{}'", other))),
+                    }
+                }
+                ch => text.push(ch),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self, digits: usize) -> crate::Result<char> {
+        let hex: String = self.chars.iter().skip(self.position).take(digits).collect();
+        let ch = u32::from_str_radix(&hex, 16)
+            .ok()
+            .filter(|_| hex.len() == digits)
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error(format!("invalid unicode escape '{}'", hex)))?;
+        self.position += digits;
+        Ok(ch)
+    }
+}
@@ -0,0 +1,162 @@
+//! Reader/writer for the `$trash/directorysizes` cache defined by the
+//! FreeDesktop trash specification (version 1.0).
+//!
+//! Each line has the form `SIZE MTIME PERCENT-ENCODED-NAME`, where `SIZE` is
+//! the total size in bytes of the trashed directory `files/NAME` and `MTIME`
+//! is the modification time (seconds since the epoch) of the matching
+//! `info/NAME.trashinfo`. An entry is only trusted while that mtime matches.
+//! Names are kept as raw bytes, so entries of non-UTF-8 names round-trip.
+
+use crate::errors::CoreError;
+use crate::fs::FileSystem;
+use crate::helpers::{percent_decode, percent_encode};
+use crate::trashinfo::{path_bytes, path_from_bytes};
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// File name of the cache inside a trash directory.
+pub const DIRECTORY_SIZES_FILE: &str = "directorysizes";
+
+/// A single cached directory size.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct DirectorySizeEntry {
+    pub size: u64,
+    pub mtime: u64,
+}
+
+/// In-memory view of a trash directory's `directorysizes` cache.
+#[derive(Debug, Clone, Default)]
+pub struct DirectorySizes {
+    entries: BTreeMap<OsString, DirectorySizeEntry>,
+}
+
+impl DirectorySizes {
+    /// Path of the cache file for `trash_dir`.
+    pub fn path_for(trash_dir: &Path) -> PathBuf {
+        trash_dir.join(DIRECTORY_SIZES_FILE)
+    }
+
+    /// Loads the cache of `trash_dir`; a missing file yields an empty cache.
+    ///
+    /// Malformed lines are ignored, as the spec requires readers to be lenient.
+    pub fn load(fs: &dyn FileSystem, trash_dir: &Path) -> crate::Result<Self> {
+        let path = Self::path_for(trash_dir);
+        if !fs.exists(&path) {
+            return Ok(Self::default());
+        }
+        Ok(Self::parse(&fs.read_to_string(&path)?))
+    }
+
+    /// Parses the textual cache format.
+    pub fn parse(content: &str) -> Self {
+        let mut entries = BTreeMap::new();
+        for line in content.lines() {
+            let mut fields = line.splitn(3, ' ');
+            let (Some(size), Some(mtime), Some(name)) = (fields.next(), fields.next(), fields.next()) else {
+                continue;
+            };
+            let (Ok(size), Ok(mtime)) = (size.parse::<u64>(), mtime.parse::<u64>()) else {
+                continue;
+            };
+            let name = path_from_bytes(percent_decode(name));
+            entries.insert(name.into_os_string(), DirectorySizeEntry { size, mtime });
+        }
+        Self { entries }
+    }
+
+    /// Renders the cache in the on-disk format.
+    pub fn render(&self) -> String {
+        self.entries
+            .iter()
+            .map(|(name, entry)| {
+                let name = percent_encode(&path_bytes(Path::new(name)), b"");
+                format!("{} {} {}\n", entry.size, entry.mtime, name)
+            })
+            .collect()
+    }
+
+    /// Writes the cache back atomically (temporary file + rename).
+    pub fn save(&self, fs: &dyn FileSystem, trash_dir: &Path) -> crate::Result<()> {
+        let path = Self::path_for(trash_dir);
+        let tmp = trash_dir.join(format!(".{DIRECTORY_SIZES_FILE}.tmp"));
+        fs.write_to_string(&tmp, &self.render())?;
+        fs.rename(&tmp, &path).inspect_err(|_| {
+            let _ = fs.remove_file(&tmp);
+        })
+    }
+
+    /// Returns the raw entry for `name`, regardless of freshness.
+    pub fn get(&self, name: &OsStr) -> Option<DirectorySizeEntry> {
+        self.entries.get(name).copied()
+    }
+
+    /// Returns the cached size of `name` if it is still valid for `info_path`.
+    pub fn fresh_size(&self, fs: &dyn FileSystem, name: &OsStr, info_path: &Path) -> Option<u64> {
+        let entry = self.get(name)?;
+        let mtime = trashinfo_mtime(fs, info_path).ok()?;
+        (entry.mtime == mtime).then_some(entry.size)
+    }
+
+    /// Records the size of the trashed directory `name`.
+    pub fn insert(&mut self, name: impl Into<OsString>, size: u64, mtime: u64) {
+        self.entries.insert(name.into(), DirectorySizeEntry { size, mtime });
+    }
+
+    /// Forgets the entry for `name`, returning whether it was present.
+    pub fn remove(&mut self, name: &OsStr) -> bool {
+        self.entries.remove(name).is_some()
+    }
+
+    /// Drops entries whose `files/NAME` no longer exists.
+    pub fn retain_existing(&mut self, fs: &dyn FileSystem, trash_dir: &Path) {
+        let files_dir = trash_dir.join("files");
+        self.entries.retain(|name, _| fs.exists(&files_dir.join(name)));
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Modification time of a trashinfo file in whole seconds since the epoch.
+pub fn trashinfo_mtime(fs: &dyn FileSystem, info_path: &Path) -> crate::Result<u64> {
+    let modified = fs
+        .metadata(info_path)?
+        .modified()
+        .map_err(|err| CoreError::io(info_path, err))?;
+    Ok(modified
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0))
+}
+
+/// Records `size` for the trashed directory `name` in `trash_dir`'s cache.
+///
+/// Intended for writers (trash-put) right after a directory has been moved
+/// into `files/` and its trashinfo written.
+pub fn record_directory_size(fs: &dyn FileSystem, trash_dir: &Path, name: &OsStr, size: u64) -> crate::Result<()> {
+    let mut trashinfo_name = name.to_os_string();
+    trashinfo_name.push(crate::TRASHINFO_EXTENSION);
+    let info_path = trash_dir.join("info").join(trashinfo_name);
+    let mtime = trashinfo_mtime(fs, &info_path)?;
+    let mut sizes = DirectorySizes::load(fs, trash_dir)?;
+    sizes.insert(name, size, mtime);
+    sizes.save(fs, trash_dir)
+}
+
+/// Removes the cache entry for `name` in `trash_dir`, if any.
+///
+/// Intended for purgers (trash-rm, trash-empty) and restore.
+pub fn forget_directory_size(fs: &dyn FileSystem, trash_dir: &Path, name: &OsStr) -> crate::Result<()> {
+    let mut sizes = DirectorySizes::load(fs, trash_dir)?;
+    if sizes.remove(name) {
+        sizes.save(fs, trash_dir)?;
+    }
+    Ok(())
+}
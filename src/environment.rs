@@ -0,0 +1,307 @@
+//! The process environment as seen by the commands: the variables that
+//! locate trash dirs (`HOME`, `XDG_DATA_HOME`, `TRASH_VOLUMES`) and the
+//! user id, behind a trait so trash dir resolution can run against a fixed
+//! environment.
+//!
+//! Every variable a command reads (or sets for the Python backend) is
+//! listed in [`ENV_VARS`] and read through a typed accessor of
+//! [`Environment`]; `--debug-env` prints them with their current values.
+
+use crate::models::CommandKind;
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+
+/// An environment variable honored by the commands.
+#[derive(Debug, Clone, Copy)]
+pub struct EnvVar {
+    pub name: &'static str,
+    /// Commands that read (or set) it.
+    pub commands: &'static [CommandKind],
+    pub description: &'static str,
+}
+
+pub const HOME: EnvVar = EnvVar {
+    name: "HOME",
+    commands: &CommandKind::ALL,
+    description: "home directory; locates the home trash and the config file",
+};
+
+pub const XDG_DATA_HOME: EnvVar = EnvVar {
+    name: "XDG_DATA_HOME",
+    commands: &CommandKind::ALL,
+    description: "the home trash is $XDG_DATA_HOME/Trash (default ~/.local/share/Trash)",
+};
+
+pub const XDG_CONFIG_HOME: EnvVar = EnvVar {
+    name: "XDG_CONFIG_HOME",
+    commands: &CommandKind::ALL,
+    description: "the config file is $XDG_CONFIG_HOME/trash-cli/config.toml (default ~/.config/...)",
+};
+
+pub const XDG_STATE_HOME: EnvVar = EnvVar {
+    name: "XDG_STATE_HOME",
+    commands: &[CommandKind::Put, CommandKind::Empty, CommandKind::Restore, CommandKind::Undo],
+    description: "the undo journal is $XDG_STATE_HOME/trash-cli/journal (default ~/.local/state/...)",
+};
+
+pub const TRASH_VOLUMES: EnvVar = EnvVar {
+    name: "TRASH_VOLUMES",
+    commands: &CommandKind::ALL,
+    description: "colon-separated volumes to look for top dir trash dirs on, instead of every mount point",
+};
+
+pub const TRASH_ENABLE_HOME_FALLBACK: EnvVar = EnvVar {
+    name: "TRASH_ENABLE_HOME_FALLBACK",
+    commands: &[CommandKind::Put],
+    description: "when 1, like --home-fallback",
+};
+
+pub const TRASH_DATE: EnvVar = EnvVar {
+    name: "TRASH_DATE",
+    commands: &[CommandKind::Empty],
+    description: "current time (YYYY-MM-DDTHH:MM:SS) used to compute the age of trashed items",
+};
+
+pub const TRASH_FAKE_UID_FOR_TESTING: EnvVar = EnvVar {
+    name: "TRASH_FAKE_UID_FOR_TESTING",
+    commands: &CommandKind::ALL,
+    description: "user id to use instead of the effective one; only meant for tests",
+};
+
+pub const TRASH_PUT_WRAPPER_NAME: EnvVar = EnvVar {
+    name: "TRASH_PUT_WRAPPER_NAME",
+    commands: &[CommandKind::Put],
+    description: "set for the Python backend: the name trash-put was invoked as",
+};
+
+pub const TRASH_EMPTY_WRAPPER_NAME: EnvVar = EnvVar {
+    name: "TRASH_EMPTY_WRAPPER_NAME",
+    commands: &[CommandKind::Empty],
+    description: "set for the Python backend: the name trash-empty was invoked as",
+};
+
+pub const TRASH_EMPTY_PYTHON_EXECUTABLE: EnvVar = EnvVar {
+    name: "TRASH_EMPTY_PYTHON_EXECUTABLE",
+    commands: &[CommandKind::Empty],
+    description: "Python interpreter running the features of trash-empty not ported yet",
+};
+
+pub const TRASH_LIST_PYTHON_EXECUTABLE: EnvVar = EnvVar {
+    name: "TRASH_LIST_PYTHON_EXECUTABLE",
+    commands: &[CommandKind::List],
+    description: "Python interpreter reported by trash-list --python",
+};
+
+pub const TRASH_RESTORE_PYTHON: EnvVar = EnvVar {
+    name: "TRASH_RESTORE_PYTHON",
+    commands: &[CommandKind::Restore],
+    description: "Python interpreter running the features of trash-restore not ported yet",
+};
+
+pub const LANGUAGE: EnvVar = EnvVar {
+    name: "LANGUAGE",
+    commands: &CommandKind::ALL,
+    description: "colon-separated languages to show messages in, most preferred first",
+};
+
+pub const LC_ALL: EnvVar = EnvVar {
+    name: "LC_ALL",
+    commands: &CommandKind::ALL,
+    description: "locale overriding LC_MESSAGES and LANG",
+};
+
+pub const LC_MESSAGES: EnvVar = EnvVar {
+    name: "LC_MESSAGES",
+    commands: &CommandKind::ALL,
+    description: "locale of the messages",
+};
+
+pub const LANG: EnvVar = EnvVar {
+    name: "LANG",
+    commands: &CommandKind::ALL,
+    description: "default locale",
+};
+
+pub const TRASH_CLI_LOCALEDIR: EnvVar = EnvVar {
+    name: "TRASH_CLI_LOCALEDIR",
+    commands: &CommandKind::ALL,
+    description: "directory of the message catalogs (default /usr/share/locale)",
+};
+
+pub const DBUS_SESSION_BUS_ADDRESS: EnvVar = EnvVar {
+    name: "DBUS_SESSION_BUS_ADDRESS",
+    commands: &[CommandKind::Put, CommandKind::Empty],
+    description: "session bus desktop notifications are sent on, when built with the notifications feature",
+};
+
+pub const NO_COLOR: EnvVar = EnvVar {
+    name: "NO_COLOR",
+    commands: &[CommandKind::List, CommandKind::Restore],
+    description: "when set and not empty, --color=auto prints no colors",
+};
+
+/// Every variable the commands honor.
+pub const ENV_VARS: &[EnvVar] = &[
+    HOME,
+    XDG_DATA_HOME,
+    XDG_CONFIG_HOME,
+    XDG_STATE_HOME,
+    TRASH_VOLUMES,
+    TRASH_ENABLE_HOME_FALLBACK,
+    TRASH_DATE,
+    TRASH_FAKE_UID_FOR_TESTING,
+    TRASH_PUT_WRAPPER_NAME,
+    TRASH_EMPTY_WRAPPER_NAME,
+    TRASH_EMPTY_PYTHON_EXECUTABLE,
+    TRASH_LIST_PYTHON_EXECUTABLE,
+    TRASH_RESTORE_PYTHON,
+    LANGUAGE,
+    LC_ALL,
+    LC_MESSAGES,
+    LANG,
+    TRASH_CLI_LOCALEDIR,
+    DBUS_SESSION_BUS_ADDRESS,
+    NO_COLOR,
+];
+
+/// The `--debug-env` dump: each variable `command` honors, its value (or
+/// `(unset)`) and what it does.
+pub fn describe_environment(environ: &dyn Environment, command: CommandKind) -> String {
+    let vars: Vec<&EnvVar> = ENV_VARS.iter().filter(|var| var.commands.contains(&command)).collect();
+    let mut lines = Vec::new();
+    for var in vars {
+        match environ.var(var.name) {
+            Some(value) => lines.push(format!("{}={}", var.name, value)),
+            None => lines.push(format!("{} (unset)", var.name)),
+        }
+        lines.push(format!("    {}", var.description));
+    }
+    lines.join("\n")
+}
+
+pub trait Environment: Send + Sync {
+    /// Value of the variable `name`, when set to valid Unicode.
+    fn var(&self, name: &str) -> Option<String>;
+
+    /// Effective user id the trash dirs belong to.
+    fn uid(&self) -> u32;
+
+    fn home(&self) -> Option<PathBuf> {
+        self.var(HOME.name).map(PathBuf::from)
+    }
+
+    fn xdg_data_home(&self) -> Option<PathBuf> {
+        self.var(XDG_DATA_HOME.name).map(PathBuf::from)
+    }
+
+    /// `$XDG_CONFIG_HOME`, or `~/.config` when it is unset or empty.
+    fn config_home(&self) -> Option<PathBuf> {
+        self.var(XDG_CONFIG_HOME.name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| self.home().map(|home| home.join(".config")))
+    }
+
+    /// `$XDG_STATE_HOME`, or `~/.local/state` when it is unset or empty.
+    fn state_home(&self) -> Option<PathBuf> {
+        self.var(XDG_STATE_HOME.name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| self.home().map(|home| home.join(".local/state")))
+    }
+
+    /// `$TRASH_VOLUMES` split on `:`, or `None` when it is unset or empty
+    /// (meaning every mount point, as in trash-cli).
+    fn trash_volumes(&self) -> Option<Vec<PathBuf>> {
+        let volumes = self.var(TRASH_VOLUMES.name)?;
+        if volumes.is_empty() {
+            return None;
+        }
+        Some(
+            volumes
+                .split(':')
+                .filter(|volume| !volume.is_empty())
+                .map(PathBuf::from)
+                .collect(),
+        )
+    }
+
+    /// Whether `TRASH_ENABLE_HOME_FALLBACK=1`.
+    fn home_fallback_enabled(&self) -> bool {
+        self.var(TRASH_ENABLE_HOME_FALLBACK.name).as_deref() == Some("1")
+    }
+
+    /// `$TRASH_DATE`, the current time as seen by trash-empty.
+    fn trash_date(&self) -> Option<String> {
+        self.var(TRASH_DATE.name)
+    }
+
+    /// The Python interpreter configured for `command`'s backend, if any.
+    fn python_executable(&self, command: CommandKind) -> Option<String> {
+        let var = match command {
+            CommandKind::Empty => TRASH_EMPTY_PYTHON_EXECUTABLE,
+            CommandKind::List => TRASH_LIST_PYTHON_EXECUTABLE,
+            CommandKind::Restore => TRASH_RESTORE_PYTHON,
+            CommandKind::Put | CommandKind::Remove | CommandKind::Undo => return None,
+        };
+        self.var(var.name)
+    }
+}
+
+/// The environment of the running process.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RealEnvironment;
+
+impl Environment for RealEnvironment {
+    fn var(&self, name: &str) -> Option<String> {
+        env::var(name).ok()
+    }
+
+    /// `geteuid()`, unless overridden by `$TRASH_FAKE_UID_FOR_TESTING`.
+    fn uid(&self) -> u32 {
+        if let Some(uid) = self.var(TRASH_FAKE_UID_FOR_TESTING.name).and_then(|uid| uid.parse().ok()) {
+            return uid;
+        }
+        effective_uid()
+    }
+}
+
+#[cfg(unix)]
+fn effective_uid() -> u32 {
+    // SAFETY: `geteuid` has no preconditions and cannot fail.
+    unsafe { libc::geteuid() }
+}
+
+#[cfg(not(unix))]
+fn effective_uid() -> u32 {
+    0
+}
+
+/// A fixed set of variables and a uid, e.g. for tests.
+#[derive(Debug, Default, Clone)]
+pub struct MapEnvironment {
+    vars: HashMap<String, String>,
+    uid: u32,
+}
+
+impl MapEnvironment {
+    pub fn new(vars: HashMap<String, String>, uid: u32) -> Self {
+        Self { vars, uid }
+    }
+
+    pub fn with_var(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.vars.insert(name.into(), value.into());
+        self
+    }
+}
+
+impl Environment for MapEnvironment {
+    fn var(&self, name: &str) -> Option<String> {
+        self.vars.get(name).cloned()
+    }
+
+    fn uid(&self) -> u32 {
+        self.uid
+    }
+}
@@ -1,3 +1,5 @@
+use crate::fs::has_immutable_flag;
+use crate::models::{ExitStatusLike, SkipReason};
 use std::{io, path::PathBuf};
 
 /// Shared error type used by all Rust command crates during migration.
@@ -7,6 +9,15 @@ pub enum CoreError {
     #[error("I/O error while accessing {0}")]
     Io(PathBuf, #[source] io::Error),
 
+    /// Writing failed because the filesystem is mounted read-only (`EROFS`).
+    #[error("read-only file system: {0}")]
+    ReadOnlyFilesystem(PathBuf, #[source] io::Error),
+
+    /// An `EPERM` caused by the immutable or append-only attribute of the
+    /// path or of its parent directory, the one named here.
+    #[error("immutable or append-only file: {0}")]
+    Immutable(PathBuf, #[source] io::Error),
+
     /// A path is invalid for the current operation.
     #[error("invalid path: {0}")]
     InvalidPath(String),
@@ -30,6 +41,10 @@ pub enum CoreError {
     /// Platform-specific behavior not available in this environment.
     #[error("unsupported platform behavior: {0}")]
     UnsupportedPlatform(String),
+
+    /// Another error, prefixed with the operation that was being attempted.
+    #[error("{0}: {1}")]
+    Context(String, #[source] Box<CoreError>),
 }
 
 impl CoreError {
@@ -45,10 +60,163 @@ impl CoreError {
         Self::Conflict(message.into())
     }
 
+    /// An I/O error on `path`, classified as [`CoreError::ReadOnlyFilesystem`]
+    /// or [`CoreError::Immutable`] when that explains it, so they get their
+    /// own message.
     pub fn io(path: impl Into<PathBuf>, error: io::Error) -> Self {
-        Self::Io(path.into(), error)
+        let path = path.into();
+        match error.raw_os_error() {
+            Some(libc::EROFS) => Self::ReadOnlyFilesystem(path, error),
+            Some(libc::EPERM) => {
+                let flagged = [Some(path.as_path()), path.parent()]
+                    .into_iter()
+                    .flatten()
+                    .find(|candidate| !candidate.as_os_str().is_empty() && has_immutable_flag(candidate));
+                match flagged {
+                    Some(flagged) => Self::Immutable(flagged.to_path_buf(), error),
+                    None => Self::Io(path, error),
+                }
+            }
+            _ => Self::Io(path, error),
+        }
+    }
+
+    /// The I/O error behind this one, below any added context.
+    pub fn io_error(&self) -> Option<&io::Error> {
+        match self.root_cause() {
+            Self::Io(_, err) | Self::ReadOnlyFilesystem(_, err) | Self::Immutable(_, err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Why an item cannot be trashed at all, when this error says so.
+    pub fn skip_reason(&self) -> Option<SkipReason> {
+        match self.root_cause() {
+            Self::ReadOnlyFilesystem(path, _) => Some(SkipReason::ReadOnlyFilesystem(path.clone())),
+            Self::Immutable(path, _) => Some(SkipReason::ImmutableFile(path.clone())),
+            _ => None,
+        }
+    }
+
+    /// Wraps this error with a description of the operation that failed,
+    /// e.g. "while writing trashinfo for foo"; the original error stays
+    /// reachable through `source()`.
+    pub fn context(self, message: impl Into<String>) -> Self {
+        Self::Context(message.into(), Box::new(self))
+    }
+
+    /// The innermost error, below any added context.
+    pub fn root_cause(&self) -> &CoreError {
+        match self {
+            Self::Context(_, inner) => inner.root_cause(),
+            other => other,
+        }
+    }
+
+    /// Category of this error, independent of its message and context.
+    pub fn kind(&self) -> ErrorCategory {
+        match self {
+            Self::Io(..) | Self::ReadOnlyFilesystem(..) | Self::Immutable(..) => ErrorCategory::Io,
+            Self::InvalidPath(_) => ErrorCategory::InvalidPath,
+            Self::MissingValue(_) => ErrorCategory::MissingValue,
+            Self::InvalidInput(_) => ErrorCategory::InvalidInput,
+            Self::PreconditionFailed(_) => ErrorCategory::PreconditionFailed,
+            Self::Conflict(_) => ErrorCategory::Conflict,
+            Self::UnsupportedPlatform(_) => ErrorCategory::UnsupportedPlatform,
+            Self::Context(_, inner) => inner.kind(),
+        }
+    }
+
+    /// Coarse status of a command that failed with this error.
+    pub fn exit_status(&self) -> ExitStatusLike {
+        ExitStatusLike::Error
+    }
+
+    /// Process exit code for a command that failed with this error.
+    pub fn exit_code(&self) -> u8 {
+        self.kind().code()
+    }
+}
+
+/// What went wrong, without the details; each category has a stable exit
+/// code taken from BSD `sysexits.h`, as trash-put has always used
+/// `EX_IOERR`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ErrorCategory {
+    Io,
+    InvalidPath,
+    MissingValue,
+    InvalidInput,
+    PreconditionFailed,
+    Conflict,
+    UnsupportedPlatform,
+}
+
+impl ErrorCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Io => "io",
+            Self::InvalidPath => "invalid-path",
+            Self::MissingValue => "missing-value",
+            Self::InvalidInput => "invalid-input",
+            Self::PreconditionFailed => "precondition-failed",
+            Self::Conflict => "conflict",
+            Self::UnsupportedPlatform => "unsupported-platform",
+        }
+    }
+
+    /// Stable exit code: `EX_IOERR` (74), `EX_DATAERR` (65), `EX_NOINPUT`
+    /// (66), `EX_USAGE` (64), `EX_CONFIG` (78), `EX_CANTCREAT` (73) and
+    /// `EX_UNAVAILABLE` (69) respectively.
+    pub const fn code(self) -> u8 {
+        match self {
+            Self::Io => 74,
+            Self::InvalidPath => 65,
+            Self::MissingValue => 66,
+            Self::InvalidInput => 64,
+            Self::PreconditionFailed => 78,
+            Self::Conflict => 73,
+            Self::UnsupportedPlatform => 69,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
     }
 }
 
 /// Shared result alias for the core crate.
 pub type Result<T> = std::result::Result<T, CoreError>;
+
+/// Adds operation context to the error of a [`Result`].
+pub trait ResultExt<T> {
+    fn context(self, message: impl Into<String>) -> Result<T>;
+
+    /// Like [`ResultExt::context`], building the message only on error.
+    fn with_context<M: Into<String>>(self, message: impl FnOnce() -> M) -> Result<T>;
+}
+
+impl<T> ResultExt<T> for Result<T> {
+    fn context(self, message: impl Into<String>) -> Result<T> {
+        self.map_err(|err| err.context(message))
+    }
+
+    fn with_context<M: Into<String>>(self, message: impl FnOnce() -> M) -> Result<T> {
+        self.map_err(|err| err.context(message()))
+    }
+}
+
+impl From<CoreError> for io::Error {
+    /// Recovers the underlying I/O error (keeping its kind and OS code) so
+    /// callers can keep matching on e.g. `EXDEV`, even below added context;
+    /// other variants become `ErrorKind::Other`.
+    fn from(error: CoreError) -> Self {
+        match error {
+            CoreError::Io(_, err) | CoreError::ReadOnlyFilesystem(_, err) | CoreError::Immutable(_, err) => err,
+            CoreError::Context(_, inner) => io::Error::from(*inner),
+            other => io::Error::other(other.to_string()),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! The exit codes of the commands, kept in one table so that scripts written
+//! against the Python implementation see the same statuses. Binaries and
+//! [`crate::OperationReport`] take their codes from here instead of
+//! spelling numbers out.
+
+use crate::models::CommandKind;
+
+/// Everything went fine.
+pub const SUCCESS: u8 = 0;
+/// Generic failure, e.g. trash-restore refusing to overwrite a file.
+pub const FAILURE: u8 = 1;
+/// The command line was not understood, as argparse exits.
+pub const USAGE: u8 = 2;
+/// trash-rm: some matched items could not be removed (not in Python).
+pub const PARTIAL_FAILURE: u8 = 2;
+/// trash-rm: no trashed item matched (not in Python).
+pub const NO_MATCH: u8 = 3;
+/// trash-rm without any PATTERN.
+pub const NO_PATTERN: u8 = 8;
+/// `EX_IOERR`: trash-put could not trash one of the files.
+pub const IO_ERROR: u8 = 74;
+/// Stopped by `SIGINT`/`SIGTERM` (see [`crate::cancel`]), as shells report
+/// a process killed by `SIGINT`.
+pub const INTERRUPTED: u8 = 130;
+
+/// The codes one command exits with.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ExitCodes {
+    /// Some of the requested work failed.
+    pub failure: u8,
+    /// The command line could not be parsed.
+    pub usage: u8,
+    /// Nothing to work on was given; only trash-rm tells it apart from a
+    /// usage error.
+    pub missing_operand: u8,
+}
+
+impl ExitCodes {
+    pub const fn of(command: CommandKind) -> Self {
+        match command {
+            CommandKind::Put => ExitCodes {
+                failure: IO_ERROR,
+                usage: USAGE,
+                missing_operand: USAGE,
+            },
+            CommandKind::Remove => ExitCodes {
+                failure: FAILURE,
+                usage: USAGE,
+                missing_operand: NO_PATTERN,
+            },
+            CommandKind::List | CommandKind::Empty | CommandKind::Restore | CommandKind::Undo => ExitCodes {
+                failure: FAILURE,
+                usage: USAGE,
+                missing_operand: USAGE,
+            },
+        }
+    }
+}
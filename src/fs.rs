@@ -1,9 +1,65 @@
+use crate::cancel::CancellationToken;
 use crate::errors::CoreError;
-use std::fs::{self, DirEntry, Metadata};
-use std::io;
+use crate::log;
+use crate::progress::{NoProgress, Progress};
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, DirEntry, FileType, Metadata, Permissions};
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
+/// One child of a directory, as streamed by [`FileSystem::read_dir_iter`].
+#[derive(Debug, Clone)]
+pub struct DirEntryInfo {
+    pub path: PathBuf,
+    /// Type of the entry itself (symlinks are not followed), taken from the
+    /// directory listing when the platform provides it.
+    pub file_type: Option<FileType>,
+}
+
+/// Capacity of a filesystem in bytes, as `df` reports it.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct SpaceUsage {
+    pub size: u64,
+    pub used: u64,
+    /// Free bytes unprivileged users can write, less than `size - used`
+    /// when blocks are reserved for root.
+    pub available: u64,
+}
+
+/// What [`recursive_size`] needs to know about one entry, symlinks not
+/// followed.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct SizeStat {
+    /// Apparent size in bytes.
+    pub size: u64,
+    pub is_dir: bool,
+    pub is_symlink: bool,
+    /// Device and inode of a directory, to visit it only once.
+    pub dir_key: Option<(u64, u64)>,
+}
+
+impl SizeStat {
+    fn of(metadata: &Metadata) -> Self {
+        SizeStat {
+            size: metadata.len(),
+            is_dir: metadata.is_dir(),
+            is_symlink: metadata.file_type().is_symlink(),
+            dir_key: inode_key(metadata).filter(|_| metadata.is_dir()),
+        }
+    }
+}
+
+impl DirEntryInfo {
+    pub fn file_name(&self) -> &OsStr {
+        self.path.file_name().unwrap_or_default()
+    }
+}
+
+/// Lazily produced directory entries.
+pub type DirEntries = Box<dyn Iterator<Item = crate::Result<DirEntryInfo>>>;
+
 /// Filesystem abstraction boundary for command implementations.
 ///
 /// Keeping this trait narrow makes it easy to write deterministic tests and
@@ -27,26 +83,130 @@ pub trait FileSystem: Send + Sync {
     /// Creates a directory.
     fn create_dir(&self, path: &Path) -> crate::Result<()>;
 
+    /// Creates a directory and all missing parent directories with `mode`
+    /// (less the umask), e.g. 0700 for trash dirs only their owner may
+    /// read. Directories that already exist keep their permissions.
+    fn create_dir_all_with_mode(&self, path: &Path, mode: u32) -> crate::Result<()>;
+
     /// Writes raw bytes atomically (truncate + replace).
     fn write(&self, path: &Path, data: &[u8]) -> crate::Result<()>;
 
     /// Writes UTF-8 text.
     fn write_to_string(&self, path: &Path, content: &str) -> crate::Result<()>;
 
+    /// Creates `path` with `data`, failing if it already exists
+    /// (`O_CREAT|O_EXCL`), so concurrent writers never share a name. With
+    /// `sync`, the file and then its parent directory are flushed to disk
+    /// (`fsync`) before returning, so the new entry survives a crash.
+    fn write_new(&self, path: &Path, data: &[u8], sync: bool) -> crate::Result<()>;
+
+    /// Appends `data` to `path`, creating it when missing. The data is
+    /// written at once with `O_APPEND`, so short records of concurrent
+    /// writers do not interleave.
+    fn append(&self, path: &Path, data: &[u8]) -> crate::Result<()>;
+
     /// Reads UTF-8 text.
     fn read_to_string(&self, path: &Path) -> crate::Result<String>;
 
+    /// Opens a file for reading, for callers that only need its start.
+    fn open(&self, path: &Path) -> crate::Result<fs::File>;
+
     /// Removes a file.
     fn remove_file(&self, path: &Path) -> crate::Result<()>;
 
-    /// Renames/moves a path.
+    /// Renames/moves a path. A symlink is moved itself, never its target.
     fn rename(&self, from: &Path, to: &Path) -> crate::Result<()>;
 
+    /// Copies the contents and permissions of the file `from` (following
+    /// symlinks) to `to`, replacing it; returns the number of bytes copied.
+    /// Where the filesystem supports it the copy is a reflink sharing the
+    /// data of `from`, which is nearly instant; otherwise the holes of a
+    /// sparse file stay holes where the platform can find them.
+    fn copy_file(&self, from: &Path, to: &Path) -> crate::Result<u64>;
+
+    /// Copies a single non-directory entry to the new path `to` without
+    /// following symlinks: a symlink is recreated with the same target, a
+    /// regular file is copied with its permissions, and a named pipe,
+    /// socket or device node is recreated with `mknod`.
+    fn copy_no_follow(&self, from: &Path, to: &Path) -> crate::Result<()>;
+
+    /// Creates `link` as a new hard link to `original`.
+    fn hard_link(&self, original: &Path, link: &Path) -> crate::Result<()>;
+
+    /// Reads the target of a symlink.
+    fn read_link(&self, path: &Path) -> crate::Result<PathBuf>;
+
+    /// Creates a symlink at `link` pointing to `target`.
+    fn symlink(&self, target: &Path, link: &Path) -> crate::Result<()>;
+
+    /// Sets the permissions of `path` (following symlinks).
+    fn set_permissions(&self, path: &Path, permissions: Permissions) -> crate::Result<()>;
+
+    /// Changes the owner and group of `path` itself, never a symlink's
+    /// target (`lchown`).
+    fn set_owner(&self, path: &Path, uid: u32, gid: u32) -> crate::Result<()>;
+
+    /// Sets the access and modification times of `path` (following symlinks).
+    fn set_times(&self, path: &Path, accessed: SystemTime, modified: SystemTime) -> crate::Result<()>;
+
+    /// Names of the extended attributes of `path` itself, POSIX ACLs
+    /// (`system.posix_acl_*`) and security labels included; empty where the
+    /// platform or the filesystem has none.
+    fn list_xattrs(&self, path: &Path) -> crate::Result<Vec<OsString>>;
+
+    /// Value of the extended attribute `name` of `path` itself.
+    fn get_xattr(&self, path: &Path, name: &OsStr) -> crate::Result<Vec<u8>>;
+
+    /// Sets the extended attribute `name` of `path` itself to `value`.
+    fn set_xattr(&self, path: &Path, name: &OsStr, value: &[u8]) -> crate::Result<()>;
+
     /// Lists directory children as concrete paths.
     fn list_dir(&self, path: &Path) -> crate::Result<Vec<PathBuf>>;
 
+    /// Streams the children of `path` one at a time, so directories with
+    /// hundreds of thousands of entries are walked with bounded memory.
+    fn read_dir_iter(&self, path: &Path) -> crate::Result<DirEntries>;
+
     /// Removes an empty directory.
     fn remove_dir(&self, path: &Path) -> crate::Result<()>;
+
+    /// Removes `path` and, when it is a directory, everything below it. A
+    /// symlink is removed itself, never its target.
+    fn remove_dir_all(&self, path: &Path) -> crate::Result<()>;
+
+    /// Like [`FileSystem::remove_dir_all`], but every directory is opened
+    /// relative to its parent without following symlinks and checked to be
+    /// the one that was inspected, so a directory swapped for a symlink while
+    /// the tree is being deleted makes the removal fail instead of deleting
+    /// outside of it. Directories on another filesystem (e.g. a bind mount
+    /// inside the tree) are refused too.
+    fn remove_dir_all_no_follow(&self, path: &Path) -> crate::Result<()>;
+
+    /// Bytes available to unprivileged users on the filesystem holding `path`.
+    fn available_space(&self, path: &Path) -> crate::Result<u64>;
+
+    /// Size, used and available bytes of the filesystem holding `path`.
+    fn space_usage(&self, path: &Path) -> crate::Result<SpaceUsage>;
+
+    /// Device number (`st_dev`) of the filesystem holding `path`, following
+    /// symlinks; two paths can be renamed into each other only when equal.
+    fn device_id(&self, path: &Path) -> crate::Result<u64>;
+
+    /// The [`SizeStat`] of `path`. `file_type`, when a directory listing
+    /// gave it, spares looking the type up: named pipes, sockets and devices
+    /// are then 0 bytes without any call, and only directories are asked
+    /// for their inode. On Linux the rest is a single `statx` asking for
+    /// just those fields, without forcing a network filesystem to refresh
+    /// its attributes.
+    fn size_stat(&self, path: &Path, file_type: Option<FileType>) -> crate::Result<SizeStat>;
+
+    /// Whether the filesystem holding `path` is mounted read-only.
+    fn is_read_only(&self, path: &Path) -> crate::Result<bool>;
+
+    /// Whether `path` itself has the immutable or append-only attribute
+    /// (`chattr +i`/`+a`), which makes renaming or removing it fail with
+    /// `EPERM`, even for root; `false` where it cannot be queried.
+    fn is_immutable(&self, path: &Path) -> bool;
 }
 
 /// Default filesystem implementation backed by `std::fs`.
@@ -78,6 +238,20 @@ impl FileSystem for RealFileSystem {
         fs::create_dir(path).map_err(|err| CoreError::io(path, err))
     }
 
+    #[cfg(unix)]
+    fn create_dir_all_with_mode(&self, path: &Path, mode: u32) -> crate::Result<()> {
+        use std::os::unix::fs::DirBuilderExt;
+        match fs::DirBuilder::new().recursive(true).mode(mode).create(path) {
+            Err(err) if !path.is_dir() => Err(CoreError::io(path, err)),
+            _ => Ok(()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn create_dir_all_with_mode(&self, path: &Path, _mode: u32) -> crate::Result<()> {
+        self.create_dir_all(path)
+    }
+
     fn write(&self, path: &Path, data: &[u8]) -> crate::Result<()> {
         fs::write(path, data).map_err(|err| CoreError::io(path, err))
     }
@@ -86,10 +260,39 @@ impl FileSystem for RealFileSystem {
         fs::write(path, content).map_err(|err| CoreError::io(path, err))
     }
 
+    fn write_new(&self, path: &Path, data: &[u8], sync: bool) -> crate::Result<()> {
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map_err(|err| CoreError::io(path, err))?;
+        let written = file.write_all(data).and_then(|()| match sync {
+            true => file.sync_all().and_then(|()| sync_parent(path)),
+            false => Ok(()),
+        });
+        written.map_err(|err| {
+            let _ = fs::remove_file(path);
+            CoreError::io(path, err)
+        })
+    }
+
+    fn append(&self, path: &Path, data: &[u8]) -> crate::Result<()> {
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .map_err(|err| CoreError::io(path, err))?;
+        file.write_all(data).map_err(|err| CoreError::io(path, err))
+    }
+
     fn read_to_string(&self, path: &Path) -> crate::Result<String> {
         fs::read_to_string(path).map_err(|err| CoreError::io(path, err))
     }
 
+    fn open(&self, path: &Path) -> crate::Result<fs::File> {
+        fs::File::open(path).map_err(|err| CoreError::io(path, err))
+    }
+
     fn remove_file(&self, path: &Path) -> crate::Result<()> {
         fs::remove_file(path).map_err(|err| CoreError::io(path, err))
     }
@@ -98,6 +301,155 @@ impl FileSystem for RealFileSystem {
         fs::rename(from, to).map_err(|err| CoreError::io(from, err))
     }
 
+    #[cfg(target_os = "linux")]
+    fn copy_file(&self, from: &Path, to: &Path) -> crate::Result<u64> {
+        use std::os::unix::fs::MetadataExt;
+
+        let metadata = self.metadata(from)?;
+        if clone_file(from, to).is_ok() {
+            return Ok(metadata.len());
+        }
+        // A file using fewer blocks than its size has holes.
+        let copied = match metadata.blocks().saturating_mul(512) < metadata.len() {
+            true => match copy_sparse(from, to) {
+                Err(err) if err.raw_os_error() == Some(libc::EINVAL) => fs::copy(from, to),
+                copied => copied,
+            },
+            false => fs::copy(from, to),
+        };
+        copied.map_err(|err| CoreError::io(from, err))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn copy_file(&self, from: &Path, to: &Path) -> crate::Result<u64> {
+        fs::copy(from, to).map_err(|err| CoreError::io(from, err))
+    }
+
+    fn copy_no_follow(&self, from: &Path, to: &Path) -> crate::Result<()> {
+        let file_type = self.symlink_metadata(from)?.file_type();
+        if file_type.is_symlink() {
+            self.symlink(&self.read_link(from)?, to)
+        } else if file_type.is_file() {
+            self.copy_file(from, to).map(|_| ())
+        } else {
+            make_node_like(from, to)
+        }
+    }
+
+    fn hard_link(&self, original: &Path, link: &Path) -> crate::Result<()> {
+        fs::hard_link(original, link).map_err(|err| CoreError::io(link, err))
+    }
+
+    fn read_link(&self, path: &Path) -> crate::Result<PathBuf> {
+        fs::read_link(path).map_err(|err| CoreError::io(path, err))
+    }
+
+    #[cfg(unix)]
+    fn symlink(&self, target: &Path, link: &Path) -> crate::Result<()> {
+        std::os::unix::fs::symlink(target, link).map_err(|err| CoreError::io(link, err))
+    }
+
+    #[cfg(not(unix))]
+    fn symlink(&self, _target: &Path, link: &Path) -> crate::Result<()> {
+        Err(CoreError::UnsupportedPlatform(format!("symlink creation at {}", link.display())))
+    }
+
+    fn set_permissions(&self, path: &Path, permissions: Permissions) -> crate::Result<()> {
+        fs::set_permissions(path, permissions).map_err(|err| CoreError::io(path, err))
+    }
+
+    #[cfg(unix)]
+    fn set_owner(&self, path: &Path, uid: u32, gid: u32) -> crate::Result<()> {
+        std::os::unix::fs::lchown(path, Some(uid), Some(gid)).map_err(|err| CoreError::io(path, err))
+    }
+
+    #[cfg(not(unix))]
+    fn set_owner(&self, path: &Path, _uid: u32, _gid: u32) -> crate::Result<()> {
+        Err(CoreError::UnsupportedPlatform(format!("ownership change of {}", path.display())))
+    }
+
+    #[cfg(unix)]
+    fn set_times(&self, path: &Path, accessed: SystemTime, modified: SystemTime) -> crate::Result<()> {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let c_path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|_| CoreError::InvalidPath(path.display().to_string()))?;
+        let times = [timespec(accessed), timespec(modified)];
+        // SAFETY: `c_path` is NUL-terminated and `times` holds the two
+        // entries `utimensat` reads.
+        if unsafe { libc::utimensat(libc::AT_FDCWD, c_path.as_ptr(), times.as_ptr(), 0) } != 0 {
+            return Err(CoreError::io(path, io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn set_times(&self, path: &Path, accessed: SystemTime, modified: SystemTime) -> crate::Result<()> {
+        let times = fs::FileTimes::new().set_accessed(accessed).set_modified(modified);
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_times(times))
+            .map_err(|err| CoreError::io(path, err))
+    }
+
+    #[cfg(target_os = "linux")]
+    fn list_xattrs(&self, path: &Path) -> crate::Result<Vec<OsString>> {
+        use std::os::unix::ffi::OsStrExt;
+
+        let c_path = c_path(path)?;
+        // SAFETY: `c_path` is NUL-terminated and the buffer holds `len`
+        // writable bytes.
+        let names = read_sized(|buffer, len| unsafe { libc::llistxattr(c_path.as_ptr(), buffer.cast(), len) });
+        match names {
+            Ok(names) => Ok(names
+                .split(|byte| *byte == 0)
+                .filter(|name| !name.is_empty())
+                .map(|name| OsStr::from_bytes(name).to_os_string())
+                .collect()),
+            Err(err) if err.raw_os_error() == Some(libc::ENOTSUP) => Ok(Vec::new()),
+            Err(err) => Err(CoreError::io(path, err)),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn list_xattrs(&self, _path: &Path) -> crate::Result<Vec<OsString>> {
+        Ok(Vec::new())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn get_xattr(&self, path: &Path, name: &OsStr) -> crate::Result<Vec<u8>> {
+        let (c_path, c_name) = (c_path(path)?, c_path(Path::new(name))?);
+        // SAFETY: both strings are NUL-terminated and the buffer holds `len`
+        // writable bytes.
+        read_sized(|buffer, len| unsafe { libc::lgetxattr(c_path.as_ptr(), c_name.as_ptr(), buffer.cast(), len) })
+            .map_err(|err| CoreError::io(path, err))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn get_xattr(&self, path: &Path, _name: &OsStr) -> crate::Result<Vec<u8>> {
+        Err(CoreError::UnsupportedPlatform(format!("extended attributes of {}", path.display())))
+    }
+
+    #[cfg(target_os = "linux")]
+    fn set_xattr(&self, path: &Path, name: &OsStr, value: &[u8]) -> crate::Result<()> {
+        let (c_path, c_name) = (c_path(path)?, c_path(Path::new(name))?);
+        // SAFETY: both strings are NUL-terminated and `value` holds the
+        // bytes passed.
+        let result =
+            unsafe { libc::lsetxattr(c_path.as_ptr(), c_name.as_ptr(), value.as_ptr().cast(), value.len(), 0) };
+        if result != 0 {
+            return Err(CoreError::io(path, io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn set_xattr(&self, path: &Path, _name: &OsStr, _value: &[u8]) -> crate::Result<()> {
+        Err(CoreError::UnsupportedPlatform(format!("extended attributes of {}", path.display())))
+    }
+
     fn list_dir(&self, path: &Path) -> crate::Result<Vec<PathBuf>> {
         fs::read_dir(path)
             .map_err(|err| CoreError::io(path, err))?
@@ -106,9 +458,466 @@ impl FileSystem for RealFileSystem {
             .map_err(|err| CoreError::io(path, err))
     }
 
+    fn read_dir_iter(&self, path: &Path) -> crate::Result<DirEntries> {
+        let dir = path.to_path_buf();
+        let entries = fs::read_dir(path).map_err(|err| CoreError::io(path, err))?;
+        Ok(Box::new(entries.map(move |entry| {
+            entry
+                .map(|entry| DirEntryInfo {
+                    path: entry.path(),
+                    file_type: entry.file_type().ok(),
+                })
+                .map_err(|err| CoreError::io(&dir, err))
+        })))
+    }
+
     fn remove_dir(&self, path: &Path) -> crate::Result<()> {
         fs::remove_dir(path).map_err(|err| CoreError::io(path, err))
     }
+
+    fn remove_dir_all(&self, path: &Path) -> crate::Result<()> {
+        if self.symlink_metadata(path)?.is_dir() {
+            fs::remove_dir_all(path).map_err(|err| CoreError::io(path, err))
+        } else {
+            self.remove_file(path)
+        }
+    }
+
+    #[cfg(unix)]
+    fn remove_dir_all_no_follow(&self, path: &Path) -> crate::Result<()> {
+        use std::os::unix::fs::MetadataExt;
+
+        let metadata = self.symlink_metadata(path)?;
+        if !metadata.is_dir() {
+            return self.remove_file(path);
+        }
+        let dir = OpenDir::open_at(libc::AT_FDCWD, &c_path(path)?, (metadata.dev(), metadata.ino()))
+            .map_err(|err| CoreError::io(path, err))?;
+        dir.remove_contents(path, metadata.dev())?;
+        drop(dir);
+        self.remove_dir(path)
+    }
+
+    #[cfg(not(unix))]
+    fn remove_dir_all_no_follow(&self, path: &Path) -> crate::Result<()> {
+        let metadata = self.symlink_metadata(path)?;
+        if !metadata.is_dir() {
+            return self.remove_file(path);
+        }
+        for entry in self.read_dir_iter(path)? {
+            self.remove_dir_all_no_follow(&entry?.path)?;
+        }
+        self.remove_dir(path)
+    }
+
+    #[cfg(unix)]
+    fn available_space(&self, path: &Path) -> crate::Result<u64> {
+        let stat = statvfs(path)?;
+        Ok((stat.f_bavail as u64).saturating_mul(stat.f_frsize as u64))
+    }
+
+    #[cfg(not(unix))]
+    fn available_space(&self, path: &Path) -> crate::Result<u64> {
+        Err(CoreError::UnsupportedPlatform(format!("free space query for {}", path.display())))
+    }
+
+    #[cfg(unix)]
+    fn space_usage(&self, path: &Path) -> crate::Result<SpaceUsage> {
+        let stat = statvfs(path)?;
+        let block = stat.f_frsize as u64;
+        let (blocks, free) = (stat.f_blocks as u64, stat.f_bfree as u64);
+        Ok(SpaceUsage {
+            size: blocks.saturating_mul(block),
+            used: blocks.saturating_sub(free).saturating_mul(block),
+            available: (stat.f_bavail as u64).saturating_mul(block),
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn space_usage(&self, path: &Path) -> crate::Result<SpaceUsage> {
+        Err(CoreError::UnsupportedPlatform(format!("free space query for {}", path.display())))
+    }
+
+    #[cfg(unix)]
+    fn device_id(&self, path: &Path) -> crate::Result<u64> {
+        use std::os::unix::fs::MetadataExt;
+        Ok(self.metadata(path)?.dev())
+    }
+
+    #[cfg(not(unix))]
+    fn device_id(&self, path: &Path) -> crate::Result<u64> {
+        Err(CoreError::UnsupportedPlatform(format!("device id query for {}", path.display())))
+    }
+
+    #[cfg(unix)]
+    fn is_read_only(&self, path: &Path) -> crate::Result<bool> {
+        Ok(statvfs(path)?.f_flag & libc::ST_RDONLY != 0)
+    }
+
+    #[cfg(not(unix))]
+    fn is_read_only(&self, path: &Path) -> crate::Result<bool> {
+        Err(CoreError::UnsupportedPlatform(format!("mount flags query for {}", path.display())))
+    }
+
+    fn is_immutable(&self, path: &Path) -> bool {
+        has_immutable_flag(path)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn size_stat(&self, path: &Path, file_type: Option<FileType>) -> crate::Result<SizeStat> {
+        use std::os::unix::fs::FileTypeExt;
+
+        if let Some(file_type) = file_type {
+            let special = file_type.is_fifo()
+                || file_type.is_socket()
+                || file_type.is_block_device()
+                || file_type.is_char_device();
+            if special {
+                return Ok(SizeStat::default());
+            }
+        }
+        let mut mask = libc::STATX_SIZE;
+        if file_type.is_none() {
+            mask |= libc::STATX_TYPE;
+        }
+        if file_type.is_none_or(|file_type| file_type.is_dir()) {
+            mask |= libc::STATX_INO;
+        }
+        let c_path = c_path(path)?;
+        let mut stat = std::mem::MaybeUninit::<libc::statx>::zeroed();
+        let flags = libc::AT_SYMLINK_NOFOLLOW | libc::AT_STATX_DONT_SYNC;
+        // SAFETY: `c_path` is NUL-terminated and `stat` points to writable,
+        // zeroed memory the size of a `statx`.
+        if unsafe { libc::statx(libc::AT_FDCWD, c_path.as_ptr(), flags, mask, stat.as_mut_ptr()) } != 0 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() == Some(libc::ENOSYS) {
+                return Ok(SizeStat::of(&self.symlink_metadata(path)?));
+            }
+            return Err(CoreError::io(path, err));
+        }
+        // SAFETY: zeroed memory is a valid `statx`, filled in on success.
+        let stat = unsafe { stat.assume_init() };
+        if stat.stx_mask & mask != mask {
+            return Ok(SizeStat::of(&self.symlink_metadata(path)?));
+        }
+        let kind = u32::from(stat.stx_mode) & libc::S_IFMT;
+        let (is_dir, is_symlink) = match file_type {
+            Some(file_type) => (file_type.is_dir(), file_type.is_symlink()),
+            None => (kind == libc::S_IFDIR, kind == libc::S_IFLNK),
+        };
+        let device = libc::makedev(stat.stx_dev_major, stat.stx_dev_minor);
+        Ok(SizeStat {
+            size: stat.stx_size,
+            is_dir,
+            is_symlink,
+            dir_key: is_dir.then_some((device, stat.stx_ino)),
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn size_stat(&self, path: &Path, _file_type: Option<FileType>) -> crate::Result<SizeStat> {
+        Ok(SizeStat::of(&self.symlink_metadata(path)?))
+    }
+}
+
+/// Flushes the directory entries of the parent of `path` to disk.
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => fs::File::open(parent)?.sync_all(),
+        _ => fs::File::open(".")?.sync_all(),
+    }
+}
+
+/// Directories cannot be opened as files here; the file itself was synced.
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// `time` as a `timespec`, clamping times before the epoch to it.
+#[cfg(unix)]
+fn timespec(time: SystemTime) -> libc::timespec {
+    let since_epoch = time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+    libc::timespec {
+        tv_sec: since_epoch.as_secs() as libc::time_t,
+        tv_nsec: since_epoch.subsec_nanos() as libc::c_long,
+    }
+}
+
+#[cfg(unix)]
+fn c_path(path: &Path) -> crate::Result<std::ffi::CString> {
+    use std::os::unix::ffi::OsStrExt;
+
+    std::ffi::CString::new(path.as_os_str().as_bytes()).map_err(|_| CoreError::InvalidPath(path.display().to_string()))
+}
+
+/// Makes `to` a reflink of `from` with `FICLONE` (btrfs, XFS, ...), which
+/// only works within one filesystem, e.g. between two btrfs subvolumes that
+/// are different devices for `rename`. On failure `to` may be left empty,
+/// for the regular copy to replace.
+#[cfg(target_os = "linux")]
+fn clone_file(from: &Path, to: &Path) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let source = fs::File::open(from)?;
+    let dest = fs::File::create(to)?;
+    // SAFETY: `FICLONE` takes the source descriptor as its argument; both
+    // descriptors are open for the duration of the call.
+    if unsafe { libc::ioctl(dest.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    dest.set_permissions(source.metadata()?.permissions())
+}
+
+/// Reads the inode flags of `path` with `FS_IOC_GETFLAGS`, looking for
+/// `FS_IMMUTABLE_FL` or `FS_APPEND_FL`. Only regular files and directories
+/// are opened, as opening a device node can have side effects.
+#[cfg(target_os = "linux")]
+pub(crate) fn has_immutable_flag(path: &Path) -> bool {
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::os::unix::io::AsRawFd;
+
+    const FS_IMMUTABLE_FL: libc::c_int = 0x10;
+    const FS_APPEND_FL: libc::c_int = 0x20;
+    if !fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_file() || metadata.is_dir()) {
+        return false;
+    }
+    let Ok(file) = fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOFOLLOW | libc::O_NONBLOCK | libc::O_NOCTTY)
+        .open(path)
+    else {
+        return false;
+    };
+    let mut flags: libc::c_int = 0;
+    // SAFETY: the kernel writes an `int` worth of flags to `flags`, which
+    // lives for the duration of the call, as does the descriptor.
+    if unsafe { libc::ioctl(file.as_raw_fd(), libc::FS_IOC_GETFLAGS, &mut flags) } != 0 {
+        return false;
+    }
+    flags & (FS_IMMUTABLE_FL | FS_APPEND_FL) != 0
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn has_immutable_flag(_path: &Path) -> bool {
+    false
+}
+
+/// Copies the data extents of `from` found with `SEEK_DATA`/`SEEK_HOLE` and
+/// only extends `to` over the holes, so a sparse file is not materialized.
+/// `EINVAL` means the filesystem cannot report holes; nothing was copied
+/// yet then.
+#[cfg(target_os = "linux")]
+fn copy_sparse(from: &Path, to: &Path) -> io::Result<u64> {
+    use std::os::unix::fs::FileExt;
+    use std::os::unix::io::AsRawFd;
+
+    let source = fs::File::open(from)?;
+    let metadata = source.metadata()?;
+    let dest = fs::File::create(to)?;
+    let len = metadata.len();
+    let mut buffer = vec![0u8; 128 * 1024];
+    let mut offset = 0;
+    while offset < len {
+        // SAFETY: a plain `lseek` on a descriptor owned by `source`.
+        let data = unsafe { libc::lseek(source.as_raw_fd(), offset as libc::off_t, libc::SEEK_DATA) };
+        if data < 0 {
+            match io::Error::last_os_error() {
+                // Only a hole is left.
+                err if err.raw_os_error() == Some(libc::ENXIO) => break,
+                err => return Err(err),
+            }
+        }
+        // SAFETY: as above; `data` is an offset the previous call returned.
+        let hole = unsafe { libc::lseek(source.as_raw_fd(), data, libc::SEEK_HOLE) };
+        if hole < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let (mut position, end) = (data as u64, (hole as u64).min(len));
+        while position < end {
+            let wanted = buffer.len().min((end - position) as usize);
+            let read = source.read_at(&mut buffer[..wanted], position)?;
+            if read == 0 {
+                break;
+            }
+            dest.write_all_at(&buffer[..read], position)?;
+            position += read as u64;
+        }
+        offset = end.max(offset + 1);
+    }
+    dest.set_len(len)?;
+    dest.set_permissions(metadata.permissions())?;
+    Ok(len)
+}
+
+/// Runs a `*xattr` call that fills a buffer, first asking for the size it
+/// needs, again as long as the value grows in between (`ERANGE`).
+#[cfg(target_os = "linux")]
+fn read_sized(mut call: impl FnMut(*mut u8, usize) -> libc::ssize_t) -> io::Result<Vec<u8>> {
+    loop {
+        let size = call(std::ptr::null_mut(), 0);
+        if size < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut buffer = vec![0u8; size as usize];
+        match call(buffer.as_mut_ptr(), buffer.len()) {
+            read if read >= 0 => {
+                buffer.truncate(read as usize);
+                return Ok(buffer);
+            }
+            _ if io::Error::last_os_error().raw_os_error() == Some(libc::ERANGE) => continue,
+            _ => return Err(io::Error::last_os_error()),
+        }
+    }
+}
+
+/// Creates at `to` a named pipe, socket or device node of the type, mode
+/// and device number of `from`; device nodes need `CAP_MKNOD`.
+#[cfg(unix)]
+fn make_node_like(from: &Path, to: &Path) -> crate::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = fs::symlink_metadata(from).map_err(|err| CoreError::io(from, err))?;
+    let c_path = c_path(to)?;
+    // SAFETY: `c_path` is NUL-terminated; mknod only reads it.
+    if unsafe { libc::mknod(c_path.as_ptr(), metadata.mode() as libc::mode_t, metadata.rdev() as libc::dev_t) } != 0 {
+        return Err(CoreError::io(to, io::Error::last_os_error()));
+    }
+    fs::set_permissions(to, metadata.permissions()).map_err(|err| CoreError::io(to, err))
+}
+
+#[cfg(not(unix))]
+fn make_node_like(from: &Path, _to: &Path) -> crate::Result<()> {
+    Err(CoreError::InvalidPath(format!("{} is not a regular file or symlink", from.display())))
+}
+
+#[cfg(unix)]
+fn statvfs(path: &Path) -> crate::Result<libc::statvfs> {
+    let c_path = c_path(path)?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `c_path` is NUL-terminated and `stat` points to writable memory
+    // large enough for a `statvfs`, which the call fully initializes on success.
+    if unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(CoreError::io(path, io::Error::last_os_error()));
+    }
+    Ok(unsafe { stat.assume_init() })
+}
+
+/// A directory opened with `O_NOFOLLOW`, walked by
+/// [`FileSystem::remove_dir_all_no_follow`] through `*at` calls so no path
+/// is ever resolved again once its parent is open.
+#[cfg(unix)]
+struct OpenDir(*mut libc::DIR);
+
+#[cfg(unix)]
+impl OpenDir {
+    /// Opens `name` relative to `parent`, failing unless it is still the
+    /// directory identified by `expected` (device, inode).
+    fn open_at(parent: libc::c_int, name: &std::ffi::CStr, expected: (u64, u64)) -> io::Result<Self> {
+        let flags = libc::O_RDONLY | libc::O_DIRECTORY | libc::O_NOFOLLOW | libc::O_CLOEXEC;
+        // SAFETY: `name` is NUL-terminated; the returned descriptor is owned
+        // here and either closed or handed over to `fdopendir`.
+        let fd = unsafe { libc::openat(parent, name.as_ptr(), flags) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fail = |err: io::Error| {
+            // SAFETY: `fd` is open and not owned by anything else yet.
+            unsafe { libc::close(fd) };
+            Err(err)
+        };
+        let stat = match fstat_at(fd, None) {
+            Ok(stat) => stat,
+            Err(err) => return fail(err),
+        };
+        if (stat.st_dev as u64, stat.st_ino as u64) != expected {
+            return fail(io::Error::other("directory was replaced while being removed"));
+        }
+        // SAFETY: `fd` is an open directory descriptor; on success the
+        // stream owns it and `closedir` closes it.
+        let dir = unsafe { libc::fdopendir(fd) };
+        if dir.is_null() {
+            return fail(io::Error::last_os_error());
+        }
+        Ok(OpenDir(dir))
+    }
+
+    fn fd(&self) -> libc::c_int {
+        // SAFETY: `self.0` is a valid stream until dropped.
+        unsafe { libc::dirfd(self.0) }
+    }
+
+    /// Names of the entries, without `.` and `..`. A read error ends the
+    /// listing early; the final `rmdir` then reports the leftovers.
+    fn names(&self) -> Vec<std::ffi::CString> {
+        let mut names = Vec::new();
+        loop {
+            // SAFETY: `self.0` is a valid stream; the entry is only read
+            // before the next `readdir` call.
+            let entry = unsafe { libc::readdir(self.0) };
+            if entry.is_null() {
+                return names;
+            }
+            // SAFETY: `d_name` is a NUL-terminated string inside `entry`.
+            let name = unsafe { std::ffi::CStr::from_ptr((*entry).d_name.as_ptr()) };
+            if !matches!(name.to_bytes(), b"." | b"..") {
+                names.push(name.to_owned());
+            }
+        }
+    }
+
+    /// Deletes every entry below this directory, whose path is `path`,
+    /// refusing to descend into directories not on `device`.
+    fn remove_contents(&self, path: &Path, device: u64) -> crate::Result<()> {
+        use std::os::unix::ffi::OsStrExt;
+
+        for name in self.names() {
+            let child = path.join(OsStr::from_bytes(name.to_bytes()));
+            let stat = fstat_at(self.fd(), Some(&name)).map_err(|err| CoreError::io(&child, err))?;
+            let is_dir = stat.st_mode & libc::S_IFMT == libc::S_IFDIR;
+            if is_dir {
+                if stat.st_dev as u64 != device {
+                    return Err(CoreError::io(&child, io::Error::other("refusing to cross into another filesystem")));
+                }
+                let dir = OpenDir::open_at(self.fd(), &name, (stat.st_dev as u64, stat.st_ino as u64))
+                    .map_err(|err| CoreError::io(&child, err))?;
+                dir.remove_contents(&child, device)?;
+            }
+            let flags = if is_dir { libc::AT_REMOVEDIR } else { 0 };
+            // SAFETY: `name` is NUL-terminated and the descriptor is open.
+            if unsafe { libc::unlinkat(self.fd(), name.as_ptr(), flags) } != 0 {
+                return Err(CoreError::io(&child, io::Error::last_os_error()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+impl Drop for OpenDir {
+    fn drop(&mut self) {
+        // SAFETY: `self.0` came from `fdopendir` and is closed only here.
+        unsafe { libc::closedir(self.0) };
+    }
+}
+
+/// `fstat` of `fd` itself, or `fstatat` of `name` relative to it without
+/// following a final symlink.
+#[cfg(unix)]
+fn fstat_at(fd: libc::c_int, name: Option<&std::ffi::CStr>) -> io::Result<libc::stat> {
+    let mut stat = std::mem::MaybeUninit::<libc::stat>::uninit();
+    // SAFETY: `name`, when given, is NUL-terminated and `stat` points to
+    // writable memory the call fully initializes on success.
+    let result = unsafe {
+        match name {
+            Some(name) => libc::fstatat(fd, name.as_ptr(), stat.as_mut_ptr(), libc::AT_SYMLINK_NOFOLLOW),
+            None => libc::fstat(fd, stat.as_mut_ptr()),
+        }
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { stat.assume_init() })
 }
 
 /// Convenience helper for command implementations that repeatedly need the first
@@ -121,3 +930,163 @@ pub fn first_entry_file_name(entries: &[DirEntry]) -> Option<String> {
             .map(|name| name.to_ascii_lowercase())
     })
 }
+
+/// Computes the apparent size of `path`, descending into directories.
+///
+/// Symlinks are never followed (they count as the size of the link itself),
+/// and each directory is visited at most once by device/inode, so bind-mount
+/// loops cannot make the walk diverge.
+pub fn recursive_size(fs: &dyn FileSystem, path: &Path) -> crate::Result<u64> {
+    let mut visited = HashSet::new();
+    recursive_size_inner(fs, path, None, &mut visited)
+}
+
+/// Sizes `path`, reusing the type its directory listing gave, see
+/// [`FileSystem::size_stat`].
+fn recursive_size_inner(
+    fs: &dyn FileSystem,
+    path: &Path,
+    file_type: Option<FileType>,
+    visited: &mut HashSet<(u64, u64)>,
+) -> crate::Result<u64> {
+    let stat = fs.size_stat(path, file_type)?;
+    if !stat.is_dir {
+        return Ok(stat.size);
+    }
+    if let Some(key) = stat.dir_key {
+        if !visited.insert(key) {
+            return Ok(0);
+        }
+    }
+
+    let mut total = stat.size;
+    for child in fs.read_dir_iter(path)? {
+        let child = child?;
+        total = total.saturating_add(recursive_size_inner(fs, &child.path, child.file_type, visited)?);
+    }
+    Ok(total)
+}
+
+/// Copies `source` to the new path `dest` depth-first, using only
+/// [`FileSystem`] operations.
+///
+/// Symlinks are recreated, never followed; permissions, extended attributes
+/// (see [`copy_xattrs`]) and access and modification times are kept.
+/// Directory permissions are applied once their contents are in place, so
+/// read-only directories can still be filled. Named pipes, sockets and
+/// device nodes are recreated with `mknod`, which for devices needs
+/// `CAP_MKNOD`.
+pub fn copy_tree(fs: &dyn FileSystem, source: &Path, dest: &Path) -> crate::Result<()> {
+    copy_tree_with_progress(fs, source, dest, &mut NoProgress)
+}
+
+/// [`copy_tree`], advancing `progress` by one item and its size for each
+/// entry copied. `progress` is neither started nor finished here.
+pub fn copy_tree_with_progress(
+    fs: &dyn FileSystem,
+    source: &Path,
+    dest: &Path,
+    progress: &mut dyn Progress,
+) -> crate::Result<()> {
+    copy_tree_cancellable(fs, source, dest, progress, &CancellationToken::new())
+}
+
+/// [`copy_tree_with_progress`] that stops with an
+/// [`io::ErrorKind::Interrupted`] error before the next entry once `cancel`
+/// is cancelled, leaving a partial copy for the caller to remove.
+pub fn copy_tree_cancellable(
+    fs: &dyn FileSystem,
+    source: &Path,
+    dest: &Path,
+    progress: &mut dyn Progress,
+    cancel: &CancellationToken,
+) -> crate::Result<()> {
+    cancel.check().map_err(|err| CoreError::io(source, err))?;
+    let metadata = fs.symlink_metadata(source)?;
+    if metadata.is_dir() {
+        fs.create_dir(dest)?;
+        for child in fs.list_dir(source)? {
+            if let Some(name) = child.file_name() {
+                copy_tree_cancellable(fs, &child, &dest.join(name), progress, cancel)?;
+            }
+        }
+        copy_xattrs(fs, source, dest);
+        fs.set_permissions(dest, metadata.permissions())?;
+        progress.advance(1, metadata.len());
+    } else {
+        fs.copy_no_follow(source, dest)?;
+        progress.advance(1, metadata.len());
+        if metadata.file_type().is_symlink() {
+            return Ok(());
+        }
+        copy_xattrs(fs, source, dest);
+    }
+    match (metadata.accessed(), metadata.modified()) {
+        (Ok(accessed), Ok(modified)) => fs.set_times(dest, accessed, modified),
+        _ => Ok(()),
+    }
+}
+
+/// Removes what [`copy_tree`] managed to create at `dest`, making its
+/// directories writable again first. Failures are ignored: this is cleanup
+/// after an error that is reported instead.
+pub(crate) fn remove_partial_copy(fs: &dyn FileSystem, dest: &Path) {
+    match fs.symlink_metadata(dest) {
+        Ok(metadata) if metadata.is_dir() => {
+            make_tree_writable(fs, dest);
+            let _ = fs.remove_dir_all(dest);
+        }
+        Ok(_) => {
+            let _ = fs.remove_file(dest);
+        }
+        Err(_) => {}
+    }
+}
+
+#[cfg(unix)]
+fn make_tree_writable(fs: &dyn FileSystem, dir: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+
+    if let Ok(metadata) = fs.symlink_metadata(dir) {
+        if metadata.is_dir() {
+            let _ = fs.set_permissions(dir, Permissions::from_mode(metadata.permissions().mode() | 0o700));
+            for entry in fs.list_dir(dir).unwrap_or_default() {
+                make_tree_writable(fs, &entry);
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn make_tree_writable(_fs: &dyn FileSystem, _dir: &Path) {}
+
+/// Copies the extended attributes of `source`, POSIX ACLs and security
+/// labels included, to `dest`, as far as its filesystem and the caller's
+/// privileges allow: a label only root may set, or a filesystem without
+/// extended attributes, must not make the copy fail, so failures are only
+/// logged.
+pub fn copy_xattrs(fs: &dyn FileSystem, source: &Path, dest: &Path) {
+    let names = match fs.list_xattrs(source) {
+        Ok(names) => names,
+        Err(err) => return log::debug("fs", format_args!("cannot list extended attributes: {}", err)),
+    };
+    for name in names {
+        if let Err(err) = fs.get_xattr(source, &name).and_then(|value| fs.set_xattr(dest, &name, &value)) {
+            log::debug(
+                "fs",
+                format_args!("cannot copy {} to {}: {}", name.to_string_lossy(), dest.display(), err),
+            );
+        }
+    }
+}
+
+#[cfg(unix)]
+fn inode_key(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn inode_key(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
@@ -0,0 +1,296 @@
+//! fnmatch(3)-compatible glob matching shared by commands that filter trashed
+//! items by pattern (trash-rm, trash-list, trash-empty --match).
+//!
+//! Supported syntax: `*`, `?`, bracket expressions (`[abc]`, `[a-z]`,
+//! `[!x]`/`[^x]`) and backslash escapes. An unterminated `[` is a literal.
+
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// Behavior switches mirroring the relevant `FNM_*` flags.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct MatchOptions {
+    /// `FNM_PATHNAME`: wildcards and bracket expressions never match `/`.
+    pub pathname: bool,
+    /// `FNM_NOESCAPE`: treat backslash as an ordinary character.
+    pub noescape: bool,
+    /// `FNM_CASEFOLD`: letters match regardless of case (`--ignore-case`).
+    pub casefold: bool,
+}
+
+impl MatchOptions {
+    pub fn pathname() -> Self {
+        Self {
+            pathname: true,
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Token {
+    Literal(char),
+    AnyChar,
+    AnyString,
+    Class { negated: bool, items: Vec<(char, char)> },
+}
+
+/// A compiled glob pattern.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct GlobPattern {
+    source: String,
+    tokens: Vec<Token>,
+    options: MatchOptions,
+}
+
+impl GlobPattern {
+    pub fn new(pattern: &str, options: MatchOptions) -> Self {
+        Self {
+            source: pattern.to_string(),
+            tokens: compile(pattern, options),
+            options,
+        }
+    }
+
+    /// The pattern text this matcher was compiled from.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn options(&self) -> MatchOptions {
+        self.options
+    }
+
+    pub fn matches(&self, subject: &str) -> bool {
+        let subject: Vec<char> = subject.chars().collect();
+        match_tokens(&self.tokens, &subject, self.options)
+    }
+
+    /// Matches a possibly non-UTF8 subject; invalid sequences can only be
+    /// matched by wildcards.
+    pub fn matches_os(&self, subject: &OsStr) -> bool {
+        self.matches(&subject.to_string_lossy())
+    }
+
+    /// Matches the original location of a trashed item as trash-rm does: a
+    /// pattern starting with `/` matches the whole path, any other pattern
+    /// its basename. With [`MatchOptions::pathname`] (`--full-path`), every
+    /// pattern matches the whole path and wildcards stop at `/`, so
+    /// `/home/me/build/*` only matches the direct children of `build`.
+    pub fn matches_original_path(&self, original_path: &Path) -> bool {
+        if self.options.pathname || self.source.starts_with('/') {
+            self.matches_os(original_path.as_os_str())
+        } else {
+            original_path.file_name().is_some_and(|name| self.matches_os(name))
+        }
+    }
+}
+
+/// One-shot convenience wrapper around [`GlobPattern`].
+pub fn fnmatch(pattern: &str, subject: &str, options: MatchOptions) -> bool {
+    GlobPattern::new(pattern, options).matches(subject)
+}
+
+fn compile(pattern: &str, options: MatchOptions) -> Vec<Token> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' => {
+                if tokens.last() != Some(&Token::AnyString) {
+                    tokens.push(Token::AnyString);
+                }
+                i += 1;
+            }
+            '?' => {
+                tokens.push(Token::AnyChar);
+                i += 1;
+            }
+            '[' => match compile_class(&chars[i + 1..], options) {
+                Some((token, consumed)) => {
+                    tokens.push(token);
+                    i += 1 + consumed;
+                }
+                None => {
+                    tokens.push(Token::Literal('['));
+                    i += 1;
+                }
+            },
+            '\\' if !options.noescape && i + 1 < chars.len() => {
+                tokens.push(Token::Literal(chars[i + 1]));
+                i += 2;
+            }
+            ch => {
+                tokens.push(Token::Literal(ch));
+                i += 1;
+            }
+        }
+    }
+    tokens
+}
+
+/// Parses a bracket expression body (after `[`), returning the token and the
+/// number of chars consumed including the closing `]`.
+fn compile_class(chars: &[char], options: MatchOptions) -> Option<(Token, usize)> {
+    let mut i = 0;
+    let negated = matches!(chars.first(), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut items = Vec::new();
+    let mut first = true;
+    loop {
+        let mut ch = *chars.get(i)?;
+        if ch == ']' && !first {
+            return Some((Token::Class { negated, items }, i + 1));
+        }
+        first = false;
+        if ch == '\\' && !options.noescape {
+            i += 1;
+            ch = *chars.get(i)?;
+        }
+        if options.pathname && ch == '/' {
+            // A slash can never be matched by a bracket expression.
+            return None;
+        }
+        i += 1;
+
+        if chars.get(i) == Some(&'-') && chars.get(i + 1).is_some_and(|next| *next != ']') {
+            let mut hi = chars[i + 1];
+            i += 2;
+            if hi == '\\' && !options.noescape {
+                hi = *chars.get(i)?;
+                i += 1;
+            }
+            items.push((ch, hi));
+        } else {
+            items.push((ch, ch));
+        }
+    }
+}
+
+fn token_matches_char(token: &Token, ch: char, options: MatchOptions) -> bool {
+    match token {
+        Token::Literal(expected) => case_variants(ch, options).any(|ch| *expected == ch),
+        Token::AnyChar => !(options.pathname && ch == '/'),
+        Token::AnyString => unreachable!("star is handled by the matcher loop"),
+        Token::Class { negated, items } => {
+            if options.pathname && ch == '/' {
+                return false;
+            }
+            let hit = case_variants(ch, options).any(|ch| items.iter().any(|(lo, hi)| *lo <= ch && ch <= *hi));
+            hit != *negated
+        }
+    }
+}
+
+/// `ch`, then with `FNM_CASEFOLD` its lower and upper case forms.
+fn case_variants(ch: char, options: MatchOptions) -> impl Iterator<Item = char> {
+    let folded = options
+        .casefold
+        .then(|| [ch.to_lowercase().next(), ch.to_uppercase().next()]);
+    std::iter::once(ch).chain(folded.into_iter().flatten().flatten())
+}
+
+/// Greedy matcher with single-point backtracking to the most recent `*`.
+fn match_tokens(tokens: &[Token], subject: &[char], options: MatchOptions) -> bool {
+    let (mut t, mut s) = (0usize, 0usize);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while s < subject.len() {
+        match tokens.get(t) {
+            Some(Token::AnyString) => {
+                backtrack = Some((t, s));
+                t += 1;
+                continue;
+            }
+            Some(token) if token_matches_char(token, subject[s], options) => {
+                t += 1;
+                s += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        match backtrack {
+            Some((star_t, star_s)) if !(options.pathname && subject[star_s] == '/') => {
+                backtrack = Some((star_t, star_s + 1));
+                t = star_t + 1;
+                s = star_s + 1;
+            }
+            _ => return false,
+        }
+    }
+
+    tokens[t..].iter().all(|token| *token == Token::AnyString)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, subject: &str) -> bool {
+        fnmatch(pattern, subject, MatchOptions::default())
+    }
+
+    #[test]
+    fn star_matches_any_run_of_characters() {
+        assert!(matches("*.txt", "notes.txt"));
+        assert!(matches("*.txt", ".txt"));
+        assert!(matches("a*b*c", "abc"));
+        assert!(matches("a*b*c", "a-b/x/c"));
+        assert!(!matches("*.txt", "notes.txt.bak"));
+    }
+
+    #[test]
+    fn question_mark_matches_one_character() {
+        assert!(matches("file?.log", "file1.log"));
+        assert!(matches("caf?", "caf\u{e9}"));
+        assert!(!matches("file?.log", "file.log"));
+        assert!(!matches("file?.log", "file10.log"));
+    }
+
+    #[test]
+    fn matches_character_classes() {
+        assert!(matches("[abc]x", "bx"));
+        assert!(matches("v[0-9].rs", "v7.rs"));
+        assert!(!matches("v[0-9].rs", "vx.rs"));
+        assert!(matches("[!a-c]x", "dx"));
+        assert!(matches("[^a-c]x", "dx"));
+        assert!(!matches("[!a-c]x", "bx"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("[x", "[x"));
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "a"));
+    }
+
+    #[test]
+    fn ignores_case_with_casefold() {
+        let options = MatchOptions {
+            casefold: true,
+            ..MatchOptions::default()
+        };
+        assert!(fnmatch("*.JPG", "photo.jpg", options));
+        assert!(fnmatch("[a-c]*", "Beta", options));
+        assert!(!matches("*.JPG", "photo.jpg"));
+    }
+
+    #[test]
+    fn matches_basenames_unless_the_pattern_is_a_full_path() {
+        let path = Path::new("/home/me/build/out.o");
+        assert!(GlobPattern::new("*.o", MatchOptions::default()).matches_original_path(path));
+        assert!(!GlobPattern::new("build/*.o", MatchOptions::default()).matches_original_path(path));
+        assert!(GlobPattern::new("/home/*.o", MatchOptions::default()).matches_original_path(path));
+    }
+
+    #[test]
+    fn full_path_matching_stops_wildcards_at_slashes() {
+        let path = Path::new("/home/me/build/out.o");
+        assert!(GlobPattern::new("/home/me/build/*", MatchOptions::pathname()).matches_original_path(path));
+        assert!(!GlobPattern::new("/home/*", MatchOptions::pathname()).matches_original_path(path));
+        assert!(!GlobPattern::new("*.o", MatchOptions::pathname()).matches_original_path(path));
+        assert!(!GlobPattern::new("/home/me/build?out.o", MatchOptions::pathname()).matches_original_path(path));
+    }
+}
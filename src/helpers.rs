@@ -1,6 +1,10 @@
 //! Shared utility helpers for migration-aware command behavior.
 
-use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use crate::environment::Environment;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::io::IsTerminal;
 use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime};
 
@@ -10,12 +14,185 @@ pub const TRASHINFO_EXTENSION: &str = ".trashinfo";
 /// Deletion date format commonly used by Trash info metadata.
 pub const TRASHINFO_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
 
+/// Deletion dates as the commands show them, like Python's `str(datetime)`.
+pub const DISPLAY_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// The time zone dates are shown in.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum DisplayZone {
+    /// The local time zone, in which the spec stores deletion dates.
+    #[default]
+    Local,
+    Utc,
+}
+
+/// Percent-encodes `bytes` as RFC 3986 describes: unreserved characters
+/// (`A-Z a-z 0-9 - . _ ~`) and the ASCII bytes in `keep` are copied, every
+/// other byte becomes `%XX` with uppercase hex digits.
+pub fn percent_encode(bytes: &[u8], keep: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len());
+    for &byte in bytes {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) || (byte.is_ascii() && keep.contains(&byte)) {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Decodes the `%XX` escapes of `text` into the bytes they stand for. A `%`
+/// not followed by two hex digits is not an escape and is kept as is, as
+/// are bytes that should have been encoded but were not.
+pub fn percent_decode(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = match bytes[index] {
+            b'%' => bytes.get(index + 1..index + 3).and_then(hex_byte),
+            _ => None,
+        };
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    decoded
+}
+
+/// The byte written by two hex digits, in either case.
+fn hex_byte(digits: &[u8]) -> Option<u8> {
+    let value = |digit: u8| char::from(digit).to_digit(16);
+    Some((value(digits[0])? << 4 | value(digits[1])?) as u8)
+}
+
+/// `path` with the home directory `home` written `~`, as shells do, for
+/// output meant for people: `/home/me/notes` becomes `~/notes`. Paths
+/// outside of `home`, and every path when `home` is `None` or `/`, are
+/// returned as they are.
+pub fn abbreviate_home(path: &Path, home: Option<&Path>) -> PathBuf {
+    let relative = home
+        .filter(|home| home.parent().is_some())
+        .and_then(|home| path.strip_prefix(home).ok());
+    match relative {
+        Some(relative) if relative.as_os_str().is_empty() => PathBuf::from("~"),
+        Some(relative) => Path::new("~").join(relative),
+        None => path.to_path_buf(),
+    }
+}
+
+/// `text` with what a terminal would act on written as C escapes, like
+/// `ls -b`: `\n`, `\t` and the other control characters, ESC and the C1
+/// controls (U+0080 to U+009F) as octal bytes (`\033`), and backslashes
+/// doubled so escapes cannot be forged. Text without any is returned as is.
+pub fn escape_control(text: &OsStr) -> Cow<'_, OsStr> {
+    let bytes = text.as_encoded_bytes();
+    let is_c1 = |index: usize| {
+        bytes[index] == 0xC2 && bytes.get(index + 1).is_some_and(|next| (0x80..0xA0).contains(next))
+    };
+    let needs_escape = |index: usize| bytes[index].is_ascii_control() || bytes[index] == b'\\' || is_c1(index);
+    if !(0..bytes.len()).any(needs_escape) {
+        return Cow::Borrowed(text);
+    }
+    let mut escaped = Vec::with_capacity(bytes.len() + 8);
+    let mut index = 0;
+    while index < bytes.len() {
+        let width = if is_c1(index) { 2 } else { 1 };
+        for &byte in &bytes[index..index + width] {
+            match byte {
+                b'\\' => escaped.extend_from_slice(b"\\\\"),
+                b'\n' => escaped.extend_from_slice(b"\\n"),
+                b'\t' => escaped.extend_from_slice(b"\\t"),
+                b'\r' => escaped.extend_from_slice(b"\\r"),
+                byte if width == 2 || byte.is_ascii_control() => {
+                    escaped.extend_from_slice(format!("\\{:03o}", byte).as_bytes())
+                }
+                byte => escaped.push(byte),
+            }
+        }
+        index += width;
+    }
+    // SAFETY: only whole ASCII or two-byte UTF-8 characters were replaced,
+    // by ASCII text.
+    Cow::Owned(unsafe { OsString::from_encoded_bytes_unchecked(escaped) })
+}
+
+/// `path` as shown to people on a terminal: `home` written `~` (see
+/// [`abbreviate_home`]) and control characters escaped (see
+/// [`escape_control`]). Output read by programs keeps paths as they are.
+pub fn terminal_path(path: &Path, home: Option<&Path>) -> PathBuf {
+    let path = abbreviate_home(path, home);
+    match escape_control(path.as_os_str()) {
+        Cow::Borrowed(_) => path,
+        Cow::Owned(escaped) => PathBuf::from(escaped),
+    }
+}
+
+/// `path` with a leading `~` written as the home directory and `$VAR` or
+/// `${VAR}` replaced by the variable, as a shell would expand them in a
+/// path given to `--trash-dir=` or written in the config file. Unset
+/// variables, and `~` without a home, are left as they are.
+pub fn expand_path(path: &Path, environ: &dyn Environment) -> PathBuf {
+    let mut rest = path.as_os_str().as_encoded_bytes();
+    let mut expanded = Vec::with_capacity(rest.len());
+    if let Some(home) = environ.home().filter(|_| rest == b"~" || rest.starts_with(b"~/")) {
+        expanded.extend_from_slice(home.as_os_str().as_encoded_bytes());
+        rest = &rest[1..];
+    }
+    while let Some(dollar) = rest.iter().position(|byte| *byte == b'$') {
+        expanded.extend_from_slice(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
+        let (name, length) = match after.first() {
+            Some(b'{') => match after.iter().position(|byte| *byte == b'}') {
+                Some(end) => (&after[1..end], end + 1),
+                None => (&after[..0], 0),
+            },
+            _ => {
+                let end = after.iter().take_while(|byte| byte.is_ascii_alphanumeric() || **byte == b'_').count();
+                (&after[..end], end)
+            }
+        };
+        let value = std::str::from_utf8(name)
+            .ok()
+            .filter(|name| !name.is_empty())
+            .and_then(|name| environ.var(name));
+        match value {
+            Some(value) => expanded.extend_from_slice(value.as_bytes()),
+            None => expanded.extend_from_slice(&rest[dollar..dollar + 1 + length]),
+        }
+        rest = &after[length..];
+    }
+    expanded.extend_from_slice(rest);
+    // SAFETY: the path was only split at ASCII bytes and joined with whole
+    // paths and strings.
+    PathBuf::from(unsafe { OsString::from_encoded_bytes_unchecked(expanded) })
+}
+
+/// `path` as printed to `stream`: escaped when it is a terminal (see
+/// [`escape_control`]), as is when it is read by a program.
+pub fn printable_path(path: &Path, stream: &impl IsTerminal) -> PathBuf {
+    match stream.is_terminal() {
+        true => terminal_path(path, None),
+        false => path.to_path_buf(),
+    }
+}
+
 /// Returns a user-safe, trimmed path string that can be used in logs and messages.
 pub fn sanitize_user_path(path: &Path) -> String {
     path.display().to_string().trim().to_string()
 }
 
 /// Builds a deterministic, namespaced filename for trash files.
+///
+/// Trash entries use [`numbered_basename`] instead, which keeps the
+/// extension last.
 pub fn build_unique_basename(file_name: &str, suffix: u64) -> String {
     let base = Path::new(file_name)
         .file_name()
@@ -24,38 +201,144 @@ pub fn build_unique_basename(file_name: &str, suffix: u64) -> String {
     format!("{base}.{suffix}")
 }
 
-/// Parses an ISO-like deletion date string into a UTC datetime.
+/// Name of the `index`-th collision candidate for `file_name`, with the
+/// counter inserted before the extension: `report.pdf`, `report_1.pdf`,
+/// `report_2.pdf`, ... Dotfiles without another dot (`.bashrc`) and names
+/// ending in a dot get the counter appended.
+pub fn numbered_basename(file_name: &OsStr, index: u64) -> OsString {
+    if index == 0 {
+        return file_name.to_os_string();
+    }
+    let bytes = file_name.as_encoded_bytes();
+    let split = bytes
+        .iter()
+        .rposition(|byte| *byte == b'.')
+        .filter(|dot| *dot > 0 && *dot + 1 < bytes.len());
+    let (stem, extension) = bytes.split_at(split.unwrap_or(bytes.len()));
+    let mut name = Vec::with_capacity(bytes.len() + 8);
+    name.extend_from_slice(stem);
+    name.extend_from_slice(format!("_{index}").as_bytes());
+    name.extend_from_slice(extension);
+    // SAFETY: the input was valid encoded bytes, and it was only split at an
+    // ASCII `.` and extended with ASCII text.
+    unsafe { OsString::from_encoded_bytes_unchecked(name) }
+}
+
+/// Parses an ISO-like deletion date string: `YYYY-MM-DDTHH:MM:SS` as the
+/// spec writes it, or as other tools do with a space instead of the `T`,
+/// fractional seconds and a `Z`, `+HH:MM` or `+HHMM` offset. Dates without
+/// an offset are in local time, as the spec requires; a local time skipped
+/// by a DST change is taken as UTC.
 pub fn parse_trash_datetime(value: &str) -> Option<DateTime<Utc>> {
-    NaiveDateTime::parse_from_str(value, TRASHINFO_TIME_FORMAT)
+    let value = value.trim();
+    let value = match value.as_bytes().get(10) {
+        Some(b' ') => Cow::Owned(format!("{}T{}", &value[..10], &value[11..])),
+        _ => Cow::Borrowed(value),
+    };
+    if let Ok(naive) = NaiveDateTime::parse_from_str(&value, "%Y-%m-%dT%H:%M:%S%.f") {
+        return Some(match Local.from_local_datetime(&naive).earliest() {
+            Some(local) => local.with_timezone(&Utc),
+            None => Utc.from_utc_datetime(&naive),
+        });
+    }
+    DateTime::parse_from_rfc3339(&value)
+        .or_else(|_| DateTime::parse_from_str(&value, "%Y-%m-%dT%H:%M:%S%.f%z"))
         .ok()
-        .map(|naive| Utc.from_utc_datetime(&naive))
-        .or_else(|| DateTime::parse_from_rfc3339(value).ok().map(|dt| dt.with_timezone(&Utc)))
+        .map(|date| date.with_timezone(&Utc))
 }
 
-/// Serializes a UTC datetime into the repository-standard trash format.
+/// Serializes `time` into the repository-standard trash format, in local
+/// time as `DeletionDate=` requires.
 pub fn serialize_system_time(time: SystemTime) -> String {
-    let dt = DateTime::<Utc>::from(time);
+    let dt = DateTime::<Local>::from(time);
     dt.format(TRASHINFO_TIME_FORMAT).to_string()
 }
 
+/// Renders `date` in `zone` with [`DISPLAY_TIME_FORMAT`].
+pub fn format_datetime(date: DateTime<Utc>, zone: DisplayZone) -> String {
+    match zone {
+        DisplayZone::Local => date.with_timezone(&Local).format(DISPLAY_TIME_FORMAT).to_string(),
+        DisplayZone::Utc => date.format(DISPLAY_TIME_FORMAT).to_string(),
+    }
+}
+
 /// Human readable size rendering shared across commands.
 pub fn print_size(bytes: u64) -> String {
-    const SUFFIXES: [&str; 5] = ["B", "K", "M", "G", "T"];
+    format_size(bytes, SizeFormat::Binary)
+}
+
+/// How sizes are printed, after the size options of du and ls.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum SizeFormat {
+    /// Powers of 1024 with one decimal, `1.5 K` ([`print_size`]).
+    #[default]
+    Binary,
+    /// Powers of 1000 with one decimal, `1.5 KB` (`--si`).
+    Si,
+    /// The exact number of bytes (`-b`).
+    Bytes,
+    /// The number of blocks of this many bytes, rounded up as du does
+    /// (`--block-size`).
+    Blocks(u64),
+}
+
+/// Renders `bytes` as `format` says; [`parse_size`] reads back what the
+/// `Binary` and `Si` formats print.
+pub fn format_size(bytes: u64, format: SizeFormat) -> String {
+    match format {
+        SizeFormat::Binary => scaled_size(bytes, 1024.0, ["B", "K", "M", "G", "T"]),
+        SizeFormat::Si => scaled_size(bytes, 1000.0, ["B", "KB", "MB", "GB", "TB"]),
+        SizeFormat::Bytes => bytes.to_string(),
+        SizeFormat::Blocks(block_size) => bytes.div_ceil(block_size.max(1)).to_string(),
+    }
+}
+
+fn scaled_size(bytes: u64, base: f64, suffixes: [&str; 5]) -> String {
     let mut value = bytes as f64;
     let mut idx = 0usize;
 
-    while value >= 1024.0 && idx < SUFFIXES.len() - 1 {
-        value /= 1024.0;
+    while value >= base && idx < suffixes.len() - 1 {
+        value /= base;
         idx += 1;
     }
 
     if idx == 0 {
-        format!("{:.0} {}", value, SUFFIXES[idx])
+        format!("{:.0} {}", value, suffixes[idx])
     } else {
-        format!("{:.1} {}", value, SUFFIXES[idx])
+        format!("{:.1} {}", value, suffixes[idx])
     }
 }
 
+/// Parses a size such as `512`, `1.5G`, `10 MiB` or `2GB`: a number, maybe
+/// with a fraction, and an optional unit. `K`, `M`, `G`, `T` and `P`, alone
+/// or followed by `iB`, are binary multiples, as [`print_size`] prints them;
+/// followed by `B` they are SI ones (`1KB` is 1000 bytes). A `B` alone is
+/// bytes, units are case insensitive and fractions of a byte are dropped.
+pub fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let digits = text.find(|ch: char| !(ch.is_ascii_digit() || ch == '.')).unwrap_or(text.len());
+    let (number, unit) = text.split_at(digits);
+    let unit = unit.trim_start().to_ascii_uppercase();
+    let multiplier = match unit.as_str() {
+        "" | "B" => 1,
+        _ => {
+            let exponent = "KMGTP".find(unit.get(..1)?)? as u32 + 1;
+            let base: u64 = match unit.get(1..)? {
+                "" | "IB" => 1024,
+                "B" => 1000,
+                _ => return None,
+            };
+            base.pow(exponent)
+        }
+    };
+    if let Ok(count) = number.parse::<u64>() {
+        return count.checked_mul(multiplier);
+    }
+    let bytes = number.parse::<f64>().ok()? * multiplier as f64;
+    // `u64::MAX as f64` rounds up to 2^64, which does not fit.
+    (bytes < u64::MAX as f64).then_some(bytes as u64)
+}
+
 /// Returns a normalized path by resolving `.` and `..` segments where possible.
 pub fn canonical_or_relaxed(path: &Path) -> PathBuf {
     path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
@@ -81,3 +364,219 @@ pub fn format_duration(duration: Duration) -> String {
         format!("{secs}s")
     }
 }
+
+/// Parses a human duration such as `36h`, `2w` or `1d12h`: whole numbers
+/// each followed by `s`, `m`, `h`, `d` or `w`. A bare number is a number
+/// of days, as DAYS arguments have always been.
+pub fn parse_human_duration(text: &str) -> Option<Duration> {
+    const DAY: u64 = 24 * 60 * 60;
+    let text = text.trim();
+    if let Ok(days) = text.parse::<u64>() {
+        return days.checked_mul(DAY).map(Duration::from_secs);
+    }
+    let mut total: u64 = 0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let digits = rest.len() - rest.trim_start_matches(|ch: char| ch.is_ascii_digit()).len();
+        let (count, tail) = rest.split_at(digits);
+        let mut tail = tail.chars();
+        let unit = match tail.next()? {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => DAY,
+            'w' => 7 * DAY,
+            _ => return None,
+        };
+        total = total.checked_add(count.parse::<u64>().ok()?.checked_mul(unit)?)?;
+        rest = tail.as_str();
+    }
+    (!text.is_empty()).then_some(Duration::from_secs(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn abbreviates_paths_below_home() {
+        let home = Some(Path::new("/home/me"));
+        assert_eq!(abbreviate_home(Path::new("/home/me/notes.txt"), home), Path::new("~/notes.txt"));
+        assert_eq!(abbreviate_home(Path::new("/home/me"), home), Path::new("~"));
+        assert_eq!(abbreviate_home(Path::new("/home/meow/x"), home), Path::new("/home/meow/x"));
+        assert_eq!(abbreviate_home(Path::new("/etc/x"), Some(Path::new("/"))), Path::new("/etc/x"));
+        assert_eq!(abbreviate_home(Path::new("/home/me/x"), None), Path::new("/home/me/x"));
+    }
+
+    #[test]
+    fn expands_tilde_and_variables() {
+        let environ = crate::environment::MapEnvironment::new(Default::default(), 1000)
+            .with_var("HOME", "/home/alice")
+            .with_var("DATA", "/data");
+        let expanded = |path: &str| expand_path(Path::new(path), &environ);
+
+        assert_eq!(expanded("~"), Path::new("/home/alice"));
+        assert_eq!(expanded("~/Trash"), Path::new("/home/alice/Trash"));
+        assert_eq!(expanded("~bob/Trash"), Path::new("~bob/Trash"));
+        assert_eq!(expanded("$DATA/.Trash-1000"), Path::new("/data/.Trash-1000"));
+        assert_eq!(expanded("${DATA}2/Trash"), Path::new("/data2/Trash"));
+        assert_eq!(expanded("/srv/$UNSET/${UNSET}/$"), Path::new("/srv/$UNSET/${UNSET}/$"));
+        assert_eq!(expanded("/srv/${DATA"), Path::new("/srv/${DATA"));
+        assert_eq!(expanded("/srv/a~b"), Path::new("/srv/a~b"));
+    }
+
+    #[test]
+    fn escapes_control_characters() {
+        let escaped = |text: &str| escape_control(OsStr::new(text)).to_string_lossy().into_owned();
+        assert_eq!(escaped("plain name.txt"), "plain name.txt");
+        assert_eq!(escaped("two\nlines"), "two\\nlines");
+        assert_eq!(escaped("\u{1b}[31mred"), "\\033[31mred");
+        assert_eq!(escaped("back\\slash"), "back\\\\slash");
+        assert_eq!(escaped("csi\u{9b}2J"), "csi\\302\\2332J");
+        assert_eq!(escaped("caf\u{e9}"), "caf\u{e9}");
+    }
+
+    #[test]
+    fn percent_encodes_all_but_unreserved_and_kept_bytes() {
+        assert_eq!(percent_encode(b"/tmp/a b+c", b"/"), "/tmp/a%20b%2Bc");
+        assert_eq!(percent_encode(b"Az09-._~", b""), "Az09-._~");
+        assert_eq!(percent_encode("caf\u{e9}".as_bytes(), b"/"), "caf%C3%A9");
+        assert_eq!(percent_encode(b"a/b", b""), "a%2Fb");
+    }
+
+    #[test]
+    fn percent_decodes_what_it_encodes() {
+        for bytes in [&b"/tmp/a b%c"[..], b"\xff\x00\n", "caf\u{e9}/\u{1b}[0m".as_bytes(), b""] {
+            assert_eq!(percent_decode(&percent_encode(bytes, b"/")), bytes);
+        }
+        assert_eq!(percent_decode("%2f%2F"), b"//");
+    }
+
+    #[test]
+    fn keeps_invalid_percent_escapes() {
+        assert_eq!(percent_decode("100%zz"), b"100%zz");
+        assert_eq!(percent_decode("a%4"), b"a%4");
+        assert_eq!(percent_decode("%"), b"%");
+        assert_eq!(percent_decode("%g1%41"), b"%g1A");
+    }
+
+    #[test]
+    fn numbers_collisions_before_the_extension() {
+        let numbered = |name: &str, index| numbered_basename(OsStr::new(name), index).into_string().unwrap();
+        assert_eq!(numbered("report.pdf", 0), "report.pdf");
+        assert_eq!(numbered("report.pdf", 1), "report_1.pdf");
+        assert_eq!(numbered("report.pdf", 12), "report_12.pdf");
+        assert_eq!(numbered("archive.tar.gz", 2), "archive.tar_2.gz");
+    }
+
+    #[test]
+    fn numbers_dotfiles_and_names_without_extension_at_the_end() {
+        let numbered = |name: &str, index| numbered_basename(OsStr::new(name), index).into_string().unwrap();
+        assert_eq!(numbered("README", 1), "README_1");
+        assert_eq!(numbered(".bashrc", 1), ".bashrc_1");
+        assert_eq!(numbered(".config.toml", 1), ".config_1.toml");
+        assert_eq!(numbered("draft.", 3), "draft._3");
+    }
+
+    #[test]
+    fn parses_spec_deletion_dates_as_local_time() {
+        let local = Local.with_ymd_and_hms(2024, 5, 1, 10, 11, 12).earliest().unwrap();
+        assert_eq!(parse_trash_datetime("2024-05-01T10:11:12"), Some(local.with_timezone(&Utc)));
+        assert_eq!(parse_trash_datetime("2024-05-01 10:11:12"), Some(local.with_timezone(&Utc)));
+    }
+
+    #[test]
+    fn parses_fractional_seconds() {
+        let date = parse_trash_datetime("2024-05-01T10:11:12.345").unwrap();
+        assert_eq!(date.timestamp_subsec_millis(), 345);
+        assert!(parse_trash_datetime("2024-05-01 10:11:12.5+02:00").is_some());
+    }
+
+    #[test]
+    fn parses_offsets() {
+        let expected = Utc.with_ymd_and_hms(2024, 5, 1, 8, 11, 12).unwrap();
+        assert_eq!(parse_trash_datetime("2024-05-01T10:11:12+02:00"), Some(expected));
+        assert_eq!(parse_trash_datetime("2024-05-01T10:11:12+0200"), Some(expected));
+        assert_eq!(parse_trash_datetime("2024-05-01 08:11:12Z"), Some(expected));
+    }
+
+    #[test]
+    fn rejects_malformed_dates() {
+        assert_eq!(parse_trash_datetime(""), None);
+        assert_eq!(parse_trash_datetime("2024-05-01"), None);
+        assert_eq!(parse_trash_datetime("2024-13-01T10:11:12"), None);
+        assert_eq!(parse_trash_datetime("yesterday"), None);
+    }
+
+    #[test]
+    fn parses_byte_counts() {
+        assert_eq!(parse_size("0"), Some(0));
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("512B"), Some(512));
+        assert_eq!(parse_size(" 512 b "), Some(512));
+    }
+
+    #[test]
+    fn parses_binary_suffixes() {
+        assert_eq!(parse_size("1K"), Some(1024));
+        assert_eq!(parse_size("10MiB"), Some(10 << 20));
+        assert_eq!(parse_size("1.5G"), Some(3 << 29));
+        assert_eq!(parse_size("2 t"), Some(2 << 40));
+        assert_eq!(parse_size("1P"), Some(1 << 50));
+    }
+
+    #[test]
+    fn parses_si_suffixes() {
+        assert_eq!(parse_size("1KB"), Some(1000));
+        assert_eq!(parse_size("1.5GB"), Some(1_500_000_000));
+        assert_eq!(parse_size("3 mb"), Some(3_000_000));
+    }
+
+    #[test]
+    fn rejects_malformed_sizes() {
+        for text in ["", "G", ".", "1.2.3", "10X", "-1", "1KiBB", "1 B B", "1e3"] {
+            assert_eq!(parse_size(text), None, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn rejects_sizes_that_overflow() {
+        assert_eq!(parse_size("18446744073709551616"), None);
+        assert_eq!(parse_size("16384P"), None);
+        assert_eq!(parse_size("16384.5P"), None);
+        assert_eq!(parse_size("18446744073709551615"), Some(u64::MAX));
+    }
+
+    #[test]
+    fn formats_sizes_like_du() {
+        assert_eq!(format_size(1500, SizeFormat::Si), "1.5 KB");
+        assert_eq!(format_size(999, SizeFormat::Si), "999 B");
+        assert_eq!(format_size(1500, SizeFormat::Bytes), "1500");
+        assert_eq!(format_size(1500, SizeFormat::Blocks(1024)), "2");
+        assert_eq!(format_size(2048, SizeFormat::Blocks(1024)), "2");
+        assert_eq!(format_size(0, SizeFormat::Blocks(512)), "0");
+    }
+
+    #[test]
+    fn parses_what_si_format_prints() {
+        for bytes in [0, 1000, 1_500_000, 2_000_000_000] {
+            assert_eq!(parse_size(&format_size(bytes, SizeFormat::Si)), Some(bytes));
+        }
+    }
+
+    #[test]
+    fn parses_what_print_size_prints() {
+        for text in ["0 B", "512 B", "1023 B", "1.0 K", "1.5 K", "10.0 M", "1.5 G", "2.3 T"] {
+            assert_eq!(print_size(parse_size(text).unwrap()), text);
+        }
+    }
+
+    #[test]
+    fn round_trips_sizes_within_print_size_precision() {
+        for bytes in [0, 1, 1023, 1024, 1536, 999_999, 123_456_789, 5 << 30, 7 << 40, u64::MAX / 4096] {
+            let parsed = parse_size(&print_size(bytes)).unwrap();
+            // One decimal of a value of at least 1 is within 5% of it.
+            assert!(parsed.abs_diff(bytes) <= bytes / 20, "{} came back as {}", bytes, parsed);
+        }
+    }
+}
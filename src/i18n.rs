@@ -0,0 +1,287 @@
+//! Translation of user-facing messages through gettext `.po` catalogs.
+//!
+//! The catalog of the `trash-cli` text domain is looked up like gettext
+//! does, as `LOCALEDIR/LANG/LC_MESSAGES/trash-cli.po`, for each language of
+//! `$LANGUAGE` and then the first of `$LC_ALL`, `$LC_MESSAGES` and `$LANG`
+//! (trying `de_DE.UTF-8`, then `de_DE`, then `de`). `LOCALEDIR` is
+//! `$TRASH_CLI_LOCALEDIR`, or `/usr/share/locale`.
+//!
+//! Message ids are the English texts; `{}` in a message stands for an
+//! argument, filled in order by [`tr_format`]. Without a catalog, or for
+//! messages it does not translate, the English text is used. The messages
+//! are listed in `po/trash-cli.pot`, which `scripts/update-pot` extracts
+//! from the sources.
+
+use crate::environment::{Environment, RealEnvironment, LANG, LANGUAGE, LC_ALL, LC_MESSAGES, TRASH_CLI_LOCALEDIR};
+use crate::errors::CoreError;
+use crate::fs::{FileSystem, RealFileSystem};
+use crate::log;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// gettext text domain of the messages.
+pub const TEXT_DOMAIN: &str = "trash-cli";
+
+/// Where catalogs are installed when `$TRASH_CLI_LOCALEDIR` is unset.
+pub const DEFAULT_LOCALE_DIR: &str = "/usr/share/locale";
+
+/// Translations of one language, by message id.
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    messages: HashMap<String, String>,
+}
+
+impl Catalog {
+    /// The catalog for the user's language, or an empty one when none is
+    /// installed. A catalog that cannot be parsed is reported and skipped.
+    pub fn load(fs: &dyn FileSystem, environ: &dyn Environment) -> Self {
+        for path in catalog_paths(environ) {
+            if !fs.exists(&path) {
+                continue;
+            }
+            match fs.read_to_string(&path).and_then(|text| Self::parse_po(&text)) {
+                Ok(catalog) => {
+                    log::debug("i18n", format_args!("using message catalog {}", path.display()));
+                    return catalog;
+                }
+                Err(err) => log::warn("i18n", format_args!("ignoring message catalog {}: {}", path.display(), err)),
+            }
+        }
+        Self::default()
+    }
+
+    /// Parses the text of a `.po` file. Fuzzy and untranslated entries are
+    /// left out, as gettext does; for plural entries only the singular form
+    /// is kept.
+    pub fn parse_po(text: &str) -> crate::Result<Self> {
+        let mut messages = HashMap::new();
+        let mut entry = PoEntry::default();
+        let mut field: Option<PoField> = None;
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let invalid = |message: &str| CoreError::invalid_input(format!("line {}: {}", index + 1, message));
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(flags) = line.strip_prefix("#,") {
+                entry.finish(&mut messages);
+                field = None;
+                entry.fuzzy = flags.split(',').any(|flag| flag.trim() == "fuzzy");
+                continue;
+            }
+            if line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('"') {
+                let text = unquote(line).ok_or_else(|| invalid("invalid string"))?;
+                let Some(field) = field else {
+                    return Err(invalid("string outside of an entry"));
+                };
+                if let Some(target) = entry.field(field) {
+                    target.push_str(&text);
+                }
+                continue;
+            }
+            let (keyword, rest) = line.split_once(char::is_whitespace).ok_or_else(|| invalid("expected a string"))?;
+            let next = match keyword {
+                "msgctxt" => PoField::Context,
+                "msgid" => PoField::Id,
+                "msgid_plural" => PoField::Plural,
+                "msgstr" | "msgstr[0]" => PoField::Translation,
+                keyword if keyword.starts_with("msgstr[") => PoField::OtherForm,
+                _ => return Err(invalid("unknown keyword")),
+            };
+            // A msgctxt or msgid after a msgstr starts the next entry.
+            if matches!(next, PoField::Context | PoField::Id) && entry.has_translation {
+                entry.finish(&mut messages);
+            }
+            if next == PoField::Translation {
+                entry.has_translation = true;
+            }
+            let text = unquote(rest.trim()).ok_or_else(|| invalid("invalid string"))?;
+            if let Some(target) = entry.field(next) {
+                target.push_str(&text);
+            }
+            field = Some(next);
+        }
+        entry.finish(&mut messages);
+        Ok(Catalog { messages })
+    }
+
+    /// The translation of `msgid`, or `msgid` itself.
+    pub fn get<'a>(&'a self, msgid: &'a str) -> &'a str {
+        self.messages.get(msgid).map(String::as_str).unwrap_or(msgid)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum PoField {
+    Context,
+    Id,
+    Plural,
+    Translation,
+    OtherForm,
+}
+
+#[derive(Debug, Default)]
+struct PoEntry {
+    context: Option<String>,
+    id: String,
+    translation: String,
+    has_translation: bool,
+    fuzzy: bool,
+}
+
+impl PoEntry {
+    /// The text being built for `field`; `None` for the plural forms,
+    /// which are not kept.
+    fn field(&mut self, field: PoField) -> Option<&mut String> {
+        match field {
+            PoField::Context => Some(self.context.get_or_insert_with(String::new)),
+            PoField::Id => Some(&mut self.id),
+            PoField::Translation => Some(&mut self.translation),
+            PoField::Plural | PoField::OtherForm => None,
+        }
+    }
+
+    /// Adds the entry to `messages` (unless it is the header, fuzzy or
+    /// untranslated) and starts a new one.
+    fn finish(&mut self, messages: &mut HashMap<String, String>) {
+        let entry = std::mem::take(self);
+        if entry.id.is_empty() || entry.translation.is_empty() || entry.fuzzy {
+            return;
+        }
+        // gettext keys messages with a context as "CONTEXT\x04MSGID".
+        let key = match entry.context {
+            Some(context) => format!("{}\u{4}{}", context, entry.id),
+            None => entry.id,
+        };
+        messages.insert(key, entry.translation);
+    }
+}
+
+/// The C-style string literal `line`, unescaped.
+fn unquote(line: &str) -> Option<String> {
+    let inner = line.strip_prefix('"')?.strip_suffix('"')?;
+    let mut text = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            text.push(ch);
+            continue;
+        }
+        match chars.next()? {
+            'n' => text.push('\n'),
+            't' => text.push('\t'),
+            'r' => text.push('\r'),
+            '"' => text.push('"'),
+            '\\' => text.push('\\'),
+            'a' => text.push('\u{7}'),
+            'b' => text.push('\u{8}'),
+            'f' => text.push('\u{c}'),
+            'v' => text.push('\u{b}'),
+            _ => return None,
+        }
+    }
+    Some(text)
+}
+
+/// Languages to try, most preferred first: `$LANGUAGE` (ignored for the C
+/// locale, as gettext does) and then the locale of the messages.
+pub fn languages(environ: &dyn Environment) -> Vec<String> {
+    let locale = [LC_ALL, LC_MESSAGES, LANG]
+        .iter()
+        .filter_map(|var| environ.var(var.name))
+        .find(|value| !value.is_empty());
+    let Some(locale) = locale else {
+        return Vec::new();
+    };
+    if locale == "C" || locale == "POSIX" || locale.starts_with("C.") {
+        return Vec::new();
+    }
+    let mut languages: Vec<String> = environ
+        .var(LANGUAGE.name)
+        .unwrap_or_default()
+        .split(':')
+        .filter(|language| !language.is_empty())
+        .map(str::to_string)
+        .collect();
+    languages.push(locale);
+    languages
+}
+
+/// `de_DE.UTF-8@euro`, `de_DE.UTF-8`, `de_DE@euro`, `de_DE`, `de`: the
+/// names gettext tries for a language, without duplicates.
+fn language_variants(language: &str) -> Vec<String> {
+    let (base, modifier) = match language.split_once('@') {
+        Some((base, modifier)) => (base, Some(modifier)),
+        None => (language, None),
+    };
+    let without_codeset = base.split('.').next().unwrap_or(base);
+    let without_territory = without_codeset.split('_').next().unwrap_or(without_codeset);
+    let mut variants: Vec<String> = Vec::new();
+    for name in [base, without_codeset, without_territory] {
+        let mut candidates = Vec::new();
+        if let Some(modifier) = modifier {
+            candidates.push(format!("{}@{}", name, modifier));
+        }
+        candidates.push(name.to_string());
+        for candidate in candidates {
+            if !variants.contains(&candidate) {
+                variants.push(candidate);
+            }
+        }
+    }
+    variants
+}
+
+/// Where the catalog may be, most preferred first.
+pub fn catalog_paths(environ: &dyn Environment) -> Vec<PathBuf> {
+    let locale_dir = environ
+        .var(TRASH_CLI_LOCALEDIR.name)
+        .filter(|dir| !dir.is_empty())
+        .unwrap_or_else(|| DEFAULT_LOCALE_DIR.to_string());
+    languages(environ)
+        .iter()
+        .flat_map(|language| language_variants(language))
+        .map(|language| {
+            PathBuf::from(&locale_dir)
+                .join(language)
+                .join("LC_MESSAGES")
+                .join(format!("{}.po", TEXT_DOMAIN))
+        })
+        .collect()
+}
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+/// The catalog of the running process, loaded on first use.
+pub fn catalog() -> &'static Catalog {
+    CATALOG.get_or_init(|| Catalog::load(&RealFileSystem, &RealEnvironment))
+}
+
+/// The translation of `msgid` in the user's language.
+pub fn tr(msgid: &'static str) -> &'static str {
+    catalog().get(msgid)
+}
+
+/// The translation of `msgid` with each `{}` replaced by the next of
+/// `args`.
+pub fn tr_format(msgid: &'static str, args: &[&dyn Display]) -> String {
+    let mut args = args.iter();
+    let mut parts = tr(msgid).split("{}");
+    let mut text = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        if let Some(arg) = args.next() {
+            text.push_str(&arg.to_string());
+        }
+        text.push_str(part);
+    }
+    text
+}
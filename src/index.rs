@@ -0,0 +1,181 @@
+//! Optional per-trash-dir index of trashinfo contents, so listing a trash
+//! with hundreds of thousands of items reads one file instead of opening
+//! every `.trashinfo`.
+//!
+//! The index is `$trash/trash-cli.index`: a header line, then one line per
+//! item of the form `PERCENT-ENCODED-NAME TAB PERCENT-ENCODED-CONTENT`,
+//! where `NAME` is the entry shared by `files/NAME` and
+//! `info/NAME.trashinfo`. put, rm and empty keep an existing index up to
+//! date; readers go through [`TrashIndex::refresh`], which compares it with
+//! the names in `info/` and reads only the trashinfo files it is missing,
+//! so an index left stale by another tool is repaired on the next read.
+
+use crate::errors::CoreError;
+use crate::fs::FileSystem;
+use crate::helpers::{percent_decode, percent_encode, TRASHINFO_EXTENSION};
+use crate::log;
+use crate::trashinfo::{path_bytes, path_from_bytes};
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+/// File name of the index inside a trash directory.
+pub const INDEX_FILE: &str = "trash-cli.index";
+
+/// First line of the index, naming its format version.
+const HEADER: &str = "# trash-cli index 1";
+
+/// The trashinfo contents of a trash dir, by entry name.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct TrashIndex {
+    entries: BTreeMap<OsString, String>,
+}
+
+impl TrashIndex {
+    pub fn path_for(trash_dir: &Path) -> PathBuf {
+        trash_dir.join(INDEX_FILE)
+    }
+
+    /// The index of `trash_dir`, or `None` when it has none.
+    pub fn load(fs: &dyn FileSystem, trash_dir: &Path) -> crate::Result<Option<Self>> {
+        let path = Self::path_for(trash_dir);
+        if !fs.exists(&path) {
+            return Ok(None);
+        }
+        Self::parse(&fs.read_to_string(&path)?).map(Some)
+    }
+
+    /// Parses the on-disk format; an unknown version is an error.
+    pub fn parse(text: &str) -> crate::Result<Self> {
+        let mut lines = text.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(CoreError::invalid_input("unknown index format"));
+        }
+        let mut entries = BTreeMap::new();
+        for (index, line) in lines.enumerate() {
+            let invalid = || CoreError::invalid_input(format!("line {}: invalid entry", index + 2));
+            let (name, content) = line.split_once('\t').ok_or_else(invalid)?;
+            let name = path_from_bytes(percent_decode(name));
+            let content = String::from_utf8(percent_decode(content)).map_err(|_| invalid())?;
+            entries.insert(name.into_os_string(), content);
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn render(&self) -> String {
+        let mut text = format!("{}\n", HEADER);
+        for (name, content) in &self.entries {
+            text.push_str(&format!(
+                "{}\t{}\n",
+                percent_encode(&path_bytes(Path::new(name)), b""),
+                percent_encode(content.as_bytes(), b"")
+            ));
+        }
+        text
+    }
+
+    /// Writes the index back atomically (temporary file + rename).
+    pub fn save(&self, fs: &dyn FileSystem, trash_dir: &Path) -> crate::Result<()> {
+        let tmp = trash_dir.join(format!(".{}.{}.tmp", INDEX_FILE, std::process::id()));
+        fs.write_to_string(&tmp, &self.render())?;
+        fs.rename(&tmp, &Self::path_for(trash_dir)).inspect_err(|_| {
+            let _ = fs.remove_file(&tmp);
+        })
+    }
+
+    pub fn get(&self, name: &OsStr) -> Option<&str> {
+        self.entries.get(name).map(String::as_str)
+    }
+
+    pub fn insert(&mut self, name: impl Into<OsString>, content: impl Into<String>) {
+        self.entries.insert(name.into(), content.into());
+    }
+
+    /// Forgets `name`, returning whether it was present.
+    pub fn remove(&mut self, name: &OsStr) -> bool {
+        self.entries.remove(name).is_some()
+    }
+
+    /// Drops the entries whose trashinfo file is gone.
+    pub fn retain_existing(&mut self, fs: &dyn FileSystem, trash_dir: &Path) {
+        let info_dir = trash_dir.join("info");
+        self.entries.retain(|name, _| {
+            let mut file_name = name.clone();
+            file_name.push(TRASHINFO_EXTENSION);
+            fs.exists(&info_dir.join(file_name))
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Applies `change` to the index of `trash_dir` and saves it, when the
+    /// trash dir has one. Used by the commands that add or remove items; a
+    /// failure only leaves the index stale, so it is logged and ignored.
+    pub fn update(fs: &dyn FileSystem, trash_dir: &Path, change: impl FnOnce(&mut Self)) {
+        let updated = Self::load(fs, trash_dir).and_then(|index| match index {
+            Some(mut index) => {
+                change(&mut index);
+                index.save(fs, trash_dir)
+            }
+            None => Ok(()),
+        });
+        if let Err(err) = updated {
+            log::warn("index", format_args!("cannot update the index of {}: {}", trash_dir.display(), err));
+        }
+    }
+
+    /// The `.trashinfo` files of `trash_dir` in directory order, with their
+    /// contents taken from the index when it has them and read otherwise.
+    ///
+    /// The index is created when missing and brought in line with `info/`
+    /// (new entries read, removed ones dropped) and saved when it changed.
+    pub fn refresh(fs: &dyn FileSystem, trash_dir: &Path) -> crate::Result<Vec<IndexedTrashinfo>> {
+        let stored = Self::load(fs, trash_dir).unwrap_or_else(|err| {
+            log::info("index", format_args!("rebuilding the index of {}: {}", trash_dir.display(), err));
+            None
+        });
+        let mut changed = stored.is_none();
+        let mut stored = stored.unwrap_or_default();
+        let mut fresh = Self::default();
+        let mut trashinfos = Vec::new();
+        for entry in fs.read_dir_iter(&trash_dir.join("info"))? {
+            let path = entry?.path;
+            let file_name = path_bytes(Path::new(path.file_name().unwrap_or_default()));
+            let Some(name) = file_name.strip_suffix(TRASHINFO_EXTENSION.as_bytes()) else {
+                log::debug("index", format_args!("skipping {}: not a .trashinfo file", path.display()));
+                continue;
+            };
+            let name = path_from_bytes(name.to_vec()).into_os_string();
+            let content = match stored.entries.remove(&name) {
+                Some(content) => Ok(content),
+                None => fs.read_to_string(&path).inspect(|_| changed = true),
+            };
+            if let Ok(content) = &content {
+                fresh.insert(name, content.clone());
+            }
+            trashinfos.push(IndexedTrashinfo { path, content });
+        }
+        // What is left of the stored entries is gone from `info/`.
+        if changed || !stored.is_empty() {
+            log::debug("index", format_args!("saving the index of {}", trash_dir.display()));
+            if let Err(err) = fresh.save(fs, trash_dir) {
+                log::warn("index", format_args!("cannot save the index of {}: {}", trash_dir.display(), err));
+            }
+        }
+        Ok(trashinfos)
+    }
+}
+
+/// A `.trashinfo` file and its content, as returned by
+/// [`TrashIndex::refresh`].
+#[derive(Debug)]
+pub struct IndexedTrashinfo {
+    pub path: PathBuf,
+    pub content: crate::Result<String>,
+}
@@ -0,0 +1,242 @@
+//! Append-only journal of the items moved by put, restore and empty, read
+//! back by `trash-undo`.
+//!
+//! The journal is `$XDG_STATE_HOME/trash-cli/journal` (by default
+//! `~/.local/state/trash-cli/journal`). Each line is one record of
+//! tab-separated fields, paths and texts percent-encoded:
+//!
+//! ```text
+//! BATCH  put|restore|empty  TRASH_DIR  NAME  PATH  TRASHINFO
+//! BATCH  undo  UNDONE_BATCH
+//! ```
+//!
+//! A batch is one command invocation; `NAME` is the entry shared by
+//! `files/NAME` and `info/NAME.trashinfo`, `PATH` where the item lives
+//! outside of the trash and `TRASHINFO` the content of its trashinfo file.
+//! An `undo` record marks a batch as undone.
+
+use crate::environment::Environment;
+use crate::fs::FileSystem;
+use crate::helpers::{percent_decode, percent_encode, TRASHINFO_EXTENSION};
+use crate::log;
+use crate::trashinfo::{path_bytes, path_from_bytes};
+use chrono::Local;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Path of the journal, relative to the XDG state directory.
+pub const JOURNAL_FILE: &str = "trash-cli/journal";
+
+/// What happened to a journaled item.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Operation {
+    /// Moved from `path` into the trash.
+    Put,
+    /// Moved from the trash back to `path`.
+    Restore,
+    /// Removed from the trash for good.
+    Empty,
+}
+
+impl Operation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Put => "put",
+            Self::Restore => "restore",
+            Self::Empty => "empty",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "put" => Some(Self::Put),
+            "restore" => Some(Self::Restore),
+            "empty" => Some(Self::Empty),
+            _ => None,
+        }
+    }
+}
+
+/// One item moved by an operation.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct JournalItem {
+    pub operation: Operation,
+    pub trash_dir: PathBuf,
+    pub name: OsString,
+    /// Where the item lives outside of the trash; empty when not known.
+    pub path: PathBuf,
+    /// Content of the item's trashinfo file.
+    pub trashinfo: String,
+}
+
+impl JournalItem {
+    pub fn backup_path(&self) -> PathBuf {
+        self.trash_dir.join("files").join(&self.name)
+    }
+
+    pub fn trashinfo_path(&self) -> PathBuf {
+        let mut name = self.name.clone();
+        name.push(TRASHINFO_EXTENSION);
+        self.trash_dir.join("info").join(name)
+    }
+}
+
+/// A line of the journal.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Record {
+    Item(JournalItem),
+    /// The batch named was undone.
+    Undo(String),
+}
+
+/// The items of one batch, in the order they were recorded.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Batch {
+    pub id: String,
+    pub items: Vec<JournalItem>,
+}
+
+/// The journal, and the batch of the running command.
+#[derive(Debug, Clone)]
+pub struct Journal {
+    /// `None` when no state directory can be found; nothing is recorded.
+    path: Option<PathBuf>,
+    batch: String,
+}
+
+impl Journal {
+    /// The journal of the user, with a new batch for this process.
+    pub fn open(environ: &dyn Environment) -> Self {
+        Self {
+            path: Self::path_for(environ),
+            batch: format!("{}.{}", Local::now().format("%Y%m%dT%H%M%S%.6f"), std::process::id()),
+        }
+    }
+
+    pub fn path_for(environ: &dyn Environment) -> Option<PathBuf> {
+        environ.state_home().map(|state_home| state_home.join(JOURNAL_FILE))
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn batch(&self) -> &str {
+        &self.batch
+    }
+
+    /// Appends `record` to the current batch. The journal is an aid, not a
+    /// requirement: a failure is logged and otherwise ignored.
+    pub fn record(&self, fs: &dyn FileSystem, record: &Record) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let written = path
+            .parent()
+            .map_or(Ok(()), |parent| fs.create_dir_all(parent))
+            .and_then(|()| fs.append(path, format_record(&self.batch, record).as_bytes()));
+        if let Err(err) = written {
+            log::warn("journal", format_args!("cannot record in {}: {}", path.display(), err));
+        }
+    }
+
+    /// Every record of the journal with its batch, oldest first. A missing
+    /// journal is empty; unreadable lines are skipped.
+    pub fn read(&self, fs: &dyn FileSystem) -> crate::Result<Vec<(String, Record)>> {
+        let Some(path) = &self.path else {
+            return Ok(Vec::new());
+        };
+        if !fs.exists(path) {
+            return Ok(Vec::new());
+        }
+        let mut records = Vec::new();
+        for (index, line) in fs.read_to_string(path)?.lines().enumerate() {
+            match parse_record(line) {
+                Some(record) => records.push(record),
+                None => log::warn("journal", format_args!("{}:{}: invalid record", path.display(), index + 1)),
+            }
+        }
+        Ok(records)
+    }
+
+    /// The most recent batch of one of `operations` that was not undone,
+    /// without the current one.
+    pub fn last_batch(&self, fs: &dyn FileSystem, operations: &[Operation]) -> crate::Result<Option<Batch>> {
+        let records = self.read(fs)?;
+        let undone: Vec<&str> = records
+            .iter()
+            .filter_map(|(_, record)| match record {
+                Record::Undo(batch) => Some(batch.as_str()),
+                Record::Item(_) => None,
+            })
+            .collect();
+        let Some(id) = records
+            .iter()
+            .rev()
+            .filter(|(batch, record)| match record {
+                Record::Item(item) => operations.contains(&item.operation) && *batch != self.batch,
+                Record::Undo(_) => false,
+            })
+            .map(|(batch, _)| batch.as_str())
+            .find(|batch| !undone.contains(batch))
+        else {
+            return Ok(None);
+        };
+        let items = records
+            .iter()
+            .filter(|(batch, _)| batch == id)
+            .filter_map(|(_, record)| match record {
+                Record::Item(item) => Some(item.clone()),
+                Record::Undo(_) => None,
+            })
+            .collect();
+        Ok(Some(Batch {
+            id: id.to_string(),
+            items,
+        }))
+    }
+}
+
+fn format_record(batch: &str, record: &Record) -> String {
+    match record {
+        Record::Item(item) => format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\n",
+            percent_encode(batch.as_bytes(), b""),
+            item.operation.as_str(),
+            percent_encode(&path_bytes(&item.trash_dir), b""),
+            percent_encode(&path_bytes(Path::new(&item.name)), b""),
+            percent_encode(&path_bytes(&item.path), b""),
+            percent_encode(item.trashinfo.as_bytes(), b""),
+        ),
+        Record::Undo(undone) => format!(
+            "{}\tundo\t{}\n",
+            percent_encode(batch.as_bytes(), b""),
+            percent_encode(undone.as_bytes(), b"")
+        ),
+    }
+}
+
+fn parse_record(line: &str) -> Option<(String, Record)> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    let batch = decode_text(fields.first()?)?;
+    let record = match fields.as_slice() {
+        [_, "undo", undone] => Record::Undo(decode_text(undone)?),
+        [_, operation, trash_dir, name, path, trashinfo] => Record::Item(JournalItem {
+            operation: Operation::parse(operation)?,
+            trash_dir: decode_path(trash_dir),
+            name: decode_path(name).into_os_string(),
+            path: decode_path(path),
+            trashinfo: decode_text(trashinfo)?,
+        }),
+        _ => return None,
+    };
+    Some((batch, record))
+}
+
+fn decode_path(field: &str) -> PathBuf {
+    path_from_bytes(percent_decode(field))
+}
+
+fn decode_text(field: &str) -> Option<String> {
+    String::from_utf8(percent_decode(field)).ok()
+}
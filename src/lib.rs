@@ -2,58 +2,227 @@
 //! This crate intentionally stays dependency-light and focuses on stable,
 //! reusable primitives that can be consumed by command-specific crates.
 
+pub mod api;
+pub mod audit;
+pub mod cancel;
+pub mod cli;
+pub mod config;
+pub mod directory_sizes;
+pub mod environment;
 pub mod errors;
+pub mod exit_codes;
 pub mod fs;
+pub mod glob;
 pub mod helpers;
+pub mod i18n;
+pub mod index;
+pub mod journal;
+pub mod log;
 pub mod models;
+pub mod mounts;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+pub mod notify;
+pub mod output;
+pub mod progress;
+pub mod put;
+pub mod quota;
+pub mod restore;
+pub mod sorting;
+pub mod terminal;
+pub mod transaction;
+pub mod trash_dirs;
+pub mod trashinfo;
+pub mod users;
+pub mod version;
 
-pub use errors::{CoreError, Result};
-pub use fs::{FileSystem, RealFileSystem};
+pub use api::{empty, list_trashed_items, purge, restore, trash, ListOptions};
+pub use audit::{AuditIssue, TrashAudit};
+pub use cancel::CancellationToken;
+pub use config::{ColorChoice, Config, PurgePolicy, CONFIG_FILE};
+pub use directory_sizes::{DirectorySizeEntry, DirectorySizes, DIRECTORY_SIZES_FILE};
+pub use environment::{describe_environment, EnvVar, Environment, MapEnvironment, RealEnvironment, ENV_VARS};
+pub use errors::{CoreError, ErrorCategory, Result, ResultExt};
+pub use exit_codes::ExitCodes;
+pub use fs::{
+    copy_tree,
+    copy_tree_cancellable,
+    copy_tree_with_progress,
+    copy_xattrs,
+    recursive_size,
+    DirEntries,
+    DirEntryInfo,
+    FileSystem,
+    RealFileSystem,
+    SizeStat,
+    SpaceUsage,
+};
+pub use glob::{fnmatch, GlobPattern, MatchOptions};
 pub use helpers::{
+    abbreviate_home,
     build_unique_basename,
+    escape_control,
+    expand_path,
+    format_datetime,
+    format_size,
+    numbered_basename,
+    parse_human_duration,
+    parse_size,
     parse_trash_datetime,
+    percent_decode,
+    percent_encode,
     print_size,
+    printable_path,
     sanitize_user_path,
     serialize_system_time,
+    terminal_path,
+    DisplayZone,
+    SizeFormat,
+    DISPLAY_TIME_FORMAT,
     TRASHINFO_EXTENSION,
     TRASHINFO_TIME_FORMAT,
 };
+pub use i18n::{tr, tr_format, Catalog};
+pub use index::{IndexedTrashinfo, TrashIndex, INDEX_FILE};
+pub use journal::{Batch, Journal, JournalItem, Operation, Record, JOURNAL_FILE};
 pub use models::{
     CommandContext,
     CommandKind,
     CommandOutcome,
     CommandOutput,
     ExitStatusLike,
+    OperationReport,
     SkipReason,
     TrashCommand,
     TrashDirectory,
+    TrashStats,
     TrashedItem,
 };
+pub use mounts::{
+    distinct_mounts, list_distinct_mount_points, list_mount_points, list_volumes, mount_for, mount_point_of,
+    list_mount_entries, parse_mountinfo, storage_volumes, volume_of, MountEntry, NetworkFilter,
+};
+pub use notify::notify;
+pub use output::{format_row, parse_fields, Field, FieldSpec, FIELDS};
+pub use progress::{stderr_progress, NoProgress, Progress, TerminalProgress};
+pub use put::{TrashOptions, Trasher};
+pub use quota::QuotaPolicy;
+pub use restore::{Conflict, RestoreOptions, Restored, Restorer};
+pub use sorting::{sort_items, sort_items_by, SortKey};
+pub use terminal::{Painter, Role, Style, Theme};
+pub use transaction::TrashTransaction;
+pub use trash_dirs::{scan_trash_dirs, TrashDirEvent, TrashDirProblem};
+pub use trashinfo::{
+    create_trashinfo,
+    entry_name,
+    format_trashinfo,
+    original_location_for,
+    parse_deletion_date,
+    parse_original_location,
+    resolve_original_location,
+    FileAttributes,
+    ReservedTrashinfo,
+    TrashInfo,
+    TrashInfoError,
+    TrashInfoKeys,
+};
+pub use users::{list_users, SystemUsers, UserDatabase, UserInfo};
+pub use version::{version_line, COMPAT_VERSION};
 
 /// Re-export a small stable API surface for command crates.
 pub mod prelude {
-    pub use crate::errors::{CoreError, Result};
-    pub use crate::fs::{FileSystem, RealFileSystem};
+    pub use crate::api::{empty, list_trashed_items, purge, restore, trash, ListOptions};
+    pub use crate::audit::{AuditIssue, TrashAudit};
+    pub use crate::cancel::CancellationToken;
+    pub use crate::config::{ColorChoice, Config, PurgePolicy, CONFIG_FILE};
+    pub use crate::directory_sizes::{DirectorySizeEntry, DirectorySizes, DIRECTORY_SIZES_FILE};
+    pub use crate::environment::{describe_environment, EnvVar, Environment, MapEnvironment, RealEnvironment, ENV_VARS};
+    pub use crate::errors::{CoreError, ErrorCategory, Result, ResultExt};
+    pub use crate::exit_codes::ExitCodes;
+    pub use crate::fs::{
+        copy_tree,
+        copy_tree_cancellable,
+        copy_tree_with_progress,
+        copy_xattrs,
+        recursive_size,
+        DirEntries,
+        DirEntryInfo,
+        FileSystem,
+        RealFileSystem,
+        SizeStat,
+        SpaceUsage,
+    };
+    pub use crate::glob::{fnmatch, GlobPattern, MatchOptions};
     pub use crate::helpers::{
+        abbreviate_home,
         build_unique_basename,
+        escape_control,
+        expand_path,
+        format_datetime,
+        format_size,
+        numbered_basename,
         canonical_or_relaxed,
         format_duration,
+        parse_human_duration,
+        parse_size,
         parse_trash_datetime,
+        percent_decode,
+        percent_encode,
         print_size,
+        printable_path,
         sanitize_user_path,
         serialize_system_time,
+        terminal_path,
+        DisplayZone,
+        SizeFormat,
+        DISPLAY_TIME_FORMAT,
         TRASHINFO_EXTENSION,
         TRASHINFO_TIME_FORMAT,
     };
+    pub use crate::i18n::{tr, tr_format, Catalog};
+    pub use crate::index::{IndexedTrashinfo, TrashIndex, INDEX_FILE};
+    pub use crate::journal::{Batch, Journal, JournalItem, Operation, Record, JOURNAL_FILE};
     pub use crate::models::{
         CommandContext,
         CommandKind,
         CommandOutcome,
         CommandOutput,
         ExitStatusLike,
+        OperationReport,
         SkipReason,
         TrashCommand,
         TrashDirectory,
+        TrashStats,
         TrashedItem,
     };
+    pub use crate::mounts::{
+        distinct_mounts, list_distinct_mount_points, list_mount_points, list_volumes, mount_for, mount_point_of,
+        list_mount_entries, parse_mountinfo, storage_volumes, volume_of, MountEntry, NetworkFilter,
+    };
+    pub use crate::notify::notify;
+    pub use crate::output::{format_row, parse_fields, Field, FieldSpec, FIELDS};
+    pub use crate::progress::{stderr_progress, NoProgress, Progress, TerminalProgress};
+    pub use crate::put::{TrashOptions, Trasher};
+    pub use crate::quota::QuotaPolicy;
+    pub use crate::restore::{Conflict, RestoreOptions, Restored, Restorer};
+    pub use crate::sorting::{sort_items, sort_items_by, SortKey};
+    pub use crate::terminal::{Painter, Role, Style, Theme};
+    pub use crate::transaction::TrashTransaction;
+    pub use crate::trash_dirs::{scan_trash_dirs, TrashDirEvent, TrashDirProblem};
+    pub use crate::trashinfo::{
+        create_trashinfo,
+        entry_name,
+        format_trashinfo,
+        original_location_for,
+        parse_deletion_date,
+        parse_original_location,
+        resolve_original_location,
+        FileAttributes,
+        ReservedTrashinfo,
+        TrashInfo,
+        TrashInfoError,
+        TrashInfoKeys,
+    };
+    pub use crate::users::{list_users, SystemUsers, UserDatabase, UserInfo};
+    pub use crate::version::{version_line, COMPAT_VERSION};
 }
@@ -0,0 +1,83 @@
+//! Diagnostics written to stderr, for seeing which trash dirs a command
+//! scanned and which candidates it skipped without resorting to strace.
+//!
+//! Each `-v` of a command raises the verbosity by one [`Level`]; warnings
+//! are always shown. A record is printed as `PROG: LEVEL: TARGET: MESSAGE`,
+//! where the target names the part of the code it comes from
+//! (`trash_dirs`, `mounts`, ...).
+
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+/// Importance of a record, from always shown to shown with `-vvv`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Level {
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Warn => "warning",
+            Self::Info => "info",
+            Self::Debug => "debug",
+            Self::Trace => "trace",
+        }
+    }
+
+    /// The most verbose level shown after `verbosity` `-v` flags.
+    pub fn for_verbosity(verbosity: u32) -> Self {
+        match verbosity {
+            0 => Self::Warn,
+            1 => Self::Info,
+            2 => Self::Debug,
+            _ => Self::Trace,
+        }
+    }
+}
+
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(Level::Warn as u8);
+static PROG: OnceLock<String> = OnceLock::new();
+
+/// Names the records after `prog` and shows those up to the level of
+/// `verbosity`.
+pub fn init(prog: &str, verbosity: u32) {
+    let _ = PROG.set(prog.to_string());
+    MAX_LEVEL.store(Level::for_verbosity(verbosity) as u8, Ordering::Relaxed);
+}
+
+/// Whether records of `level` are shown; lets callers skip work only
+/// needed for a record.
+pub fn enabled(level: Level) -> bool {
+    level as u8 <= MAX_LEVEL.load(Ordering::Relaxed)
+}
+
+/// Writes a record to stderr when `level` is enabled.
+pub fn log(level: Level, target: &str, message: fmt::Arguments) {
+    if !enabled(level) {
+        return;
+    }
+    let prog = PROG.get().map(String::as_str).unwrap_or("trash-cli");
+    let _ = writeln!(io::stderr().lock(), "{}: {}: {}: {}", prog, level.as_str(), target, message);
+}
+
+pub fn warn(target: &str, message: fmt::Arguments) {
+    log(Level::Warn, target, message);
+}
+
+pub fn info(target: &str, message: fmt::Arguments) {
+    log(Level::Info, target, message);
+}
+
+pub fn debug(target: &str, message: fmt::Arguments) {
+    log(Level::Debug, target, message);
+}
+
+pub fn trace(target: &str, message: fmt::Arguments) {
+    log(Level::Trace, target, message);
+}
@@ -1,5 +1,9 @@
+use crate::exit_codes::{self, ExitCodes};
+use crate::trashinfo::path_bytes;
 use chrono::{DateTime, Utc};
-use std::path::PathBuf;
+use std::io::{self, Write};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 
 /// Command family being implemented in Rust (or wrapped).
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
@@ -9,9 +13,12 @@ pub enum CommandKind {
     Empty,
     Restore,
     Remove,
+    Undo,
 }
 
 impl CommandKind {
+    pub const ALL: [CommandKind; 6] = [Self::Put, Self::List, Self::Empty, Self::Restore, Self::Remove, Self::Undo];
+
     pub fn as_str(&self) -> &'static str {
         match self {
             Self::Put => "put",
@@ -19,6 +26,7 @@ impl CommandKind {
             Self::Empty => "empty",
             Self::Restore => "restore",
             Self::Remove => "rm",
+            Self::Undo => "undo",
         }
     }
 }
@@ -33,7 +41,7 @@ impl std::fmt::Display for CommandKind {
 #[derive(Debug, Clone)]
 pub struct CommandContext {
     pub command: CommandKind,
-    pub args: Vec<String>,
+    pub args: Vec<OsString>,
     pub cwd: PathBuf,
     pub interactive: bool,
     pub dry_run: bool,
@@ -42,13 +50,14 @@ pub struct CommandContext {
 #[derive(Debug, Clone)]
 pub struct CommandOutput {
     pub command: CommandKind,
-    pub stdout: Vec<String>,
+    /// Lines for stdout; they may hold paths, which need not be UTF-8.
+    pub stdout: Vec<OsString>,
     pub stderr: Vec<String>,
     pub exit_code: u8,
 }
 
 impl CommandOutput {
-    pub fn success(command: CommandKind, stdout: impl Into<Vec<String>>) -> Self {
+    pub fn success(command: CommandKind, stdout: impl Into<Vec<OsString>>) -> Self {
         Self {
             command,
             stdout: stdout.into(),
@@ -62,8 +71,20 @@ impl CommandOutput {
             command,
             stdout: Vec::new(),
             stderr: vec![stderr.into()],
-            exit_code: 1,
+            exit_code: ExitCodes::of(command).failure,
+        }
+    }
+
+    /// Writes the collected lines to the given stdout/stderr streams.
+    pub fn write_to(&self, stdout: &mut impl Write, stderr: &mut impl Write) -> io::Result<()> {
+        for line in &self.stdout {
+            stdout.write_all(&path_bytes(Path::new(line)))?;
+            stdout.write_all(b"\n")?;
         }
+        for line in &self.stderr {
+            writeln!(stderr, "{line}")?;
+        }
+        Ok(())
     }
 }
 
@@ -85,6 +106,14 @@ impl TrashDirectory {
         }
     }
 
+    /// Trash directory rooted at `path` with the standard `files/` and
+    /// `info/` layout.
+    pub fn at(path: PathBuf) -> Self {
+        let files_dir = path.join("files");
+        let info_dir = path.join("info");
+        Self::new(path, files_dir, info_dir)
+    }
+
     pub fn with_mount_point(mut self, mount_point: PathBuf) -> Self {
         self.mount_point = Some(mount_point);
         self
@@ -114,6 +143,52 @@ impl TrashedItem {
     }
 }
 
+/// Aggregated counters over a set of trashed items (one trash dir or all).
+#[derive(Debug, Clone, Default)]
+pub struct TrashStats {
+    pub item_count: u64,
+    pub total_size: u64,
+    /// Items whose size could not be determined (not included in `total_size`).
+    pub unknown_size_count: u64,
+    pub oldest: Option<DateTime<Utc>>,
+    pub newest: Option<DateTime<Utc>>,
+}
+
+impl TrashStats {
+    /// Accounts for a single item.
+    pub fn record(&mut self, size_bytes: Option<u64>, deleted_at: Option<DateTime<Utc>>) {
+        self.item_count += 1;
+        match size_bytes {
+            Some(size) => self.total_size = self.total_size.saturating_add(size),
+            None => self.unknown_size_count += 1,
+        }
+        if let Some(date) = deleted_at {
+            self.oldest = Some(self.oldest.map_or(date, |oldest| oldest.min(date)));
+            self.newest = Some(self.newest.map_or(date, |newest| newest.max(date)));
+        }
+    }
+
+    /// Accounts for an already-built `TrashedItem`.
+    pub fn record_item(&mut self, item: &TrashedItem) {
+        self.record(item.size_bytes, item.deleted_at);
+    }
+
+    /// Folds another aggregate into this one (e.g. per-dir into global).
+    pub fn merge(&mut self, other: &TrashStats) {
+        self.item_count += other.item_count;
+        self.total_size = self.total_size.saturating_add(other.total_size);
+        self.unknown_size_count += other.unknown_size_count;
+        for date in [other.oldest, other.newest].into_iter().flatten() {
+            self.oldest = Some(self.oldest.map_or(date, |oldest| oldest.min(date)));
+            self.newest = Some(self.newest.map_or(date, |newest| newest.max(date)));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.item_count == 0
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TrashCommand {
     pub kind: CommandKind,
@@ -127,6 +202,39 @@ pub enum SkipReason {
     AlreadyTrashed(PathBuf),
     UnsupportedScheme(String),
     InternalError(String),
+    /// `/`, a home directory, a mount point root or a trash directory,
+    /// which are only trashed when explicitly forced.
+    ProtectedPath(PathBuf),
+    /// A named pipe, socket or device node, only trashed with
+    /// `--allow-special`: renaming one into the trash works like for any
+    /// file, but a copy to another device recreates the node, which loses
+    /// the process on the other end of a pipe or socket and needs privileges
+    /// for devices.
+    UnsupportedFileType(PathBuf),
+    /// The item or the directory holding it is on a read-only filesystem,
+    /// so it cannot be moved nor copied and removed.
+    ReadOnlyFilesystem(PathBuf),
+    /// The item, or the directory holding it, is immutable or append-only,
+    /// which `chattr -i -a` clears.
+    ImmutableFile(PathBuf),
+    /// The user answered no when asked to confirm.
+    Declined(PathBuf),
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingPath(path) => write!(f, "no such file or directory: '{}'", path.display()),
+            Self::AlreadyTrashed(path) => write!(f, "already in the trash: '{}'", path.display()),
+            Self::UnsupportedScheme(scheme) => write!(f, "unsupported scheme: {scheme}"),
+            Self::InternalError(message) => write!(f, "internal error: {message}"),
+            Self::ProtectedPath(path) => write!(f, "refusing to trash protected path '{}'", path.display()),
+            Self::UnsupportedFileType(path) => write!(f, "refusing to trash special file '{}'", path.display()),
+            Self::ReadOnlyFilesystem(path) => write!(f, "'{}' is on a read-only file system", path.display()),
+            Self::ImmutableFile(path) => write!(f, "'{}' is immutable or append-only", path.display()),
+            Self::Declined(path) => write!(f, "not confirmed: '{}'", path.display()),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -143,19 +251,118 @@ pub enum CommandOutcome {
     },
 }
 
-#[derive(Debug, Clone, Copy)]
+impl CommandOutcome {
+    /// Process exit code this outcome alone would warrant; a skip is not a
+    /// failure.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::Completed(output) => output.exit_code,
+            Self::Skipped { .. } => 0,
+            Self::Failed { command, .. } => ExitCodes::of(*command).failure,
+        }
+    }
+}
+
+/// Outcomes of a command that touches many paths, in the order they were
+/// recorded, from which the final exit code is computed.
+#[derive(Debug, Clone)]
+pub struct OperationReport {
+    pub command: CommandKind,
+    pub outcomes: Vec<CommandOutcome>,
+}
+
+impl OperationReport {
+    pub fn new(command: CommandKind) -> Self {
+        Self {
+            command,
+            outcomes: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, outcome: CommandOutcome) {
+        self.outcomes.push(outcome);
+    }
+
+    /// Completed outcomes with a zero exit code.
+    pub fn succeeded(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|outcome| matches!(outcome, CommandOutcome::Completed(_)) && outcome.exit_code() == 0)
+            .count()
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.outcomes.iter().filter(|outcome| matches!(outcome, CommandOutcome::Skipped { .. })).count()
+    }
+
+    /// Failed outcomes and completed ones with a non-zero exit code.
+    pub fn failed(&self) -> usize {
+        self.outcomes.iter().filter(|outcome| outcome.exit_code() != 0).count()
+    }
+
+    /// The highest exit code among the outcomes, 0 when there are none.
+    pub fn exit_code(&self) -> u8 {
+        self.outcomes.iter().map(CommandOutcome::exit_code).max().unwrap_or(0)
+    }
+
+    /// `Ok` when nothing failed, `Error` when nothing succeeded, `Warning`
+    /// for a mix; skips count as neither.
+    pub fn worst_exit_status(&self) -> ExitStatusLike {
+        ExitStatusLike::from_counts(self.succeeded(), self.failed())
+    }
+
+    /// One line per skip and failure, then the totals, e.g.
+    /// "trash-put: 3 succeeded, 1 skipped, 0 failed".
+    pub fn summary(&self) -> Vec<String> {
+        let prefix = format!("trash-{}", self.command);
+        let mut lines = Vec::new();
+        for outcome in &self.outcomes {
+            match outcome {
+                CommandOutcome::Skipped { path, reason, .. } => {
+                    lines.push(format!("{}: skipped '{}': {}", prefix, path.display(), reason))
+                }
+                CommandOutcome::Failed { reason, .. } => lines.push(format!("{}: {}", prefix, reason)),
+                CommandOutcome::Completed(_) => {}
+            }
+        }
+        lines.push(format!(
+            "{}: {} succeeded, {} skipped, {} failed",
+            prefix,
+            self.succeeded(),
+            self.skipped(),
+            self.failed()
+        ));
+        lines
+    }
+}
+
+/// Coarse command result, mapped to a process exit code.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum ExitStatusLike {
     Ok,
+    /// Some, but not all, of the requested work failed.
     Warning,
     Error,
+    /// Nothing matched the user's selection (e.g. trash-rm patterns).
+    NoMatch,
 }
 
 impl ExitStatusLike {
     pub fn as_code(self) -> u8 {
         match self {
-            Self::Ok => 0,
-            Self::Warning => 2,
-            Self::Error => 1,
+            Self::Ok => exit_codes::SUCCESS,
+            Self::Warning => exit_codes::PARTIAL_FAILURE,
+            Self::Error => exit_codes::FAILURE,
+            Self::NoMatch => exit_codes::NO_MATCH,
+        }
+    }
+
+    /// Derives the status of a batch from its success and failure counts.
+    pub fn from_counts(succeeded: usize, failed: usize) -> Self {
+        match (succeeded, failed) {
+            (_, 0) => Self::Ok,
+            (0, _) => Self::Error,
+            _ => Self::Warning,
         }
     }
 }
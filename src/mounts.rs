@@ -0,0 +1,551 @@
+//! Mount table access and volume resolution shared by all commands.
+//!
+//! The table can list one directory several times: bind mounts show a
+//! directory of a filesystem at another target, a target can be mounted
+//! over, and the subvolumes of a btrfs filesystem all share its device.
+//! Volumes to scan are therefore deduplicated by what they show, while a
+//! path is resolved against every target, longest prefix first.
+
+use crate::environment::Environment;
+use crate::log;
+use std::env;
+use std::fs;
+use std::ffi::OsString;
+use std::os::unix::ffi::OsStringExt;
+use std::path::{Path, PathBuf};
+
+/// Mount table consulted on Linux.
+pub const MOUNTS_FILE: &str = "/proc/mounts";
+
+/// Mount table with device numbers and bind-mount roots, preferred to
+/// [`MOUNTS_FILE`] when readable.
+pub const MOUNTINFO_FILE: &str = "/proc/self/mountinfo";
+
+/// Filesystem types whose data lives on another host.
+pub const NETWORK_FSTYPES: &[&str] = &[
+    "9p",
+    "afs",
+    "ceph",
+    "cifs",
+    "fuse.gcsfuse",
+    "fuse.glusterfs",
+    "fuse.rclone",
+    "fuse.s3fs",
+    "fuse.sshfs",
+    "glusterfs",
+    "lustre",
+    "ncpfs",
+    "nfs",
+    "nfs4",
+    "smb3",
+    "smbfs",
+    "sshfs",
+];
+
+/// Filesystem types that hold no user files: kernel interfaces, and images
+/// such as snap packages that are mounted read-only.
+pub const PSEUDO_FSTYPES: &[&str] = &[
+    "autofs",
+    "binfmt_misc",
+    "bpf",
+    "cgroup",
+    "cgroup2",
+    "configfs",
+    "debugfs",
+    "devpts",
+    "devtmpfs",
+    "efivarfs",
+    "erofs",
+    "fusectl",
+    "hugetlbfs",
+    "iso9660",
+    "mqueue",
+    "nsfs",
+    "proc",
+    "pstore",
+    "rpc_pipefs",
+    "securityfs",
+    "selinuxfs",
+    "squashfs",
+    "sysfs",
+    "tracefs",
+];
+
+/// One line of `/proc/self/mountinfo`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MountEntry {
+    /// `major:minor` of the filesystem; btrfs reports the same one for all
+    /// the subvolumes of a filesystem.
+    pub device: String,
+    /// Directory of the filesystem shown at `target`: `/` for a plain mount,
+    /// the bound directory or the subvolume otherwise.
+    pub root: PathBuf,
+    pub target: PathBuf,
+    pub fstype: String,
+    pub source: String,
+    /// Mounted `ro`, so nothing can be trashed there.
+    pub read_only: bool,
+}
+
+impl MountEntry {
+    /// Whether the filesystem is served over the network.
+    pub fn is_network(&self) -> bool {
+        NETWORK_FSTYPES.contains(&self.fstype.as_str())
+    }
+
+    /// Whether the mount can hold user files: neither of
+    /// [`PSEUDO_FSTYPES`] nor read-only.
+    pub fn is_storage(&self) -> bool {
+        !PSEUDO_FSTYPES.contains(&self.fstype.as_str()) && !self.read_only
+    }
+}
+
+/// Which network filesystems volume discovery scans. Reaching a slow or
+/// unreachable server stalls the whole command, so by default none is.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct NetworkFilter {
+    /// Scan every network filesystem (`--include-network`).
+    pub include_all: bool,
+    /// Network filesystem types scanned anyway (`include_network` in the
+    /// config file).
+    pub fstypes: Vec<String>,
+}
+
+impl NetworkFilter {
+    pub fn allows(&self, entry: &MountEntry) -> bool {
+        !entry.is_network() || self.include_all || self.fstypes.contains(&entry.fstype)
+    }
+}
+
+/// Lists mount point targets in mount-table order, without duplicates.
+pub fn list_mount_points() -> Vec<PathBuf> {
+    let targets = match fs::read(MOUNTINFO_FILE) {
+        Ok(content) => parse_mountinfo(&content).into_iter().map(|entry| entry.target).collect(),
+        Err(_) => match fs::read(MOUNTS_FILE) {
+            Ok(content) => parse_mount_points(&content),
+            Err(_) => return Vec::new(),
+        },
+    };
+    let mut points = Vec::new();
+    for target in targets {
+        if !points.contains(&target) {
+            points.push(target);
+        }
+    }
+    points
+}
+
+/// Every mount of [`MOUNTINFO_FILE`], in mount-table order; none when it
+/// cannot be read.
+pub fn list_mount_entries() -> Vec<MountEntry> {
+    fs::read(MOUNTINFO_FILE).map(parse_mountinfo).unwrap_or_default()
+}
+
+/// Lists the targets of [`distinct_mounts`] that `network` allows, falling
+/// back to every mount point when only [`MOUNTS_FILE`] is readable.
+pub fn list_distinct_mount_points(network: &NetworkFilter) -> Vec<PathBuf> {
+    let Ok(content) = fs::read(MOUNTINFO_FILE) else {
+        return list_mount_points();
+    };
+    distinct_mounts(parse_mountinfo(&content))
+        .into_iter()
+        .filter(|entry| {
+            let allowed = network.allows(entry);
+            if !allowed {
+                log::info(
+                    "mounts",
+                    format_args!("skipping {} volume {}", entry.fstype, entry.target.display()),
+                );
+            }
+            allowed
+        })
+        .map(|entry| entry.target)
+        .collect()
+}
+
+/// Extracts the (unescaped) target column of a `/proc/mounts`-style table.
+/// Tables are bytes, as mount points need not be UTF-8.
+pub fn parse_mount_points(content: impl AsRef<[u8]>) -> Vec<PathBuf> {
+    let mut points = Vec::new();
+    for line in content.as_ref().split(|byte| *byte == b'\n') {
+        let Some(target) = fields(line).nth(1) else {
+            continue;
+        };
+        let target = PathBuf::from(unescape_mountpoint(target));
+        if !points.contains(&target) {
+            points.push(target);
+        }
+    }
+    points
+}
+
+/// Parses a `/proc/self/mountinfo`-style table, skipping malformed lines.
+pub fn parse_mountinfo(content: impl AsRef<[u8]>) -> Vec<MountEntry> {
+    content
+        .as_ref()
+        .split(|byte| *byte == b'\n')
+        .filter_map(|line| {
+            let fields: Vec<&[u8]> = fields(line).collect();
+            // Optional fields (`shared:N`, ...) run up to a lone `-`.
+            let separator = fields.iter().skip(6).position(|field| *field == b"-")? + 6;
+            let (fstype, source) = (fields.get(separator + 1)?, fields.get(separator + 2)?);
+            Some(MountEntry {
+                device: String::from_utf8_lossy(fields.get(2)?).into_owned(),
+                root: PathBuf::from(unescape_mountpoint(fields.get(3)?)),
+                target: PathBuf::from(unescape_mountpoint(fields.get(4)?)),
+                fstype: String::from_utf8_lossy(fstype).into_owned(),
+                source: unescape_mountpoint(source).to_string_lossy().into_owned(),
+                read_only: fields.get(5)?.split(|byte| *byte == b',').any(|option| option == b"ro"),
+            })
+        })
+        .collect()
+}
+
+/// The blank-separated fields of a mount table line.
+fn fields(line: &[u8]) -> impl Iterator<Item = &[u8]> {
+    line.split(u8::is_ascii_whitespace).filter(|field| !field.is_empty())
+}
+
+/// Keeps one mount per directory shown, in mount-table order:
+///
+/// - a target mounted several times keeps its last, visible, mount;
+/// - a directory of a filesystem (same device and root) mounted at several
+///   targets, as with bind mounts, keeps its first target only, so that its
+///   top-dir trashes are not found twice.
+///
+/// btrfs subvolumes share a device but not a root, so each one is kept.
+pub fn distinct_mounts(entries: Vec<MountEntry>) -> Vec<MountEntry> {
+    let mut by_target: Vec<MountEntry> = Vec::new();
+    for entry in entries {
+        match by_target.iter_mut().find(|kept| kept.target == entry.target) {
+            Some(kept) => *kept = entry,
+            None => by_target.push(entry),
+        }
+    }
+    let mut distinct: Vec<MountEntry> = Vec::new();
+    for entry in by_target {
+        let shown = |kept: &MountEntry| kept.device == entry.device && kept.root == entry.root;
+        match distinct.iter().find(|kept| shown(kept)) {
+            Some(kept) => log::debug(
+                "mounts",
+                format_args!("{} shows the same directory as {}", entry.target.display(), kept.target.display()),
+            ),
+            None => distinct.push(entry),
+        }
+    }
+    distinct
+}
+
+/// The mount whose target is the longest prefix of `path`; the last one
+/// listed when a target is mounted several times.
+pub fn mount_for<'a>(path: &Path, entries: &'a [MountEntry]) -> Option<&'a MountEntry> {
+    longest_prefix(path, entries, |entry| &entry.target)
+}
+
+/// The mount point of `mount_points` that is the longest prefix of `path`,
+/// as given; see [`longest_prefix`].
+pub fn mount_point_of<'a>(path: &Path, mount_points: &'a [PathBuf]) -> Option<&'a Path> {
+    longest_prefix(path, mount_points, PathBuf::as_path).map(PathBuf::as_path)
+}
+
+/// The item whose `target` is the longest prefix of `path`, the last one
+/// on ties. Paths are compared component by component, not as strings, so
+/// `/mnt/data` is not a prefix of `/mnt/data2`, and `.` components and
+/// trailing slashes do not count.
+fn longest_prefix<'a, T>(path: &Path, items: &'a [T], target: impl Fn(&T) -> &Path) -> Option<&'a T> {
+    items
+        .iter()
+        .filter(|item| path.starts_with(target(item)))
+        .max_by_key(|item| target(item).components().count())
+}
+
+/// Decodes the `\NNN` octal escapes the kernel uses for blanks and
+/// backslashes in mount targets, byte for byte: any three octal digits up
+/// to `\377` are a byte, other backslashes are kept.
+pub fn unescape_mountpoint(bytes: &[u8]) -> OsString {
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes
+            .get(i + 1..i + 4)
+            .filter(|digits| digits.iter().all(|digit| (b'0'..=b'7').contains(digit)) && digits[0] <= b'3');
+        if let (b'\\', Some(digits)) = (bytes[i], octal) {
+            let value = digits
+                .iter()
+                .fold(0u32, |acc, digit| acc * 8 + u32::from(digit - b'0'));
+            decoded.push(value as u8);
+            i += 4;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    OsString::from_vec(decoded)
+}
+
+/// `volumes` without those the mount table shows are not
+/// [storage](MountEntry::is_storage); volumes missing from it, or all of
+/// them when it cannot be read, are kept.
+pub fn storage_volumes(volumes: Vec<PathBuf>) -> Vec<PathBuf> {
+    let Ok(content) = fs::read(MOUNTINFO_FILE) else {
+        return volumes;
+    };
+    let entries = parse_mountinfo(&content);
+    volumes
+        .into_iter()
+        .filter(|volume| {
+            let entry = entries.iter().rev().find(|entry| entry.target == *volume);
+            let keep = entry.is_none_or(MountEntry::is_storage);
+            if !keep {
+                log::debug("mounts", format_args!("hiding volume {}", volume.display()));
+            }
+            keep
+        })
+        .collect()
+}
+
+/// Volumes to scan for top-level trash dirs: `$TRASH_VOLUMES` when set and
+/// non-empty (colon separated), otherwise the mount points of
+/// [`list_distinct_mount_points`].
+pub fn list_volumes(environ: &dyn Environment, network: &NetworkFilter) -> Vec<PathBuf> {
+    let volumes = match environ.trash_volumes() {
+        Some(volumes) => {
+            log::debug("mounts", format_args!("volumes from $TRASH_VOLUMES: {}", volumes.len()));
+            volumes
+        }
+        None => {
+            let volumes = list_distinct_mount_points(network);
+            log::debug("mounts", format_args!("volumes from the mount table: {}", volumes.len()));
+            volumes
+        }
+    };
+    for volume in &volumes {
+        log::trace("mounts", format_args!("volume {}", volume.display()));
+    }
+    volumes
+}
+
+/// Returns the mount point containing `path`: the longest of `mount_points`
+/// prefixing its resolved form, unless a directory below it lives on
+/// another device (`st_dev`) than its parent, like `os.path.ismount`.
+/// Comparing devices finds the right volume for symlinked paths and for
+/// mounts missing from `mount_points`; listing bind mounts, which share the
+/// device of their source, still makes them volumes of their own. Only
+/// directories are compared, as overlayfs reports the device of the lower
+/// or upper layer for other files. `/` when none matches.
+pub fn volume_of(path: &Path, mount_points: &[PathBuf]) -> PathBuf {
+    let resolved = resolve(path);
+    let listed = mount_point_of(&resolved, mount_points).unwrap_or(Path::new("/"));
+    for ancestor in resolved.ancestors().take_while(|ancestor| *ancestor != listed) {
+        let Some(parent) = ancestor.parent() else {
+            break;
+        };
+        if let (Some(device), Some(parent_device)) = (directory_device(ancestor), directory_device(parent)) {
+            if device != parent_device {
+                return ancestor.to_path_buf();
+            }
+        }
+    }
+    listed.to_path_buf()
+}
+
+/// `path` made absolute, with the symlinks of its longest existing
+/// ancestor resolved and the rest appended unchanged.
+pub(crate) fn resolve(path: &Path) -> PathBuf {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir()
+            .map(|cwd| cwd.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    };
+    for ancestor in absolute.ancestors() {
+        if let Ok(resolved) = fs::canonicalize(ancestor) {
+            let rest = absolute.strip_prefix(ancestor).unwrap_or_else(|_| Path::new(""));
+            return resolved.join(rest);
+        }
+    }
+    absolute
+}
+
+#[cfg(unix)]
+fn directory_device(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path)
+        .ok()
+        .filter(|metadata| metadata.is_dir())
+        .map(|metadata| metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn directory_device(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A container whose root is an overlayfs, with host directories bound
+    /// into it, one of them twice.
+    const OVERLAY: &str = "\
+600 550 0:52 / / rw,relatime master:1 - overlay overlay rw,lowerdir=/var/lib/docker/overlay2/l/A,upperdir=/u,workdir=/w
+601 600 0:55 / /proc rw,nosuid,nodev,noexec,relatime - proc proc rw
+602 600 259:2 /var/lib/docker/containers/abc/hostname /etc/hostname rw,relatime - ext4 /dev/nvme0n1p2 rw
+603 600 259:2 /home/alice/project /work rw,relatime - ext4 /dev/nvme0n1p2 rw
+604 600 259:2 /home/alice/project /src rw,relatime - ext4 /dev/nvme0n1p2 rw
+605 603 0:60 / /work/build rw,relatime - tmpfs tmpfs rw
+";
+
+    /// A btrfs filesystem with `@`, `@home` and `@snapshots` subvolumes,
+    /// `@home` bound a second time, and a mount stacked on `/mnt`.
+    const BTRFS: &str = "\
+28 1 0:31 /@ / rw,relatime shared:1 - btrfs /dev/sda2 rw,ssd,space_cache=v2,subvolid=256,subvol=/@
+29 28 0:31 /@home /home rw,relatime shared:2 - btrfs /dev/sda2 rw,ssd,space_cache=v2,subvolid=257,subvol=/@home
+30 28 0:31 /@snapshots /.snapshots rw,relatime shared:3 - btrfs /dev/sda2 rw,subvolid=258,subvol=/@snapshots
+31 28 0:31 /@home /srv/home rw,relatime shared:2 - btrfs /dev/sda2 rw,subvolid=257,subvol=/@home
+32 28 8:17 / /mnt rw,relatime shared:4 - ext4 /dev/sdb1 rw
+33 32 8:33 / /mnt rw,relatime shared:5 - vfat /dev/sdc1 rw
+34 29 0:31 /@home/alice/Shared\\040Stuff /media/shared\\040stuff rw - btrfs /dev/sda2 rw
+";
+
+    fn targets(entries: &[MountEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.target.to_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn parses_mountinfo_fields() {
+        let entries = parse_mountinfo(BTRFS);
+
+        assert_eq!(entries.len(), 7);
+        assert_eq!(
+            entries[1],
+            MountEntry {
+                device: "0:31".to_string(),
+                root: PathBuf::from("/@home"),
+                target: PathBuf::from("/home"),
+                fstype: "btrfs".to_string(),
+                source: "/dev/sda2".to_string(),
+                read_only: false,
+            }
+        );
+        assert_eq!(entries[6].root, Path::new("/@home/alice/Shared Stuff"));
+        assert_eq!(entries[6].target, Path::new("/media/shared stuff"));
+    }
+
+    #[test]
+    fn skips_malformed_mountinfo_lines() {
+        let entries = parse_mountinfo("garbage\n1 2 0:1 / /x rw no separator here\n3 1 0:2 / /y rw - tmpfs tmpfs rw\n");
+
+        assert_eq!(targets(&entries), ["/y"]);
+    }
+
+    #[test]
+    fn tells_storage_from_pseudo_and_read_only_mounts() {
+        let entries = parse_mountinfo(
+            "1 0 8:1 / / rw,relatime - ext4 /dev/sda1 rw\n\
+             2 1 0:5 / /proc rw,nosuid - proc proc rw\n\
+             3 1 0:6 / /sys/fs/cgroup rw - cgroup2 cgroup2 rw\n\
+             4 1 7:0 / /snap/core/1 ro,nodev,relatime - squashfs /dev/loop0 ro\n\
+             5 1 8:2 / /mnt/backup ro,relatime - ext4 /dev/sdb1 rw\n",
+        );
+        let storage: Vec<&MountEntry> = entries.iter().filter(|entry| entry.is_storage()).collect();
+
+        assert_eq!(storage.len(), 1);
+        assert_eq!(storage[0].target, Path::new("/"));
+        assert!(entries[4].read_only);
+    }
+
+    #[test]
+    fn keeps_every_btrfs_subvolume() {
+        let distinct = distinct_mounts(parse_mountinfo(BTRFS));
+
+        assert_eq!(targets(&distinct), ["/", "/home", "/.snapshots", "/mnt", "/media/shared stuff"]);
+    }
+
+    #[test]
+    fn keeps_the_visible_mount_of_a_stacked_target() {
+        let distinct = distinct_mounts(parse_mountinfo(BTRFS));
+        let mnt = distinct.iter().find(|entry| entry.target == Path::new("/mnt")).unwrap();
+
+        assert_eq!(mnt.fstype, "vfat");
+    }
+
+    #[test]
+    fn drops_second_bind_mount_of_the_same_directory() {
+        let distinct = distinct_mounts(parse_mountinfo(OVERLAY));
+
+        assert_eq!(targets(&distinct), ["/", "/proc", "/etc/hostname", "/work", "/work/build"]);
+    }
+
+    #[test]
+    fn skips_network_mounts_unless_allowed() {
+        let entries = parse_mountinfo(
+            "\
+1 0 8:1 / / rw - ext4 /dev/sda1 rw
+2 1 0:40 / /srv/nfs rw - nfs4 fileserver:/export rw
+3 1 0:41 / /home/alice/remote rw - fuse.sshfs alice@host: rw
+",
+        );
+        let allowed = |filter: &NetworkFilter| -> Vec<MountEntry> {
+            entries.iter().filter(|entry| filter.allows(entry)).cloned().collect()
+        };
+
+        assert_eq!(targets(&allowed(&NetworkFilter::default())), ["/"]);
+        let nfs = NetworkFilter {
+            include_all: false,
+            fstypes: vec!["nfs4".to_string()],
+        };
+        assert_eq!(targets(&allowed(&nfs)), ["/", "/srv/nfs"]);
+        let all = NetworkFilter {
+            include_all: true,
+            fstypes: Vec::new(),
+        };
+        assert_eq!(targets(&allowed(&all)), ["/", "/srv/nfs", "/home/alice/remote"]);
+    }
+
+    #[test]
+    fn resolves_the_longest_target_prefix() {
+        let entries = parse_mountinfo(OVERLAY);
+        let target_of = |path: &str| mount_for(Path::new(path), &entries).map(|entry| entry.target.clone());
+
+        assert_eq!(target_of("/work/build/out.o"), Some(PathBuf::from("/work/build")));
+        assert_eq!(target_of("/work/main.rs"), Some(PathBuf::from("/work")));
+        assert_eq!(target_of("/src/main.rs"), Some(PathBuf::from("/src")));
+        assert_eq!(target_of("/workspace/file"), Some(PathBuf::from("/")));
+        assert_eq!(mount_for(Path::new("/etc/passwd"), &entries).unwrap().fstype, "overlay");
+    }
+
+    #[test]
+    fn matches_mount_points_by_whole_components() {
+        let mount_points = [PathBuf::from("/"), PathBuf::from("/mnt/data/"), PathBuf::from("/mnt/./data/photos")];
+        let mount_point = |path: &str| mount_point_of(Path::new(path), &mount_points).unwrap();
+
+        assert_eq!(mount_point("/mnt/data2/file"), Path::new("/"));
+        assert_eq!(mount_point("/mnt/data/file"), Path::new("/mnt/data/"));
+        assert_eq!(mount_point("/mnt/data"), Path::new("/mnt/data/"));
+        assert_eq!(mount_point("/mnt/data/photos2"), Path::new("/mnt/data/"));
+        assert_eq!(mount_point("/mnt/data/photos/cat.jpg"), Path::new("/mnt/./data/photos"));
+        let volume = volume_of(Path::new("/no-such-volume/data2/file"), &[PathBuf::from("/no-such-volume/data")]);
+        assert_eq!(volume, Path::new("/"));
+    }
+
+    #[test]
+    fn resolves_paths_to_their_btrfs_subvolume() {
+        let entries = parse_mountinfo(BTRFS);
+        let mount = |path: &str| mount_for(Path::new(path), &entries).unwrap();
+
+        assert_eq!(mount("/home/alice/notes.txt").root, Path::new("/@home"));
+        assert_eq!(mount("/.snapshots/1/snapshot").root, Path::new("/@snapshots"));
+        assert_eq!(mount("/homework").root, Path::new("/@"));
+        assert_eq!(mount("/mnt/photo.jpg").fstype, "vfat");
+    }
+
+    #[test]
+    fn unescapes_octal_sequences() {
+        assert_eq!(unescape_mountpoint(b"/media/a\\040b\\011c\\134d"), "/media/a b\tc\\d");
+        assert_eq!(unescape_mountpoint(b"/trailing\\04"), "/trailing\\04");
+        assert_eq!(unescape_mountpoint(b"/too\\400big"), "/too\\400big");
+        assert_eq!(unescape_mountpoint(b"/caf\\303\\251\\377").into_vec(), b"/caf\xc3\xa9\xff");
+    }
+}
@@ -0,0 +1,40 @@
+//! Async variants of the [`crate::api`] entry points for applications that
+//! run on a tokio runtime (servers, TUIs), built with the `async` feature.
+//!
+//! Trash operations are file system calls all the way down, so each variant
+//! runs its synchronous counterpart on tokio's blocking thread pool: the
+//! logic stays in one place and the executor threads never block. They
+//! work on the real environment and must be awaited within a runtime.
+
+use crate::api::{self, ListOptions, TrashOptions};
+use crate::environment::RealEnvironment;
+use crate::models::{OperationReport, TrashedItem};
+use std::panic;
+use std::path::PathBuf;
+use tokio::task;
+
+/// [`api::list_trashed_items`], collected.
+pub async fn list_trashed_items(options: ListOptions) -> Vec<crate::Result<TrashedItem>> {
+    blocking(move || api::list_trashed_items(&RealEnvironment, &options).collect()).await
+}
+
+/// [`api::trash`].
+pub async fn trash(paths: Vec<PathBuf>, options: TrashOptions) -> OperationReport {
+    blocking(move || api::trash(paths, &options)).await
+}
+
+/// [`api::empty`].
+pub async fn empty(options: ListOptions) -> OperationReport {
+    blocking(move || api::empty(&RealEnvironment, &options)).await
+}
+
+/// Runs `operation` on the blocking thread pool, propagating its panics.
+async fn blocking<T: Send + 'static>(operation: impl FnOnce() -> T + Send + 'static) -> T {
+    match task::spawn_blocking(operation).await {
+        Ok(value) => value,
+        Err(err) => match err.try_into_panic() {
+            Ok(payload) => panic::resume_unwind(payload),
+            Err(err) => panic!("trash operation did not complete: {}", err),
+        },
+    }
+}
@@ -0,0 +1,76 @@
+//! Desktop notifications through the freedesktop notification service
+//! (`org.freedesktop.Notifications` on the session bus), so that trashing
+//! from a terminal, for instance through an `rm` alias, or emptying the
+//! trash gives visible feedback.
+//!
+//! Only built with the `notifications` feature; without it, or when no
+//! session bus is known, [`notify`] does nothing. Messages are sent with
+//! `gdbus`, which comes with GLib on desktops running a notification
+//! server, instead of linking a D-Bus implementation.
+
+use crate::environment::{Environment, DBUS_SESSION_BUS_ADDRESS};
+use crate::log;
+
+/// Application name the notifications are sent under.
+pub const APP_NAME: &str = "trash-cli";
+
+/// Icon name from the freedesktop icon naming specification.
+pub const ICON: &str = "user-trash-full";
+
+/// Whether [`notify`] sends anything: the feature is built in and the
+/// process runs in a desktop session.
+pub fn is_enabled(environ: &dyn Environment) -> bool {
+    cfg!(feature = "notifications")
+        && environ
+            .var(DBUS_SESSION_BUS_ADDRESS.name)
+            .is_some_and(|address| !address.is_empty())
+}
+
+/// Shows `summary` as a desktop notification. Failures are only logged:
+/// the operation it reports has already succeeded.
+pub fn notify(environ: &dyn Environment, summary: &str) {
+    if !is_enabled(environ) {
+        return;
+    }
+    log::debug("notify", format_args!("notifying '{}'", summary));
+    send(summary);
+}
+
+#[cfg(feature = "notifications")]
+fn send(summary: &str) {
+    use std::process::{Command, Stdio};
+
+    let status = Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            "org.freedesktop.Notifications",
+            "--object-path",
+            "/org/freedesktop/Notifications",
+            "--method",
+            "org.freedesktop.Notifications.Notify",
+        ])
+        // app_name, replaces_id, app_icon, summary, body, actions, hints,
+        // expire_timeout (-1: the server's default).
+        .args([&quote(APP_NAME), "0", &quote(ICON), &quote(summary), "''", "[]", "{}", "-1"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => log::debug("notify", format_args!("gdbus failed: {}", status)),
+        Err(err) => log::debug("notify", format_args!("cannot run gdbus: {}", err)),
+    }
+}
+
+#[cfg(not(feature = "notifications"))]
+fn send(_summary: &str) {}
+
+/// `text` as a GVariant string literal, the form `gdbus call` parses its
+/// arguments in.
+#[cfg(feature = "notifications")]
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
@@ -0,0 +1,115 @@
+//! Field-based output of trashed items: a command prints one line per item
+//! made of the fields the user picked, in the order given, for instance
+//! `trash-list --output date,size,path`.
+//!
+//! Every field is listed in [`FIELDS`] with its name and what it shows;
+//! commands render the values, this module only names, parses and joins
+//! them.
+
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+
+/// A column of item output.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Field {
+    Date,
+    Size,
+    Path,
+    TrashDir,
+    File,
+}
+
+/// The documentation of a [`Field`].
+#[derive(Debug, Clone, Copy)]
+pub struct FieldSpec {
+    pub field: Field,
+    /// Name used in field lists.
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// Every field, in help order.
+pub const FIELDS: &[FieldSpec] = &[
+    FieldSpec {
+        field: Field::Date,
+        name: "date",
+        description: "deletion date, YYYY-MM-DD HH:MM:SS",
+    },
+    FieldSpec {
+        field: Field::Size,
+        name: "size",
+        description: "size of the trashed file or directory, in bytes",
+    },
+    FieldSpec {
+        field: Field::Path,
+        name: "path",
+        description: "original location",
+    },
+    FieldSpec {
+        field: Field::TrashDir,
+        name: "trashdir",
+        description: "trash directory holding the item",
+    },
+    FieldSpec {
+        field: Field::File,
+        name: "file",
+        description: "the trashed copy, in the files/ directory of the trash dir",
+    },
+];
+
+impl Field {
+    pub fn parse(name: &str) -> Option<Self> {
+        FIELDS.iter().find(|spec| spec.name == name).map(|spec| spec.field)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.spec().name
+    }
+
+    pub fn description(&self) -> &'static str {
+        self.spec().description
+    }
+
+    fn spec(&self) -> &'static FieldSpec {
+        FIELDS
+            .iter()
+            .find(|spec| spec.field == *self)
+            .expect("every field is registered")
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Parses a comma-separated list of field names, keeping their order.
+pub fn parse_fields(list: &str) -> Result<Vec<Field>, String> {
+    list.split(',')
+        .map(str::trim)
+        .map(|name| {
+            Field::parse(name).ok_or_else(|| {
+                let names: Vec<&str> = FIELDS.iter().map(|spec| spec.name).collect();
+                format!("unknown field '{}' (choose from {})", name, names.join(", "))
+            })
+        })
+        .collect()
+}
+
+/// The registry as help text: one `NAME  DESCRIPTION` line per field.
+pub fn describe_fields() -> String {
+    let width = FIELDS.iter().map(|spec| spec.name.len()).max().unwrap_or(0);
+    FIELDS
+        .iter()
+        .map(|spec| format!("{:width$}  {}", spec.name, spec.description, width = width))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Joins the values of `fields`, as given by `value`, with spaces. Values
+/// are `OsString`s so that paths are printed byte for byte.
+pub fn format_row<E>(fields: &[Field], mut value: impl FnMut(Field) -> Result<OsString, E>) -> Result<OsString, E> {
+    let values = fields.iter().map(|field| value(*field)).collect::<Result<Vec<OsString>, E>>()?;
+    Ok(values.join(OsStr::new(" ")))
+}
@@ -0,0 +1,160 @@
+//! Progress of long operations (emptying a large trash, copying a tree to
+//! another device), drawn on stderr when it is a terminal.
+
+use crate::helpers::{format_duration, print_size};
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+/// Receives the progress of an operation over a number of items.
+pub trait Progress {
+    /// Begins the operation; the totals are `None` when not known up front.
+    fn start(&mut self, total_items: Option<u64>, total_bytes: Option<u64>);
+
+    /// Records that `items` more items, totalling `bytes`, are done.
+    fn advance(&mut self, items: u64, bytes: u64);
+
+    /// Ends the operation, clearing whatever was drawn.
+    fn finish(&mut self);
+
+    /// Whether the progress is shown at all; callers skip measuring sizes
+    /// only needed for the report when it is not.
+    fn is_visible(&self) -> bool {
+        true
+    }
+}
+
+/// Progress that is not reported.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn start(&mut self, _total_items: Option<u64>, _total_bytes: Option<u64>) {}
+
+    fn advance(&mut self, _items: u64, _bytes: u64) {}
+
+    fn finish(&mut self) {}
+
+    fn is_visible(&self) -> bool {
+        false
+    }
+}
+
+/// Width of the bar, between the brackets.
+const BAR_WIDTH: usize = 24;
+
+/// Minimum time between two redraws.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A one-line progress bar on stderr:
+/// `trash-empty: [=========>      ] 420/1000 items, 1.2 GB freed, ETA 35s`.
+#[derive(Debug)]
+pub struct TerminalProgress {
+    prog: String,
+    /// What happens to the bytes: `freed`, `copied`, ...
+    bytes_verb: &'static str,
+    started: Instant,
+    last_draw: Option<Instant>,
+    items: u64,
+    bytes: u64,
+    total_items: Option<u64>,
+    total_bytes: Option<u64>,
+}
+
+impl TerminalProgress {
+    pub fn new(prog: &str, bytes_verb: &'static str) -> Self {
+        Self {
+            prog: prog.to_string(),
+            bytes_verb,
+            started: Instant::now(),
+            last_draw: None,
+            items: 0,
+            bytes: 0,
+            total_items: None,
+            total_bytes: None,
+        }
+    }
+
+    /// The part done so far, by bytes when their total is known and by
+    /// items otherwise.
+    fn fraction(&self) -> Option<f64> {
+        match (self.total_bytes, self.total_items) {
+            (Some(total), _) if total > 0 => Some(self.bytes as f64 / total as f64),
+            (_, Some(total)) if total > 0 => Some(self.items as f64 / total as f64),
+            _ => None,
+        }
+    }
+
+    /// The time left, extrapolated from the rate so far.
+    fn eta(&self) -> Option<Duration> {
+        let fraction = self.fraction().filter(|fraction| *fraction > 0.0)?;
+        let elapsed = self.started.elapsed().as_secs_f64();
+        Some(Duration::from_secs_f64(elapsed * (1.0 - fraction.min(1.0)) / fraction))
+    }
+
+    fn line(&self) -> String {
+        let mut line = format!("{}: ", self.prog);
+        if let Some(fraction) = self.fraction() {
+            let filled = ((fraction.min(1.0) * BAR_WIDTH as f64) as usize).min(BAR_WIDTH);
+            let head = if filled < BAR_WIDTH { ">" } else { "" };
+            line.push_str(&format!(
+                "[{}{}{}] ",
+                "=".repeat(filled),
+                head,
+                " ".repeat(BAR_WIDTH - filled - head.len())
+            ));
+        }
+        match self.total_items {
+            Some(total) => line.push_str(&format!("{}/{} items", self.items, total)),
+            None => line.push_str(&format!("{} items", self.items)),
+        }
+        line.push_str(&format!(", {} {}", print_size(self.bytes), self.bytes_verb));
+        if let Some(eta) = self.eta() {
+            line.push_str(&format!(", ETA {}", format_duration(eta)));
+        }
+        line
+    }
+
+    fn draw(&mut self) {
+        let now = Instant::now();
+        if self.last_draw.is_some_and(|last| now.duration_since(last) < REDRAW_INTERVAL) {
+            return;
+        }
+        self.last_draw = Some(now);
+        let mut stderr = io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[K{}", self.line());
+        let _ = stderr.flush();
+    }
+}
+
+impl Progress for TerminalProgress {
+    fn start(&mut self, total_items: Option<u64>, total_bytes: Option<u64>) {
+        self.started = Instant::now();
+        self.last_draw = None;
+        self.items = 0;
+        self.bytes = 0;
+        self.total_items = total_items;
+        self.total_bytes = total_bytes;
+    }
+
+    fn advance(&mut self, items: u64, bytes: u64) {
+        self.items += items;
+        self.bytes += bytes;
+        self.draw();
+    }
+
+    fn finish(&mut self) {
+        if self.last_draw.take().is_some() {
+            let mut stderr = io::stderr().lock();
+            let _ = write!(stderr, "\r\x1b[K");
+            let _ = stderr.flush();
+        }
+    }
+}
+
+/// A [`TerminalProgress`] when stderr is a terminal, no progress otherwise.
+pub fn stderr_progress(prog: &str, bytes_verb: &'static str) -> Box<dyn Progress> {
+    match io::stderr().is_terminal() {
+        true => Box::new(TerminalProgress::new(prog, bytes_verb)),
+        false => Box::new(NoProgress),
+    }
+}
@@ -0,0 +1,768 @@
+//! Trashing files as trash-put does: the trash dir of each file is the
+//! first of the home trash, `$topdir/.Trash/$uid` and `$topdir/.Trash-$uid`
+//! that lives on its volume, checked and created as the specification
+//! requires, with the home trash as a last resort when the home fallback
+//! is enabled.
+//!
+//! Messages are those of trash-put; [`crate::api::trash`] is the entry point
+//! for other applications.
+
+use crate::cancel::CancellationToken;
+use crate::directory_sizes::record_directory_size;
+use crate::environment::Environment;
+use crate::exit_codes::ExitCodes;
+use crate::fs::{copy_tree_cancellable, recursive_size, remove_partial_copy, FileSystem};
+use crate::helpers::{abbreviate_home, terminal_path};
+use crate::i18n::{tr, tr_format};
+use crate::index::TrashIndex;
+use crate::journal::{Journal, JournalItem, Operation, Record};
+use crate::log;
+use crate::models::{CommandKind, CommandOutcome, CommandOutput, SkipReason, TrashDirectory, TrashedItem};
+use crate::mounts::{list_mount_points, resolve, volume_of};
+use crate::progress::{stderr_progress, NoProgress, Progress};
+use crate::quota::QuotaPolicy;
+use crate::transaction::TrashTransaction;
+use crate::trash_dirs::home_trash_dirs;
+use crate::trashinfo::{format_trashinfo, original_location_for, FileAttributes};
+use chrono::{DateTime, Local};
+use std::cell::{Cell, RefCell};
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// How files are trashed.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct TrashOptions {
+    /// Trash into this trash dir only (`--trash-dir`).
+    pub trash_dir: Option<PathBuf>,
+    /// Volume every file is taken to live on when choosing its top
+    /// directory trash (`--force-volume`).
+    pub forced_volume: Option<PathBuf>,
+    /// Copy files no trash dir of their volume accepts into the home trash
+    /// (`--home-fallback`); `TRASH_ENABLE_HOME_FALLBACK=1` enables it too.
+    pub home_fallback: bool,
+    /// Silently ignore nonexistent files (`-f`), as rm does.
+    pub force: bool,
+    /// Trash protected paths too (`--force-protected`), see
+    /// [`SkipReason::ProtectedPath`].
+    pub force_protected: bool,
+    /// Refused like `/`, the home directory, mount points and trash dirs.
+    pub protected_paths: Vec<PathBuf>,
+    /// Trash named pipes, sockets and device nodes too (`--allow-special`),
+    /// see [`SkipReason::UnsupportedFileType`].
+    pub allow_special: bool,
+    /// `1` reports the trash dir of each file on stderr, `2` also every
+    /// trash dir tried.
+    pub verbose: u32,
+    /// Report the `files/` entry of each trashed file on stdout.
+    pub print: bool,
+    /// Show the progress of copies across devices on a terminal stderr.
+    pub progress: bool,
+    /// Limits of the trash dirs files are trashed into (`[quota]`), applied
+    /// by [`Trasher::enforce_quota`].
+    pub quota: QuotaPolicy,
+    /// Flush each trashinfo and `info/` to disk before moving the file
+    /// (`--fsync`), so a crash cannot lose what restoring it needs.
+    pub fsync: bool,
+    /// Write the home directory as `~` in the messages of `-v` (see
+    /// [`abbreviate_home`]).
+    pub abbreviate_home: bool,
+    /// Escape the control characters of the paths in messages (see
+    /// [`crate::helpers::escape_control`]), for a terminal stderr.
+    pub escape_control: bool,
+}
+
+/// How a candidate trash dir must be validated before use.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Check {
+    None,
+    /// `$topdir/.Trash/$uid`: the shared parent must be a sticky,
+    /// non-symlink directory.
+    TopTrashDir,
+}
+
+/// Whether a candidate trash dir accepts the file.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Gate {
+    /// Only files living on the same volume as the trash dir.
+    SameVolume,
+    /// Any file, copied across devices if needed (`--home-fallback`).
+    HomeFallback,
+}
+
+#[derive(Debug)]
+struct Candidate {
+    trash_dir: TrashDirectory,
+    check: Check,
+    gate: Gate,
+}
+
+impl Candidate {
+    /// A trash dir whose `Path=` entries are absolute (the home trash).
+    fn new(path: PathBuf, check: Check, gate: Gate) -> Self {
+        Candidate {
+            trash_dir: TrashDirectory::at(path),
+            check,
+            gate,
+        }
+    }
+
+    /// A trash dir whose `Path=` entries are relative to `volume`.
+    fn on_volume(path: PathBuf, volume: &Path, check: Check) -> Self {
+        Candidate {
+            trash_dir: TrashDirectory::at(path).with_mount_point(volume.to_path_buf()),
+            check,
+            gate: Gate::SameVolume,
+        }
+    }
+}
+
+/// Trashes files one at a time with the same [`TrashOptions`].
+pub struct Trasher<'a> {
+    fs: &'a dyn FileSystem,
+    environ: &'a dyn Environment,
+    options: &'a TrashOptions,
+    uid: u32,
+    mount_points: Vec<PathBuf>,
+    /// Resolved paths that are refused unless `--force-protected` is given.
+    protected: Vec<PathBuf>,
+    journal: Journal,
+    /// Files trashed so far, for the desktop notification.
+    trashed: Cell<usize>,
+    /// Trash dirs trashed into so far, with the names given there.
+    used: RefCell<Vec<(TrashDirectory, Vec<OsString>)>>,
+    /// Asked before trashing each file (`-i`).
+    confirm: Option<&'a dyn Fn(&Path) -> bool>,
+    /// Aborts copies across devices, which are then rolled back.
+    cancel: CancellationToken,
+}
+
+impl<'a> Trasher<'a> {
+    pub fn new(fs: &'a dyn FileSystem, environ: &'a dyn Environment, options: &'a TrashOptions) -> Self {
+        let uid = environ.uid();
+        let mount_points = list_mount_points();
+        let protected = protected_paths(
+            environ,
+            uid,
+            &mount_points,
+            options.trash_dir.as_deref(),
+            &options.protected_paths,
+        );
+        Trasher {
+            fs,
+            environ,
+            options,
+            uid,
+            mount_points,
+            protected,
+            journal: Journal::open(environ),
+            trashed: Cell::new(0),
+            used: RefCell::default(),
+            confirm: None,
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    /// Only trashes the files `confirm` accepts; it is asked once the file
+    /// is known to exist and not to be protected.
+    pub fn with_confirm(mut self, confirm: &'a dyn Fn(&Path) -> bool) -> Self {
+        self.confirm = Some(confirm);
+        self
+    }
+
+    /// Stops a copy across devices once `cancel` is cancelled: the partial
+    /// copy and the trashinfo are removed and the file stays in place.
+    pub fn with_cancel(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Number of files trashed so far.
+    pub fn trashed(&self) -> usize {
+        self.trashed.get()
+    }
+
+    /// Applies [`TrashOptions::quota`] to the trash dirs trashed into so
+    /// far, sparing the files this `Trasher` trashed; see
+    /// [`QuotaPolicy::enforce`].
+    pub fn enforce_quota(&self) -> Vec<crate::Result<TrashedItem>> {
+        self.used
+            .borrow()
+            .iter()
+            .flat_map(|(trash_dir, names)| self.options.quota.enforce(self.fs, &self.journal, trash_dir, names))
+            .collect()
+    }
+
+    /// Trashes `path` in the first candidate trash dir that accepts it,
+    /// see [`Trasher::trash_outcome`].
+    pub fn trash(&self, path: &Path) -> CommandOutput {
+        match self.trash_outcome(path) {
+            CommandOutcome::Completed(output) => output,
+            _ => CommandOutput::success(CommandKind::Put, Vec::new()),
+        }
+    }
+
+    /// Trashes `path` in the first candidate trash dir that accepts it.
+    ///
+    /// `--print` reports the resulting `files/` entry on stdout, `-v` the
+    /// trash dir on stderr (`-vv` also each candidate tried); failures list
+    /// every rejected candidate and complete with a failure exit code. A
+    /// missing path under `-f` and a declined `-i` prompt are skipped
+    /// silently, neither succeeding nor failing.
+    pub fn trash_outcome(&self, path: &Path) -> CommandOutcome {
+        let mut output = CommandOutput::success(CommandKind::Put, Vec::new());
+        if matches!(last_segment(path).to_str(), Some("." | "..")) {
+            let (kind, shown) = (describe(self.fs, path), self.escaped(path));
+            return failure(output, tr_format("trash-put: cannot trash {} '{}'", &[&kind, &shown.display()]));
+        }
+        let normalized: PathBuf = path.components().collect();
+        if self.fs.symlink_metadata(&normalized).is_err() {
+            if self.options.force {
+                return skipped(path, SkipReason::MissingPath(path.to_path_buf()));
+            }
+            let shown = self.escaped(path);
+            return failure(output, tr_format("trash-put: cannot trash non existent '{}'", &[&shown.display()]));
+        }
+        if let Some(reason) = self.protection(&normalized) {
+            return failure(
+                output,
+                tr_format(
+                    "trash-put: cannot trash '{}': {} (use --force-protected to override)",
+                    &[&self.escaped(path).display(), &reason],
+                ),
+            );
+        }
+        if let Some(reason) = self.special_file(&normalized) {
+            log::debug("put", format_args!("{}", reason));
+            return failure(
+                output,
+                tr_format(
+                    "trash-put: cannot trash {} '{}': special files are not trashed (use --allow-special to override)",
+                    &[&describe(self.fs, path), &self.escaped(path).display()],
+                ),
+            );
+        }
+        if let Some(reason) = self.blocked(&normalized) {
+            log::debug("put", format_args!("{}", reason));
+            let hint = match reason {
+                SkipReason::ReadOnlyFilesystem(_) => tr("remount it read-write first"),
+                _ => tr("clear the attribute with 'chattr -i -a' first"),
+            };
+            return failure(
+                output,
+                tr_format("trash-put: cannot trash '{}': {} ({})", &[&self.escaped(path).display(), &reason, &hint]),
+            );
+        }
+        if self.confirm.is_some_and(|confirm| !confirm(path)) {
+            return skipped(path, SkipReason::Declined(path.to_path_buf()));
+        }
+        let Some(basename) = normalized.file_name() else {
+            let (kind, shown) = (describe(self.fs, path), self.escaped(path));
+            return failure(output, tr_format("trash-put: cannot trash {} '{}'", &[&kind, &shown.display()]));
+        };
+
+        let volume = match &self.options.forced_volume {
+            Some(volume) => volume.clone(),
+            None => volume_of(&parent_realpath(&normalized), &self.mount_points),
+        };
+        let mut failures = Vec::new();
+        for candidate in self.candidates(&volume) {
+            if self.options.verbose > 1 {
+                output.stderr.push(tr_format(
+                    "trash-put: trying trash dir: {} from volume: {}",
+                    &[&self.shown(&candidate.trash_dir.path).display(), &volume.display()],
+                ));
+            }
+            match self.trash_in(&normalized, basename, &volume, &candidate, &mut output) {
+                Ok(backup_path) => {
+                    self.trashed.set(self.trashed.get() + 1);
+                    self.record_use(&candidate.trash_dir, &backup_path);
+                    if self.options.verbose > 0 {
+                        output.stderr.push(tr_format(
+                            "trash-put: trashed '{}' to '{}'",
+                            &[&self.shown(path).display(), &self.shown(&candidate.trash_dir.path).display()],
+                        ));
+                    }
+                    if self.options.print {
+                        output.stdout.push(backup_path.into_os_string());
+                    }
+                    return CommandOutcome::Completed(output);
+                }
+                Err(reason) => {
+                    log::debug(
+                        "put",
+                        format_args!("not trashing in {}: {}", candidate.trash_dir.path.display(), reason),
+                    );
+                    failures.push((candidate, reason));
+                }
+            }
+        }
+
+        output.stderr.push(tr_format(
+            "trash-put: cannot trash {} '{}' (from volume '{}')",
+            &[&describe(self.fs, path), &self.escaped(path).display(), &volume.display()],
+        ));
+        for (candidate, reason) in failures {
+            output.stderr.push(tr_format(
+                "trash-put:  `- failed to trash {} in {}, because {}",
+                &[&self.escaped(path).display(), &candidate.trash_dir.path.display(), &reason],
+            ));
+        }
+        output.exit_code = ExitCodes::of(CommandKind::Put).failure;
+        CommandOutcome::Completed(output)
+    }
+
+    /// Trash dirs to try, in order: the home trash, `$volume/.Trash/$uid`,
+    /// `$volume/.Trash-$uid` (created when missing) and finally, when the
+    /// home fallback is enabled, the home trash regardless of volume.
+    fn candidates(&self, volume: &Path) -> Vec<Candidate> {
+        if let Some(trash_dir) = &self.options.trash_dir {
+            let trash_dir_volume = volume_of(&resolve(trash_dir), &self.mount_points);
+            return vec![Candidate::on_volume(trash_dir.clone(), &trash_dir_volume, Check::None)];
+        }
+
+        let mut candidates: Vec<Candidate> = home_trash_dirs(self.environ)
+            .into_iter()
+            .map(|path| Candidate::new(path, Check::None, Gate::SameVolume))
+            .collect();
+        candidates.push(Candidate::on_volume(
+            volume.join(".Trash").join(self.uid.to_string()),
+            volume,
+            Check::TopTrashDir,
+        ));
+        candidates.push(Candidate::on_volume(
+            volume.join(format!(".Trash-{}", self.uid)),
+            volume,
+            Check::None,
+        ));
+        if self.home_fallback_enabled() {
+            candidates.extend(
+                home_trash_dirs(self.environ)
+                    .into_iter()
+                    .map(|path| Candidate::new(path, Check::None, Gate::HomeFallback)),
+            );
+        }
+        candidates
+    }
+
+    /// Refuses protected paths unless `--force-protected` was given. A
+    /// symlink to a protected dir is not protected itself, as only the link
+    /// is trashed.
+    fn protection(&self, normalized: &Path) -> Option<SkipReason> {
+        if self.options.force_protected {
+            return None;
+        }
+        let resolved = match normalized.file_name() {
+            Some(basename) => parent_realpath(normalized).join(basename),
+            None => resolve(normalized),
+        };
+        self.protected
+            .contains(&resolved)
+            .then_some(SkipReason::ProtectedPath(resolved))
+    }
+
+    /// Refuses named pipes, sockets and device nodes unless
+    /// `--allow-special` was given.
+    fn special_file(&self, normalized: &Path) -> Option<SkipReason> {
+        if self.options.allow_special {
+            return None;
+        }
+        let file_type = self.fs.symlink_metadata(normalized).ok()?.file_type();
+        is_special(&file_type).then(|| SkipReason::UnsupportedFileType(normalized.to_path_buf()))
+    }
+
+    /// Refuses up front what no trash dir could take: items on a read-only
+    /// filesystem, and immutable or append-only items or parent directories,
+    /// which can neither be renamed nor removed after a copy.
+    fn blocked(&self, normalized: &Path) -> Option<SkipReason> {
+        let parent: PathBuf = parent_realpath(normalized).components().collect();
+        if self.fs.is_read_only(&parent).unwrap_or(false) {
+            return Some(SkipReason::ReadOnlyFilesystem(parent));
+        }
+        if self.fs.is_immutable(normalized) {
+            return Some(SkipReason::ImmutableFile(normalized.to_path_buf()));
+        }
+        self.fs.is_immutable(&parent).then_some(SkipReason::ImmutableFile(parent))
+    }
+
+    /// Whether `path` and `trash_dir` live on the same device, comparing the
+    /// file's parent with the nearest existing ancestor of the trash dir;
+    /// `None` when either device cannot be queried.
+    fn same_device(&self, path: &Path, trash_dir: &Path) -> Option<bool> {
+        let file_device = self.fs.device_id(&parent_realpath(path)).ok()?;
+        let trash_dir = resolve(trash_dir);
+        let trash_device = trash_dir
+            .ancestors()
+            .find_map(|ancestor| self.fs.device_id(ancestor).ok())?;
+        Some(file_device == trash_device)
+    }
+
+    fn record_use(&self, trash_dir: &TrashDirectory, backup_path: &Path) {
+        let name = backup_path.file_name().unwrap_or_default().to_os_string();
+        let mut used = self.used.borrow_mut();
+        match used.iter_mut().find(|(used, _)| used.path == trash_dir.path) {
+            Some((_, names)) => names.push(name),
+            None => used.push((trash_dir.clone(), vec![name])),
+        }
+    }
+
+    /// `path` as the messages of `-v` show it.
+    fn shown(&self, path: &Path) -> PathBuf {
+        let home = self.environ.home().filter(|_| self.options.abbreviate_home);
+        match self.options.escape_control {
+            true => terminal_path(path, home.as_deref()),
+            false => abbreviate_home(path, home.as_deref()),
+        }
+    }
+
+    /// `path` as error messages show it: in full, escaped on a terminal.
+    fn escaped(&self, path: &Path) -> PathBuf {
+        match self.options.escape_control {
+            true => terminal_path(path, None),
+            false => path.to_path_buf(),
+        }
+    }
+
+    fn home_fallback_enabled(&self) -> bool {
+        self.options.home_fallback || self.environ.home_fallback_enabled()
+    }
+
+    /// Trashes `path` in `candidate`, returning the `files/` entry it was
+    /// moved to.
+    fn trash_in(
+        &self,
+        path: &Path,
+        basename: &OsStr,
+        volume: &Path,
+        candidate: &Candidate,
+        output: &mut CommandOutput,
+    ) -> Result<PathBuf, String> {
+        let trash_dir = &candidate.trash_dir;
+        if candidate.check == Check::TopTrashDir {
+            check_top_trash_dir(self.fs, trash_dir)?;
+        }
+        if candidate.gate == Gate::SameVolume {
+            let trash_dir_volume = volume_of(&resolve(&trash_dir.path), &self.mount_points);
+            let same_volume = match self.same_device(path, &trash_dir.path) {
+                Some(same) if self.options.forced_volume.is_none() => same,
+                _ => trash_dir_volume == volume,
+            };
+            if !same_volume {
+                return Err(tr_format(
+                    "trash dir and file to be trashed are not in the same volume, trash-dir volume: {}, file volume: {}",
+                    &[&trash_dir_volume.display(), &volume.display()],
+                ));
+            }
+        }
+        // Trash dirs are private to their owner: a missing `.Trash-$uid`,
+        // home trash or `files/` and `info/` are created 0700.
+        for dir in [&trash_dir.path, &trash_dir.files_dir, &trash_dir.info_dir] {
+            self.fs
+                .create_dir_all_with_mode(dir, 0o700)
+                .map_err(|err| tr_format("error during directory creation: {}", &[&io::Error::from(err)]))?;
+        }
+
+        let absolute_path = parent_realpath(path).join(basename);
+        let original_location = original_location_for(trash_dir, &absolute_path);
+        // The clock is read for each file, as a long batch takes a while.
+        let deleted_at = DateTime::<Local>::from(self.fs.now()).naive_local();
+        let mut content = format_trashinfo(&original_location, deleted_at);
+        if let Some(attributes) = self.fs.symlink_metadata(path).ok().as_ref().and_then(FileAttributes::of) {
+            content.push_str(&attributes.render());
+        }
+        let transaction = TrashTransaction::begin(self.fs, trash_dir, basename, &content, self.options.fsync)
+            .map_err(|err| tr_format("failed to create trashinfo: {}", &[&err]))?;
+        let (reserved, moved) = transaction
+            .commit_with(|backup_path| move_into_trash(self.fs, path, backup_path, self.options.progress, &self.cancel))
+            .map_err(|err| {
+                tr_format("failed to move {} in {}: {}", &[&path.display(), &trash_dir.files_dir.display(), &err])
+            })?;
+        if let Moved::SourceLeftBehind(err) = moved {
+            // `EPERM` deep in the tree, where `CoreError::io` cannot tell
+            // which entry carries the attribute.
+            let hint = match err.raw_os_error() == Some(libc::EPERM) {
+                true => tr(" (an entry may be immutable or append-only, see 'lsattr -R')"),
+                false => "",
+            };
+            output.stderr.push(tr_format(
+                "trash-put: '{}' was copied to {} but could not be removed: {}{}",
+                &[&self.escaped(path).display(), &self.escaped(&reserved.backup_path).display(), &err, &hint],
+            ));
+            output.exit_code = ExitCodes::of(CommandKind::Put).failure;
+        }
+        TrashIndex::update(self.fs, &trash_dir.path, |index| index.insert(reserved.name.clone(), content.clone()));
+        self.journal.record(
+            self.fs,
+            &Record::Item(JournalItem {
+                operation: Operation::Put,
+                trash_dir: std::path::absolute(&trash_dir.path).unwrap_or_else(|_| trash_dir.path.clone()),
+                name: reserved.name.clone(),
+                path: absolute_path,
+                trashinfo: content,
+            }),
+        );
+
+        if self.fs.symlink_metadata(&reserved.backup_path).is_ok_and(|metadata| metadata.is_dir()) {
+            if let Ok(size) = recursive_size(self.fs, &reserved.backup_path) {
+                let _ = record_directory_size(self.fs, &trash_dir.path, &reserved.name, size);
+            }
+        }
+        Ok(reserved.backup_path)
+    }
+}
+
+fn failure(mut output: CommandOutput, message: String) -> CommandOutcome {
+    output.stderr.push(message);
+    output.exit_code = ExitCodes::of(CommandKind::Put).failure;
+    CommandOutcome::Completed(output)
+}
+
+fn skipped(path: &Path, reason: SkipReason) -> CommandOutcome {
+    log::debug("put", format_args!("skipping: {}", reason));
+    CommandOutcome::Skipped {
+        command: CommandKind::Put,
+        path: path.to_path_buf(),
+        reason,
+    }
+}
+
+fn check_top_trash_dir(fs: &dyn FileSystem, trash_dir: &TrashDirectory) -> Result<(), String> {
+    let parent = trash_dir.path.parent().unwrap_or_else(|| Path::new("/"));
+    let problem = match fs.symlink_metadata(parent) {
+        Err(_) => Some(tr("trash dir cannot be created because its parent does not exists")),
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            if fs.metadata(parent).is_ok_and(|metadata| metadata.is_dir()) {
+                Some(tr("trash dir is insecure, its parent should not be a symlink"))
+            } else {
+                Some(tr("trash dir cannot be created as its parent is a file instead of being a directory"))
+            }
+        }
+        Ok(metadata) if !metadata.is_dir() => {
+            Some(tr("trash dir cannot be created as its parent is a file instead of being a directory"))
+        }
+        Ok(metadata) if !is_sticky(&metadata) => {
+            Some(tr("trash dir is insecure, its parent should be sticky"))
+        }
+        Ok(_) => None,
+    };
+    match problem {
+        Some(problem) => Err(tr_format(
+            "{}, trash-dir: {}, parent: {}",
+            &[&problem, &trash_dir.path.display(), &parent.display()],
+        )),
+        None => Ok(()),
+    }
+}
+
+/// How a move into the trash completed.
+enum Moved {
+    /// The item now only lives in the trash.
+    Complete,
+    /// A directory was fully copied into the trash but removing the
+    /// original failed part way; the copy is kept so nothing is lost.
+    SourceLeftBehind(io::Error),
+}
+
+/// Moves `path` to its reserved `files/` entry, falling back to copy and
+/// delete when the trash dir lives on another device.
+fn move_into_trash(
+    fs: &dyn FileSystem,
+    path: &Path,
+    backup_path: &Path,
+    progress: bool,
+    cancel: &CancellationToken,
+) -> io::Result<Moved> {
+    match fs.rename(path, backup_path).map_err(io::Error::from) {
+        Ok(()) => Ok(Moved::Complete),
+        Err(err) if err.raw_os_error() == Some(libc::EXDEV) => {
+            copy_then_remove(fs, path, backup_path, progress, cancel)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Copies `source` to `dest` and removes `source` only once the whole copy
+/// succeeded. A failed copy is removed again, so the trash never holds a
+/// truncated item, nor a duplicate of a file that could not be removed.
+///
+/// With `progress`, the copy's progress is shown when stderr is a terminal.
+/// Cancelling `cancel` stops the copy, which is then removed as a failed one.
+fn copy_then_remove(
+    fs: &dyn FileSystem,
+    source: &Path,
+    dest: &Path,
+    progress: bool,
+    cancel: &CancellationToken,
+) -> io::Result<Moved> {
+    let is_dir = fs.symlink_metadata(source)?.is_dir();
+    let mut progress: Box<dyn Progress> = match progress {
+        true => stderr_progress("trash-put", "copied"),
+        false => Box::new(NoProgress),
+    };
+    if progress.is_visible() {
+        progress.start(None, recursive_size(fs, source).ok());
+    }
+    let copied = copy_tree_cancellable(fs, source, dest, progress.as_mut(), cancel);
+    progress.finish();
+    if let Err(err) = copied {
+        remove_partial_copy(fs, dest);
+        return Err(err.into());
+    }
+    if is_dir {
+        return Ok(match fs.remove_dir_all(source) {
+            Ok(()) => Moved::Complete,
+            Err(err) => Moved::SourceLeftBehind(err.into()),
+        });
+    }
+    if let Err(err) = fs.remove_file(source) {
+        remove_partial_copy(fs, dest);
+        return Err(err.into());
+    }
+    Ok(Moved::Complete)
+}
+
+/// The last `/`-separated segment of `path` as typed, like Python's
+/// `os.path.basename` (empty for a trailing slash).
+#[cfg(unix)]
+fn last_segment(path: &Path) -> &OsStr {
+    use std::os::unix::ffi::OsStrExt;
+    let bytes = path.as_os_str().as_bytes();
+    let start = bytes.iter().rposition(|byte| *byte == b'/').map_or(0, |index| index + 1);
+    OsStr::from_bytes(&bytes[start..])
+}
+
+#[cfg(not(unix))]
+fn last_segment(path: &Path) -> &OsStr {
+    match path.to_str() {
+        Some(text) => OsStr::new(&text[text.rfind('/').map_or(0, |index| index + 1)..]),
+        None => path.file_name().unwrap_or_default(),
+    }
+}
+
+/// Canonical form of the directory containing `path`.
+fn parent_realpath(path: &Path) -> PathBuf {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    resolve(parent)
+}
+
+/// Textual description of what `path` points to, as used in diagnostics.
+pub fn describe(fs: &dyn FileSystem, path: &Path) -> &'static str {
+    let metadata = match fs.symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return tr("non existent"),
+    };
+    if metadata.file_type().is_symlink() {
+        return tr("symbolic link");
+    }
+    if metadata.is_dir() {
+        return match last_segment(path).to_str() {
+            Some(".") if path.as_os_str() != "." => tr("'.' directory"),
+            Some("..") if path.as_os_str() != ".." => tr("'..' directory"),
+            _ => tr("directory"),
+        };
+    }
+    if metadata.is_file() {
+        return if metadata.len() == 0 {
+            tr("regular empty file")
+        } else {
+            tr("regular file")
+        };
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        let file_type = metadata.file_type();
+        if file_type.is_fifo() {
+            return tr("named pipe");
+        }
+        if file_type.is_socket() {
+            return tr("socket");
+        }
+        if file_type.is_block_device() {
+            return tr("block device");
+        }
+        if file_type.is_char_device() {
+            return tr("character device");
+        }
+    }
+    tr("entry")
+}
+
+/// Whether `file_type` is a named pipe, a socket or a device node.
+#[cfg(unix)]
+fn is_special(file_type: &fs::FileType) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    file_type.is_fifo() || file_type.is_socket() || file_type.is_block_device() || file_type.is_char_device()
+}
+
+#[cfg(not(unix))]
+fn is_special(_file_type: &fs::FileType) -> bool {
+    false
+}
+
+/// Whether the sticky bit is set, as the parent of a `$topdir/.Trash` needs.
+#[cfg(unix)]
+fn is_sticky(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o1000 != 0
+}
+
+#[cfg(not(unix))]
+fn is_sticky(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// `/`, the home directory, every mount point root and every trash dir
+/// files could be trashed into, all resolved.
+fn protected_paths(
+    environ: &dyn Environment,
+    uid: u32,
+    mount_points: &[PathBuf],
+    trash_dir: Option<&Path>,
+    configured: &[PathBuf],
+) -> Vec<PathBuf> {
+    let mut protected = vec![PathBuf::from("/")];
+    protected.extend_from_slice(configured);
+    protected.extend(environ.home());
+    protected.extend(home_trash_dirs(environ));
+    protected.extend(trash_dir.map(Path::to_path_buf));
+    for mount_point in mount_points {
+        protected.push(mount_point.clone());
+        protected.push(mount_point.join(".Trash"));
+        protected.push(mount_point.join(".Trash").join(uid.to_string()));
+        protected.push(mount_point.join(format!(".Trash-{}", uid)));
+    }
+    protected.iter().map(|path| resolve(path)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::MapEnvironment;
+    use crate::fs::RealFileSystem;
+
+    #[test]
+    fn force_alone_does_not_trash_protected_paths() {
+        let root = std::env::temp_dir().join(format!("trash-cli-put-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("www")).unwrap();
+        let environ = MapEnvironment::new(Default::default(), 1000);
+        let mut options = TrashOptions {
+            force: true,
+            protected_paths: vec![root.join("www")],
+            ..TrashOptions::default()
+        };
+
+        let forced = Trasher::new(&RealFileSystem, &environ, &options).protection(&root.join("www"));
+        options.force_protected = true;
+        let overridden = Trasher::new(&RealFileSystem, &environ, &options).protection(&root.join("www"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+        assert!(matches!(forced, Some(SkipReason::ProtectedPath(path)) if path == resolve(&root.join("www"))));
+        assert!(overridden.is_none());
+    }
+}
@@ -0,0 +1,117 @@
+//! Bounded trash dirs: the `[quota]` table of the config file limits the
+//! size and the age of the items of each trash dir, and trash-put purges
+//! the oldest items of the trash dirs it trashed into until they fit.
+//!
+//! ```toml
+//! [quota]
+//! max_size = "10G"          # per trash dir, see parse_size
+//! max_age = "30d"           # see parse_human_duration; an integer is days
+//! ```
+
+use crate::api::purge_with;
+use crate::fs::{recursive_size, FileSystem};
+use crate::journal::Journal;
+use crate::log;
+use crate::models::{TrashDirectory, TrashedItem};
+use crate::trashinfo::{entry_name, parse_deletion_date, resolve_original_location, TrashInfo};
+use chrono::{DateTime, Utc};
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Limits on the items of each trash dir.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct QuotaPolicy {
+    /// Total size of the trashed items, in bytes.
+    pub max_size: Option<u64>,
+    /// Items trashed longer ago than this are purged.
+    pub max_age: Option<Duration>,
+}
+
+impl QuotaPolicy {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_size.is_none() && self.max_age.is_none()
+    }
+
+    /// Purges the items of `trash_dir` that do not fit: first those older
+    /// than `max_age`, then the oldest ones (items without a deletion date
+    /// count as oldest) until the rest fits in `max_size`.
+    ///
+    /// Items named in `spare`, such as those just trashed, are never purged,
+    /// even when they alone exceed the limit. Orphans are left to
+    /// `trash-empty --gc-orphans`, and items whose trashinfo cannot be parsed
+    /// are left alone. Each purged item comes back with its size; failures
+    /// are reported and the next item is tried.
+    pub fn enforce(
+        &self,
+        fs: &dyn FileSystem,
+        journal: &Journal,
+        trash_dir: &TrashDirectory,
+        spare: &[OsString],
+    ) -> Vec<crate::Result<TrashedItem>> {
+        if self.is_unlimited() {
+            return Vec::new();
+        }
+        let mut items = match items(fs, trash_dir) {
+            Ok(items) => items,
+            Err(err) => return vec![Err(err)],
+        };
+        if self.max_size.is_some() {
+            for item in &mut items {
+                item.size_bytes = Some(recursive_size(fs, &item.trashed_path).unwrap_or(0));
+            }
+        }
+        items.sort_by_key(|item| item.deleted_at);
+        let now = DateTime::<Utc>::from(fs.now());
+        let expired = |item: &TrashedItem| match (self.max_age, item.deleted_at) {
+            (Some(max_age), Some(deleted_at)) => (now - deleted_at).to_std().is_ok_and(|age| age > max_age),
+            _ => false,
+        };
+        let mut total: u64 = items.iter().filter_map(|item| item.size_bytes).sum();
+        let mut purged = Vec::new();
+        for item in items {
+            let name = item.trashed_path.file_name().unwrap_or_default();
+            let over_size = self.max_size.is_some_and(|max_size| total > max_size);
+            if spare.iter().any(|spared| spared == name) || !(expired(&item) || over_size) {
+                continue;
+            }
+            log::info("quota", format_args!("purging {}", item.trashed_path.display()));
+            match purge_with(fs, journal, &item) {
+                Ok(()) => {
+                    total -= item.size_bytes.unwrap_or(0);
+                    purged.push(Ok(item));
+                }
+                Err(err) => purged.push(Err(err)),
+            }
+        }
+        purged
+    }
+}
+
+/// The items of `trash_dir` that have a valid trashinfo, in directory order:
+/// an item the trashinfo does not identify is never purged.
+fn items(fs: &dyn FileSystem, trash_dir: &TrashDirectory) -> crate::Result<Vec<TrashedItem>> {
+    if !fs.exists(&trash_dir.info_dir) {
+        return Ok(Vec::new());
+    }
+    let volume = trash_dir.mount_point.clone().unwrap_or_else(|| PathBuf::from("/"));
+    let mut items = Vec::new();
+    for entry in fs.read_dir_iter(&trash_dir.info_dir)? {
+        let info_path = entry?.path;
+        let Some(name) = entry_name(&info_path) else {
+            continue;
+        };
+        let content = fs.read_to_string(&info_path).unwrap_or_default();
+        let original_path = match TrashInfo::parse(&content) {
+            Ok(info) => resolve_original_location(&volume, &info.path),
+            Err(err) => {
+                log::debug("quota", format_args!("skipping {}: {}", info_path.display(), err));
+                continue;
+            }
+        };
+        let mut item = TrashedItem::new(original_path, trash_dir.files_dir.join(name), info_path, trash_dir.path.clone());
+        item.deleted_at = parse_deletion_date(&content);
+        items.push(item);
+    }
+    Ok(items)
+}
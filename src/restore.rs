@@ -0,0 +1,368 @@
+//! Putting trashed items back where they came from, as trash-restore does:
+//! an existing destination is handled by the [`Conflict`] policy, missing
+//! parents are recreated and the trashinfo, and a directory's entry in the
+//! `directorysizes` cache, are removed once the item has moved. The
+//! permissions, ownership and modification time trash-put stored in the
+//! trashinfo are put back.
+//!
+//! [`crate::api::restore`] is the entry point for other applications.
+
+use crate::directory_sizes::forget_directory_size;
+use crate::environment::Environment;
+use crate::errors::{CoreError, ResultExt};
+use crate::fs::{copy_tree, remove_partial_copy, FileSystem};
+use crate::i18n::tr_format;
+use crate::index::TrashIndex;
+use crate::journal::{Journal, JournalItem, Operation, Record};
+use crate::log;
+use crate::models::TrashedItem;
+use crate::trashinfo::{FileAttributes, TrashInfo};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// What to do when an item's destination is taken again.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum Conflict {
+    /// Refuse, like the Python implementation.
+    #[default]
+    Fail,
+    /// Replace the existing entry, which is kept until the item is in place.
+    Overwrite,
+    /// Move the existing entry aside to `NAME~` (replacing an older backup).
+    Backup,
+    /// Leave both the existing entry and the trashed item alone.
+    Skip,
+}
+
+/// How items are restored.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct RestoreOptions {
+    pub conflict: Conflict,
+    /// Directory to restore into instead of each item's original parent
+    /// (`--to`).
+    pub to: Option<PathBuf>,
+}
+
+/// How a single restore ended.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Restored {
+    /// The item was moved to this path.
+    Done(PathBuf),
+    /// The destination exists and the policy is [`Conflict::Skip`].
+    Skipped(PathBuf),
+}
+
+/// Puts trashed items back, following the conflict policy and `to`.
+pub struct Restorer<'a> {
+    fs: &'a dyn FileSystem,
+    options: &'a RestoreOptions,
+    journal: Journal,
+}
+
+impl<'a> Restorer<'a> {
+    pub fn new(fs: &'a dyn FileSystem, environ: &dyn Environment, options: &'a RestoreOptions) -> Self {
+        Restorer {
+            fs,
+            options,
+            journal: Journal::open(environ),
+        }
+    }
+
+    /// Where `item` is restored to: its original location, or the same
+    /// basename inside `to`.
+    pub fn destination(&self, item: &TrashedItem) -> PathBuf {
+        match (&self.options.to, item.original_path.file_name()) {
+            (Some(to), Some(name)) => to.join(name),
+            _ => item.original_path.clone(),
+        }
+    }
+
+    /// Moves `item` to its destination and drops its trashinfo, resolving an
+    /// existing destination according to the conflict policy.
+    ///
+    /// With [`Conflict::Fail`], a taken destination is a
+    /// [`CoreError::Conflict`] whose message is the one trash-restore prints.
+    pub fn restore(&self, item: &TrashedItem) -> crate::Result<Restored> {
+        let fs = self.fs;
+        let destination = self.destination(item);
+        // An overwritten destination waits here until the item is in place,
+        // and goes back, like a backup, when the item cannot be moved.
+        let mut aside = None;
+        let mut backup = None;
+        if fs.symlink_metadata(&destination).is_ok() {
+            match self.options.conflict {
+                Conflict::Fail => {
+                    return Err(CoreError::conflict(tr_format(
+                        "Refusing to overwrite existing file \"{}\".",
+                        &[&destination.file_name().unwrap_or_default().to_string_lossy()],
+                    )))
+                }
+                Conflict::Skip => return Ok(Restored::Skipped(destination)),
+                Conflict::Backup => {
+                    let mut name = destination.clone().into_os_string();
+                    name.push("~");
+                    fs.rename(&destination, Path::new(&name))
+                        .with_context(|| tr_format("cannot back up {}", &[&destination.display()]))?;
+                    backup = Some(PathBuf::from(name));
+                }
+                // A file on the same filesystem is replaced by the rename itself.
+                Conflict::Overwrite if replaces_in_place(fs, &item.trashed_path, &destination) => {}
+                Conflict::Overwrite => {
+                    let temporary = aside_path(fs, &destination);
+                    fs.rename(&destination, &temporary)
+                        .with_context(|| tr_format("cannot overwrite {}", &[&destination.display()]))?;
+                    aside = Some(temporary);
+                }
+            }
+        }
+        // Kept for the journal, so that trash-undo can put the item back.
+        let trashinfo = fs.read_to_string(&item.info_path).unwrap_or_default();
+        let moved = match destination.parent() {
+            Some(parent) => fs.create_dir_all(parent),
+            None => Ok(()),
+        }
+        .and_then(|()| {
+            move_path(fs, &item.trashed_path, &destination).map_err(|err| CoreError::io(&item.trashed_path, err))
+        });
+        if let Err(err) = moved {
+            for set_aside in aside.iter().chain(&backup) {
+                if let Err(err) = fs.rename(set_aside, &destination) {
+                    log::debug("restore", format_args!("cannot put back {}: {}", destination.display(), err));
+                }
+            }
+            return Err(err);
+        }
+        if let Some(aside) = &aside {
+            if let Err(err) = fs.remove_dir_all(aside) {
+                log::debug("restore", format_args!("cannot remove the overwritten {}: {}", aside.display(), err));
+            }
+        }
+        if let Some(attributes) = TrashInfo::parse(&trashinfo).ok().and_then(|info| info.attributes()) {
+            restore_attributes(fs, &destination, &attributes);
+        }
+        fs.remove_file(&item.info_path)?;
+        let name = item.trashed_path.file_name().unwrap_or_default();
+        TrashIndex::update(fs, &item.trash_dir, |index| {
+            index.remove(name);
+        });
+        if fs.symlink_metadata(&destination).is_ok_and(|metadata| metadata.is_dir()) {
+            let _ = forget_directory_size(fs, &item.trash_dir, name);
+        }
+        self.journal.record(
+            fs,
+            &Record::Item(JournalItem {
+                operation: Operation::Restore,
+                trash_dir: std::path::absolute(&item.trash_dir).unwrap_or_else(|_| item.trash_dir.clone()),
+                name: name.to_os_string(),
+                path: destination.clone(),
+                trashinfo,
+            }),
+        );
+        Ok(Restored::Done(destination))
+    }
+}
+
+/// Gives `path` back the attributes it was trashed with, as far as allowed:
+/// only root may give a file away, so a failed change of owner is expected
+/// and only logged, like any other failure.
+#[cfg(unix)]
+fn restore_attributes(fs: &dyn FileSystem, path: &Path, attributes: &FileAttributes) {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let Ok(metadata) = fs.symlink_metadata(path) else {
+        return;
+    };
+    if metadata.file_type().is_symlink() {
+        return;
+    }
+    let mut failures = Vec::new();
+    if (metadata.uid(), metadata.gid()) != (attributes.uid, attributes.gid) {
+        failures.extend(fs.set_owner(path, attributes.uid, attributes.gid).err());
+    }
+    // Changing the owner clears setuid and setgid, so the mode comes after.
+    failures.extend(fs.set_permissions(path, std::fs::Permissions::from_mode(attributes.mode)).err());
+    let accessed = metadata.accessed().unwrap_or(attributes.modified);
+    failures.extend(fs.set_times(path, accessed, attributes.modified).err());
+    for err in failures {
+        log::debug("restore", format_args!("cannot restore the attributes of {}: {}", path.display(), err));
+    }
+}
+
+#[cfg(not(unix))]
+fn restore_attributes(_fs: &dyn FileSystem, _path: &Path, _attributes: &FileAttributes) {}
+
+/// Whether renaming `source` over `dest` replaces it atomically: neither is
+/// a directory and both are on the same filesystem.
+#[cfg(unix)]
+fn replaces_in_place(fs: &dyn FileSystem, source: &Path, dest: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (fs.symlink_metadata(source), fs.symlink_metadata(dest)) {
+        (Ok(source), Ok(dest)) => !source.is_dir() && !dest.is_dir() && source.dev() == dest.dev(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn replaces_in_place(_fs: &dyn FileSystem, _source: &Path, _dest: &Path) -> bool {
+    false
+}
+
+/// A free hidden sibling of `path` to keep an overwritten entry in until
+/// the restore is done.
+fn aside_path(fs: &dyn FileSystem, path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    (0..)
+        .map(|attempt| path.with_file_name(format!(".{}.{}.{}.restore", name, std::process::id(), attempt)))
+        .find(|candidate| fs.symlink_metadata(candidate).is_err())
+        .unwrap_or_default()
+}
+
+/// Renames `source` to `dest`, copying and deleting across devices. A failed
+/// copy is removed again, so that a retry does not meet half of it.
+fn move_path(fs: &dyn FileSystem, source: &Path, dest: &Path) -> io::Result<()> {
+    match fs.rename(source, dest).map_err(io::Error::from) {
+        Err(err) if err.raw_os_error() == Some(libc::EXDEV) => {
+            if let Err(err) = copy_tree(fs, source, dest) {
+                remove_partial_copy(fs, dest);
+                return Err(err.into());
+            }
+            fs.remove_dir_all(source).map_err(io::Error::from)
+        }
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::MapEnvironment;
+    use crate::fs::RealFileSystem;
+
+    /// A scratch dir holding `Trash/` with `name` trashed in it, and a
+    /// destination `name` that is already taken.
+    fn taken_destination(test: &str, name: &str) -> (PathBuf, TrashedItem) {
+        let root = std::env::temp_dir().join(format!("trash-cli-restore-{}-{}", test, std::process::id()));
+        let trash_dir = root.join("Trash");
+        std::fs::create_dir_all(trash_dir.join("files")).unwrap();
+        std::fs::create_dir_all(trash_dir.join("info")).unwrap();
+        std::fs::write(trash_dir.join("files").join(name), "trashed").unwrap();
+        let trashinfo = format!("[Trash Info]\nPath={}\nDeletionDate=2024-01-01T00:00:00\n", root.join(name).display());
+        std::fs::write(trash_dir.join("info").join(format!("{}.trashinfo", name)), trashinfo).unwrap();
+        std::fs::write(root.join(name), "existing").unwrap();
+        let item = TrashedItem::new(
+            root.join(name),
+            trash_dir.join("files").join(name),
+            trash_dir.join("info").join(format!("{}.trashinfo", name)),
+            trash_dir,
+        );
+        (root, item)
+    }
+
+    fn restore(conflict: Conflict, item: &TrashedItem) -> crate::Result<Restored> {
+        let options = RestoreOptions { conflict, to: None };
+        let environ = MapEnvironment::new(Default::default(), 1000);
+        Restorer::new(&RealFileSystem, &environ, &options).restore(item)
+    }
+
+    fn read(path: &Path) -> Option<String> {
+        std::fs::read_to_string(path).ok()
+    }
+
+    #[test]
+    fn fail_refuses_a_taken_destination() {
+        let (root, item) = taken_destination("fail", "notes.txt");
+        let result = restore(Conflict::Fail, &item);
+        let (destination, trashed) = (read(&root.join("notes.txt")), read(&item.trashed_path));
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert!(matches!(result, Err(CoreError::Conflict(message)) if message.contains("notes.txt")));
+        assert_eq!(destination.as_deref(), Some("existing"));
+        assert_eq!(trashed.as_deref(), Some("trashed"));
+    }
+
+    #[test]
+    fn skip_leaves_a_taken_destination_alone() {
+        let (root, item) = taken_destination("skip", "notes.txt");
+        let result = restore(Conflict::Skip, &item);
+        let (destination, trashed) = (read(&root.join("notes.txt")), read(&item.trashed_path));
+        let info_kept = item.info_path.exists();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert!(matches!(result, Ok(Restored::Skipped(path)) if path == root.join("notes.txt")));
+        assert_eq!(destination.as_deref(), Some("existing"));
+        assert_eq!(trashed.as_deref(), Some("trashed"));
+        assert!(info_kept);
+    }
+
+    #[test]
+    fn overwrite_replaces_a_taken_destination() {
+        let (root, item) = taken_destination("overwrite", "notes.txt");
+        let result = restore(Conflict::Overwrite, &item);
+        let destination = read(&root.join("notes.txt"));
+        let info_kept = item.info_path.exists();
+        let leftovers = std::fs::read_dir(&root).unwrap().count();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert!(matches!(result, Ok(Restored::Done(_))));
+        assert_eq!(destination.as_deref(), Some("trashed"));
+        assert!(!info_kept);
+        assert_eq!(leftovers, 2);
+    }
+
+    #[test]
+    fn backup_renames_a_taken_destination() {
+        let (root, item) = taken_destination("backup", "notes.txt");
+        let result = restore(Conflict::Backup, &item);
+        let (destination, backup) = (read(&root.join("notes.txt")), read(&root.join("notes.txt~")));
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert!(matches!(result, Ok(Restored::Done(_))));
+        assert_eq!(destination.as_deref(), Some("trashed"));
+        assert_eq!(backup.as_deref(), Some("existing"));
+    }
+
+    #[test]
+    fn undoes_the_backup_when_the_move_fails() {
+        let (root, item) = taken_destination("backup-fails", "notes.txt");
+        // The trashed copy is gone, so moving it fails.
+        std::fs::remove_file(&item.trashed_path).unwrap();
+        let result = restore(Conflict::Backup, &item);
+        let (destination, backup) = (read(&root.join("notes.txt")), root.join("notes.txt~").exists());
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert!(result.is_err());
+        assert_eq!(destination.as_deref(), Some("existing"));
+        assert!(!backup);
+    }
+
+    #[test]
+    fn keeps_an_overwritten_destination_when_the_move_fails() {
+        let root = std::env::temp_dir().join(format!("trash-cli-restore-{}", std::process::id()));
+        let trash_dir = root.join("Trash");
+        std::fs::create_dir_all(trash_dir.join("info")).unwrap();
+        std::fs::create_dir_all(root.join("notes")).unwrap();
+        std::fs::write(root.join("notes/keep.txt"), "kept").unwrap();
+        // The trashed copy is gone, so moving it fails.
+        let item = TrashedItem::new(
+            root.join("notes"),
+            trash_dir.join("files/notes"),
+            trash_dir.join("info/notes.trashinfo"),
+            trash_dir.clone(),
+        );
+        let options = RestoreOptions {
+            conflict: Conflict::Overwrite,
+            to: None,
+        };
+        let environ = MapEnvironment::new(Default::default(), 1000);
+        let result = Restorer::new(&RealFileSystem, &environ, &options).restore(&item);
+        let kept = std::fs::read_to_string(root.join("notes/keep.txt"));
+        let leftovers = std::fs::read_dir(&root).unwrap().count();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert!(result.is_err());
+        assert_eq!(kept.unwrap(), "kept");
+        assert_eq!(leftovers, 2);
+    }
+}
@@ -0,0 +1,66 @@
+//! Ordering of trashed-item listings shared by commands that present
+//! candidates to the user (trash-restore, trash-list).
+
+use crate::models::TrashedItem;
+
+/// Field a listing is ordered by; every order is ascending.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum SortKey {
+    /// Oldest deletion first; undated items go first.
+    #[default]
+    Date,
+    /// By original path, then by deletion date.
+    Path,
+    /// Smallest first; items of unknown size go first.
+    Size,
+    /// Keep the order the items were found in.
+    None,
+}
+
+impl SortKey {
+    /// Names accepted on the command line, in help order.
+    pub const NAMES: [&'static str; 4] = ["date", "path", "size", "none"];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "date" => Some(Self::Date),
+            "path" => Some(Self::Path),
+            "size" => Some(Self::Size),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Date => "date",
+            Self::Path => "path",
+            Self::Size => "size",
+            Self::None => "none",
+        }
+    }
+
+    /// Whether sorting by this key needs `size_bytes` to be filled in.
+    pub fn needs_size(&self) -> bool {
+        matches!(self, Self::Size)
+    }
+}
+
+/// Sorts `items` by `key`; the sort is stable, so ties keep their order.
+pub fn sort_items(items: &mut [TrashedItem], key: SortKey) {
+    sort_items_by(items, key, |item| item);
+}
+
+/// [`sort_items`] for values that hold a [`TrashedItem`], e.g. paired with
+/// the line that lists it.
+pub fn sort_items_by<T>(items: &mut [T], key: SortKey, item: impl Fn(&T) -> &TrashedItem) {
+    match key {
+        SortKey::Date => items.sort_by_key(|value| item(value).deleted_at),
+        SortKey::Path => items.sort_by(|a, b| {
+            let (a, b) = (item(a), item(b));
+            (&a.original_path, a.deleted_at).cmp(&(&b.original_path, b.deleted_at))
+        }),
+        SortKey::Size => items.sort_by_key(|value| item(value).size_bytes),
+        SortKey::None => {}
+    }
+}
@@ -0,0 +1,166 @@
+//! Colored output of trash-list and trash-restore. `--color` (or `color` in
+//! the config file) says when: `auto` colors a terminal unless `NO_COLOR` is
+//! set to something, `always` and `never` mean what they say. The look of
+//! each kind of text comes from the `[colors]` table of the config file:
+//!
+//! ```toml
+//! [colors]
+//! date = "blue"             # the defaults
+//! size = "green"
+//! missing = "red"           # items whose trashed copy is gone
+//! orphan = "yellow"         # trashed copies without trashinfo (--check)
+//! ```
+//!
+//! A style is made of space-separated words: `bold`, `dim`, `italic`,
+//! `underline`, `reverse`, a color (`red`, `bright-blue`, ...) or raw SGR
+//! parameters such as `38;5;208`. An empty style leaves that text alone.
+
+use crate::config::ColorChoice;
+use crate::environment::{Environment, NO_COLOR};
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::io::IsTerminal;
+
+/// Colors in SGR order: `30 + index` in the foreground, `90 + index` for
+/// their `bright-` variants.
+const COLORS: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+/// What a piece of text shows, which decides its style.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Role {
+    Date,
+    Size,
+    /// An item whose trashed copy is gone.
+    Missing,
+    /// A trashed copy without trashinfo.
+    Orphan,
+}
+
+impl Role {
+    /// Every role, in the order of [`Role::NAMES`].
+    pub const ALL: [Role; 4] = [Role::Date, Role::Size, Role::Missing, Role::Orphan];
+    /// Keys of the `[colors]` table.
+    pub const NAMES: [&'static str; 4] = ["date", "size", "missing", "orphan"];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::NAMES.iter().position(|known| *known == name).map(|index| Self::ALL[index])
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        Self::NAMES[*self as usize]
+    }
+}
+
+/// The parameters of an SGR sequence, e.g. `1;32` for bold green; empty
+/// for plain text.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Style(String);
+
+impl Style {
+    /// Parses the words of a style (see the module documentation).
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut codes = Vec::new();
+        for word in text.split_whitespace() {
+            let code = match word {
+                "bold" => "1".to_string(),
+                "dim" => "2".to_string(),
+                "italic" => "3".to_string(),
+                "underline" => "4".to_string(),
+                "reverse" => "7".to_string(),
+                _ if word.bytes().all(|byte| byte.is_ascii_digit() || byte == b';') => word.to_string(),
+                _ => match word.strip_prefix("bright-") {
+                    Some(color) => (90 + color_index(color, word)?).to_string(),
+                    None => (30 + color_index(word, word)?).to_string(),
+                },
+            };
+            codes.push(code);
+        }
+        Ok(Style(codes.join(";")))
+    }
+
+    fn color(code: &str) -> Self {
+        Style(code.to_string())
+    }
+}
+
+fn color_index(color: &str, word: &str) -> Result<usize, String> {
+    COLORS.iter().position(|known| *known == color).ok_or_else(|| {
+        format!(
+            "unknown style '{}' (use bold, dim, italic, underline, reverse, a color like red or bright-blue, \
+             or SGR codes like 38;5;208)",
+            word
+        )
+    })
+}
+
+/// The style of each [`Role`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Theme {
+    styles: [Style; 4],
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            styles: [Style::color("34"), Style::color("32"), Style::color("31"), Style::color("33")],
+        }
+    }
+}
+
+impl Theme {
+    pub fn style(&self, role: Role) -> &Style {
+        &self.styles[role as usize]
+    }
+
+    pub fn set(&mut self, role: Role, style: Style) {
+        self.styles[role as usize] = style;
+    }
+}
+
+/// Styles the text written to one stream, or leaves it alone.
+#[derive(Debug, Clone, Default)]
+pub struct Painter {
+    theme: Option<Theme>,
+}
+
+impl Painter {
+    /// Paints with `theme` when `choice` asks for it on `stream`.
+    pub fn new(choice: ColorChoice, stream: &impl IsTerminal, environ: &dyn Environment, theme: &Theme) -> Self {
+        let enabled = match choice {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => stream.is_terminal() && environ.var(NO_COLOR.name).is_none_or(|value| value.is_empty()),
+        };
+        Painter {
+            theme: enabled.then(|| theme.clone()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.theme.is_some()
+    }
+
+    /// `text` in the style of `role`.
+    pub fn paint<'a>(&self, role: Role, text: &'a str) -> Cow<'a, str> {
+        match self.sgr(role) {
+            Some(sgr) => Cow::Owned(format!("\x1b[{}m{}\x1b[0m", sgr, text)),
+            None => Cow::Borrowed(text),
+        }
+    }
+
+    /// [`Painter::paint`] for paths and other text that need not be UTF-8.
+    pub fn paint_os(&self, role: Role, text: &OsStr) -> OsString {
+        let Some(sgr) = self.sgr(role) else {
+            return text.to_os_string();
+        };
+        let mut painted = OsString::from(format!("\x1b[{}m", sgr));
+        painted.push(text);
+        painted.push("\x1b[0m");
+        painted
+    }
+
+    fn sgr(&self, role: Role) -> Option<&str> {
+        let Style(sgr) = self.theme.as_ref()?.style(role);
+        Some(sgr.as_str()).filter(|sgr| !sgr.is_empty())
+    }
+}
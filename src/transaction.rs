@@ -0,0 +1,150 @@
+//! Two-phase trashing of a single item.
+//!
+//! The sequence is: reserve a unique name by exclusively creating its
+//! `.trashinfo`, then move the item into `files/`. If the move fails (or the
+//! transaction is dropped before committing) the trashinfo is removed again,
+//! so a trash directory never gains an orphaned trashinfo describing an item
+//! that is not there.
+
+use crate::errors::CoreError;
+use crate::fs::FileSystem;
+use crate::models::TrashDirectory;
+use crate::trashinfo::{create_trashinfo, ReservedTrashinfo};
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// A trashinfo that has been written but whose item has not been moved yet.
+pub struct TrashTransaction<'a> {
+    fs: &'a dyn FileSystem,
+    reserved: ReservedTrashinfo,
+    finished: bool,
+}
+
+impl<'a> TrashTransaction<'a> {
+    /// Phase one: reserves a unique entry for `basename` in `trash_dir` by
+    /// writing its trashinfo `content`, flushed to disk when `sync` is set.
+    pub fn begin(
+        fs: &'a dyn FileSystem,
+        trash_dir: &TrashDirectory,
+        basename: &OsStr,
+        content: &str,
+        sync: bool,
+    ) -> crate::Result<Self> {
+        let reserved = create_trashinfo(fs, trash_dir, basename, content, sync)?;
+        Ok(Self {
+            fs,
+            reserved,
+            finished: false,
+        })
+    }
+
+    pub fn reserved(&self) -> &ReservedTrashinfo {
+        &self.reserved
+    }
+
+    /// Where the item has to be moved to.
+    pub fn backup_path(&self) -> &Path {
+        &self.reserved.backup_path
+    }
+
+    /// Phase two: renames `source` into place.
+    pub fn commit(self, source: &Path) -> crate::Result<ReservedTrashinfo> {
+        let fs = self.fs;
+        self.commit_with(|backup_path| fs.rename(source, backup_path))
+            .map(|(reserved, ())| reserved)
+    }
+
+    /// Phase two with a custom `mover` (e.g. one falling back to copy and
+    /// delete across devices), called with the backup path. When `mover`
+    /// fails the trashinfo is rolled back and its error returned.
+    pub fn commit_with<T, E>(
+        mut self,
+        mover: impl FnOnce(&Path) -> Result<T, E>,
+    ) -> Result<(ReservedTrashinfo, T), E> {
+        let moved = mover(&self.reserved.backup_path);
+        if moved.is_err() {
+            let _ = self.remove_trashinfo();
+        }
+        self.finished = true;
+        moved.map(|value| (self.reserved.clone(), value))
+    }
+
+    /// Abandons the transaction, removing the reserved trashinfo.
+    pub fn rollback(mut self) -> crate::Result<()> {
+        self.finished = true;
+        self.remove_trashinfo()
+    }
+
+    fn remove_trashinfo(&self) -> crate::Result<()> {
+        match self.fs.remove_file(&self.reserved.trashinfo_path) {
+            Err(CoreError::Io(_, err)) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
+impl Drop for TrashTransaction<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.remove_trashinfo();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::RealFileSystem;
+    use std::path::PathBuf;
+
+    fn trash_dir(test: &str) -> TrashDirectory {
+        let path = std::env::temp_dir().join(format!("trash-cli-transaction-{}-{}", test, std::process::id()));
+        std::fs::create_dir_all(path.join("files")).unwrap();
+        std::fs::create_dir_all(path.join("info")).unwrap();
+        TrashDirectory::new(path.clone(), path.join("files"), path.join("info"))
+    }
+
+    fn begin<'a>(trash_dir: &TrashDirectory) -> TrashTransaction<'a> {
+        TrashTransaction::begin(&RealFileSystem, trash_dir, OsStr::new("notes.txt"), "[Trash Info]\n", false).unwrap()
+    }
+
+    #[test]
+    fn commit_keeps_the_trashinfo_and_the_item() {
+        let trash_dir = trash_dir("commit");
+        let source = trash_dir.path.join("notes.txt");
+        std::fs::write(&source, "notes").unwrap();
+        let reserved = begin(&trash_dir).commit(&source).unwrap();
+        let kept = (reserved.trashinfo_path.exists(), reserved.backup_path.exists());
+        std::fs::remove_dir_all(&trash_dir.path).unwrap();
+
+        assert_eq!(kept, (true, true));
+    }
+
+    #[test]
+    fn dropping_without_commit_removes_the_trashinfo() {
+        let trash_dir = trash_dir("drop");
+        let transaction = begin(&trash_dir);
+        let trashinfo_path = transaction.reserved().trashinfo_path.clone();
+        let reserved = trashinfo_path.exists();
+        drop(transaction);
+        let left = trashinfo_path.exists();
+        std::fs::remove_dir_all(&trash_dir.path).unwrap();
+
+        assert!(reserved);
+        assert!(!left);
+    }
+
+    #[test]
+    fn a_failed_move_leaves_nothing_behind() {
+        let trash_dir = trash_dir("failed");
+        let transaction = begin(&trash_dir);
+        let backup_path = transaction.backup_path().to_path_buf();
+        let result = transaction.commit(&trash_dir.path.join("missing"));
+        let entries = |dir: &PathBuf| std::fs::read_dir(dir).unwrap().count();
+        let left = (entries(&trash_dir.files_dir), entries(&trash_dir.info_dir), backup_path.exists());
+        std::fs::remove_dir_all(&trash_dir.path).unwrap();
+
+        assert!(result.is_err());
+        assert_eq!(left, (0, 0, false));
+    }
+}
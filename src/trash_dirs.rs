@@ -0,0 +1,229 @@
+//! Trash directory discovery (home trash plus per-volume top-dir trashes),
+//! mirroring `trashcli.trash_dirs_scanner`.
+//!
+//! Top-dir trashes are only used when they pass the checks of the
+//! FreeDesktop trash specification: `$topdir/.Trash` must be a real,
+//! sticky, world-writable directory, and `$topdir/.Trash/$uid` and
+//! `$topdir/.Trash-$uid` real directories owned by the user.
+
+use crate::environment::Environment;
+use crate::fs::FileSystem;
+use crate::log;
+use crate::models::TrashDirectory;
+use crate::mounts::volume_of;
+use crate::users::UserDatabase;
+use std::fmt;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+
+/// Why an existing top-dir trash is not used.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TrashDirProblem {
+    /// `$topdir/.Trash` does not have the sticky bit.
+    ParentNotSticky,
+    /// `$topdir/.Trash` is a symlink.
+    ParentSymlink,
+    /// `$topdir/.Trash` is not writable by every user.
+    ParentNotWorldWritable,
+    /// The trash dir itself is a symlink.
+    Symlink,
+    /// The trash dir is not a directory.
+    NotDirectory,
+    /// The trash dir belongs to another user than the one it is named for.
+    WrongOwner,
+}
+
+impl TrashDirProblem {
+    /// Stable key, as printed by `trash-list --trash-dirs`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ParentNotSticky => "parent_not_sticky",
+            Self::ParentSymlink => "parent_is_symlink",
+            Self::ParentNotWorldWritable => "parent_not_world_writable",
+            Self::Symlink => "is_symlink",
+            Self::NotDirectory => "not_a_directory",
+            Self::WrongOwner => "wrong_owner",
+        }
+    }
+}
+
+impl fmt::Display for TrashDirProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ParentNotSticky => "parent not sticky",
+            Self::ParentSymlink => "parent is symlink",
+            Self::ParentNotWorldWritable => "parent not world-writable",
+            Self::Symlink => "trash dir is symlink",
+            Self::NotDirectory => "trash dir is not a directory",
+            Self::WrongOwner => "trash dir owned by another user",
+        })
+    }
+}
+
+/// Outcome of probing one candidate trash directory.
+#[derive(Debug, Clone)]
+pub enum TrashDirEvent {
+    Found(TrashDirectory),
+    /// A top-dir trash exists but fails a check of the specification.
+    Skipped(PathBuf, TrashDirProblem),
+}
+
+impl TrashDirEvent {
+    pub fn found(&self) -> Option<&TrashDirectory> {
+        match self {
+            Self::Found(trash_dir) => Some(trash_dir),
+            _ => None,
+        }
+    }
+}
+
+/// Home trash directory candidates derived from `XDG_DATA_HOME` or `HOME`.
+pub fn home_trash_dirs(environ: &dyn Environment) -> Vec<PathBuf> {
+    if let Some(xdg_data_home) = environ.xdg_data_home() {
+        return vec![xdg_data_home.join("Trash")];
+    }
+    if let Some(home) = environ.home() {
+        return vec![home.join(".local/share/Trash")];
+    }
+    Vec::new()
+}
+
+/// Scans the home trash and the top-dir trashes of `volumes` for `uid`.
+pub fn scan_trash_dirs(
+    fs: &dyn FileSystem,
+    environ: &dyn Environment,
+    uid: u32,
+    volumes: &[PathBuf],
+) -> Vec<TrashDirEvent> {
+    let mut events: Vec<TrashDirEvent> = home_trash_dirs(environ)
+        .into_iter()
+        .map(|path| {
+            log::debug("trash_dirs", format_args!("home trash dir: {}", path.display()));
+            home_trash_event(path, volumes)
+        })
+        .collect();
+    for volume in volumes {
+        scan_volume(fs, volume, uid, &mut events);
+    }
+    events
+}
+
+/// Scans the trash dirs of every user: `~/.local/share/Trash` of each home
+/// plus each user's top-dir trashes on `volumes`.
+pub fn scan_all_users_trash_dirs(
+    fs: &dyn FileSystem,
+    users: &dyn UserDatabase,
+    volumes: &[PathBuf],
+) -> Vec<TrashDirEvent> {
+    let mut events = Vec::new();
+    for user in users.users() {
+        let home_trash = user.home.join(".local/share/Trash");
+        log::debug("trash_dirs", format_args!("home trash dir of {}: {}", user.name, home_trash.display()));
+        events.push(home_trash_event(home_trash, volumes));
+        for volume in volumes {
+            scan_volume(fs, volume, user.uid, &mut events);
+        }
+    }
+    events
+}
+
+/// The home trash at `path`, on the volume of `volumes` that holds it.
+fn home_trash_event(path: PathBuf, volumes: &[PathBuf]) -> TrashDirEvent {
+    let volume = volume_of(&path, volumes);
+    TrashDirEvent::Found(TrashDirectory::at(path).with_mount_point(volume))
+}
+
+/// Probes `$volume/.Trash/$uid` and `$volume/.Trash-$uid`.
+pub fn scan_volume(fs: &dyn FileSystem, volume: &Path, uid: u32, out: &mut Vec<TrashDirEvent>) {
+    log::trace("trash_dirs", format_args!("probing volume {} for uid {}", volume.display(), uid));
+    let top = volume.join(".Trash").join(uid.to_string());
+    if let Ok(metadata) = fs.symlink_metadata(&top) {
+        let parent = volume.join(".Trash");
+        let problem = check_shared_trash(fs, &parent).or_else(|| check_user_trash(&metadata, uid));
+        out.push(top_dir_event(top, volume, problem));
+    }
+
+    let alt = volume.join(format!(".Trash-{uid}"));
+    if let Ok(metadata) = fs.symlink_metadata(&alt) {
+        out.push(top_dir_event(alt, volume, check_user_trash(&metadata, uid)));
+    }
+}
+
+fn top_dir_event(path: PathBuf, volume: &Path, problem: Option<TrashDirProblem>) -> TrashDirEvent {
+    match problem {
+        Some(problem) => {
+            log::info("trash_dirs", format_args!("skipping {}: {}", path.display(), problem));
+            TrashDirEvent::Skipped(path, problem)
+        }
+        None => {
+            log::debug("trash_dirs", format_args!("top trash dir: {}", path.display()));
+            TrashDirEvent::Found(TrashDirectory::at(path).with_mount_point(volume.to_path_buf()))
+        }
+    }
+}
+
+/// Checks `$topdir/.Trash`, shared by the users of the volume.
+pub fn check_shared_trash(fs: &dyn FileSystem, path: &Path) -> Option<TrashDirProblem> {
+    let Ok(metadata) = fs.symlink_metadata(path) else {
+        return Some(TrashDirProblem::ParentNotSticky);
+    };
+    if metadata.file_type().is_symlink() {
+        return Some(TrashDirProblem::ParentSymlink);
+    }
+    if !metadata.is_dir() || mode(&metadata) & 0o1000 == 0 {
+        return Some(TrashDirProblem::ParentNotSticky);
+    }
+    if mode(&metadata) & 0o002 == 0 {
+        return Some(TrashDirProblem::ParentNotWorldWritable);
+    }
+    None
+}
+
+/// Checks a trash dir of `uid` given its metadata, symlinks not followed.
+pub fn check_user_trash(metadata: &Metadata, uid: u32) -> Option<TrashDirProblem> {
+    if metadata.file_type().is_symlink() {
+        return Some(TrashDirProblem::Symlink);
+    }
+    if !metadata.is_dir() {
+        return Some(TrashDirProblem::NotDirectory);
+    }
+    if owner(metadata).is_some_and(|owner| owner != uid) {
+        return Some(TrashDirProblem::WrongOwner);
+    }
+    None
+}
+
+#[cfg(unix)]
+fn mode(metadata: &Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode()
+}
+
+/// Without Unix permissions nothing counts as sticky or world-writable.
+#[cfg(not(unix))]
+fn mode(_metadata: &Metadata) -> u32 {
+    0
+}
+
+#[cfg(unix)]
+fn owner(metadata: &Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.uid())
+}
+
+#[cfg(not(unix))]
+fn owner(_metadata: &Metadata) -> Option<u32> {
+    None
+}
+
+/// Wraps explicit `--trash-dir` values, resolving the volume each lives on.
+pub fn user_specified_trash_dirs(trash_dirs: &[PathBuf], mount_points: &[PathBuf]) -> Vec<TrashDirEvent> {
+    trash_dirs
+        .iter()
+        .map(|path| {
+            let volume = volume_of(path, mount_points);
+            log::debug("trash_dirs", format_args!("trash dir {} on volume {}", path.display(), volume.display()));
+            TrashDirEvent::Found(TrashDirectory::at(path.clone()).with_mount_point(volume))
+        })
+        .collect()
+}
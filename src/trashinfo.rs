@@ -0,0 +1,568 @@
+//! The `.trashinfo` format defined by the FreeDesktop trash specification
+//! (version 1.0): the writer side mirrors `trashcli.put.format_trash_info`
+//! and `trashcli.put.janitor_tools.info_file_persister`, the reader side
+//! extracts the `Path=` and `DeletionDate=` keys back.
+//!
+//! Reading is lenient, as trash dirs written by other tools hold all sorts
+//! of variations (see [`TrashInfo::parse`]); only a missing or empty
+//! `Path=` makes a trashinfo unusable.
+
+use crate::errors::CoreError;
+use crate::fs::FileSystem;
+use crate::helpers::{
+    numbered_basename, parse_trash_datetime, percent_decode, percent_encode, TRASHINFO_EXTENSION, TRASHINFO_TIME_FORMAT,
+};
+use crate::models::TrashDirectory;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, BufRead, Read};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Renders a trashinfo document; `deletion_date` is local time, as the spec
+/// requires.
+pub fn format_trashinfo(original_location: &Path, deletion_date: NaiveDateTime) -> String {
+    format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        encode_original_location(original_location),
+        deletion_date.format(TRASHINFO_TIME_FORMAT)
+    )
+}
+
+/// Value to store in `Path=` for `path` (absolute, with its parent already
+/// resolved) when trashed in `trash_dir`.
+///
+/// Top directory trashes (those with a `mount_point` other than `/`) store
+/// the location relative to the volume root, as the spec recommends, so the
+/// volume can be mounted elsewhere; the home trash stores absolute paths.
+pub fn original_location_for(trash_dir: &TrashDirectory, path: &Path) -> PathBuf {
+    match &trash_dir.mount_point {
+        Some(volume) if volume != Path::new("/") => match path.strip_prefix(volume) {
+            Ok(relative) if !relative.as_os_str().is_empty() => relative.to_path_buf(),
+            _ => path.to_path_buf(),
+        },
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Reader-side counterpart of [`original_location_for`]: absolute `Path=`
+/// values are used verbatim, relative ones are joined to `volume`.
+pub fn resolve_original_location(volume: &Path, stored: &Path) -> PathBuf {
+    if stored.is_absolute() {
+        stored.to_path_buf()
+    } else {
+        volume.join(stored)
+    }
+}
+
+/// The contents of a trashinfo file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TrashInfo {
+    /// `Path=`, percent-decoded; relative to the volume for top directory
+    /// trashes (see [`resolve_original_location`]).
+    pub path: PathBuf,
+    /// `DeletionDate=` as written, which may not parse.
+    pub deletion_date: Option<String>,
+    /// Every other line but blank ones, in file order: unknown keys, keys of
+    /// other groups, comments. [`TrashInfo::render`] writes them back.
+    pub extra: Vec<String>,
+}
+
+/// Why a trashinfo cannot be used.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TrashInfoError {
+    /// No `Path=` key in the `[Trash Info]` group.
+    MissingPath,
+    /// `Path=` on this line (1-based) has no value.
+    EmptyPath(usize),
+}
+
+impl std::fmt::Display for TrashInfoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingPath => f.write_str("no Path key"),
+            Self::EmptyPath(line) => write!(f, "empty Path on line {}", line),
+        }
+    }
+}
+
+impl std::error::Error for TrashInfoError {}
+
+impl TrashInfo {
+    /// Parses `content`, accepting what other tools write besides the spec:
+    /// a byte order mark, CRLF line endings, spaces around `=`, a missing
+    /// `[Trash Info]` header (keys before any group count as its own) and
+    /// unknown keys. When a key is repeated the first value wins, like
+    /// trash-cli always did.
+    pub fn parse(content: &str) -> Result<Self, TrashInfoError> {
+        let keys = Keys::read(content);
+        match keys.path {
+            None => Err(TrashInfoError::MissingPath),
+            Some((line, "")) => Err(TrashInfoError::EmptyPath(line)),
+            Some((_, encoded)) => Ok(TrashInfo {
+                path: path_from_bytes(percent_decode(encoded)),
+                deletion_date: keys.deletion_date.map(str::to_string),
+                extra: keys.extra.into_iter().map(str::to_string).collect(),
+            }),
+        }
+    }
+
+    /// The deletion date, when it parses.
+    pub fn deleted_at(&self) -> Option<DateTime<Utc>> {
+        self.deletion_date.as_deref().and_then(parse_trash_datetime)
+    }
+
+    /// The spec's form of this trashinfo: `[Trash Info]`, `Path=` and
+    /// `DeletionDate=` first, then the extra lines as they were read.
+    pub fn render(&self) -> String {
+        let mut content = format!("[Trash Info]\nPath={}\n", encode_original_location(&self.path));
+        if let Some(deletion_date) = &self.deletion_date {
+            content.push_str(&format!("DeletionDate={}\n", deletion_date));
+        }
+        for line in &self.extra {
+            content.push_str(line);
+            content.push('\n');
+        }
+        content
+    }
+}
+
+/// Keys trash-cli adds to the `[Trash Info]` group for
+/// [`FileAttributes`]; other implementations ignore them, as the spec asks
+/// of unknown keys.
+const MODE_KEY: &str = "X-TrashCli-Mode";
+const UID_KEY: &str = "X-TrashCli-Uid";
+const GID_KEY: &str = "X-TrashCli-Gid";
+const MTIME_KEY: &str = "X-TrashCli-Mtime";
+
+/// Permissions, ownership and modification time of an item when it was
+/// trashed, which a copy across devices does not all keep. trash-put stores
+/// them in the trashinfo and trash-restore puts them back.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct FileAttributes {
+    /// Permission bits, setuid/setgid and sticky included.
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub modified: SystemTime,
+}
+
+impl FileAttributes {
+    /// The attributes of the entry `metadata` describes; `None` for a
+    /// symlink, whose own attributes mean nothing.
+    #[cfg(unix)]
+    pub fn of(metadata: &std::fs::Metadata) -> Option<Self> {
+        use std::os::unix::fs::MetadataExt;
+        if metadata.file_type().is_symlink() {
+            return None;
+        }
+        Some(FileAttributes {
+            mode: metadata.mode() & 0o7777,
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            modified: metadata.modified().ok()?,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn of(_metadata: &std::fs::Metadata) -> Option<Self> {
+        None
+    }
+
+    /// The trashinfo lines holding these attributes, to append after
+    /// [`format_trashinfo`]; the mode is octal, the time in seconds since
+    /// the epoch with nanoseconds.
+    pub fn render(&self) -> String {
+        let modified = self.modified.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+        format!(
+            "{}={:04o}\n{}={}\n{}={}\n{}={}.{:09}\n",
+            MODE_KEY,
+            self.mode,
+            UID_KEY,
+            self.uid,
+            GID_KEY,
+            self.gid,
+            MTIME_KEY,
+            modified.as_secs(),
+            modified.subsec_nanos()
+        )
+    }
+}
+
+/// `seconds[.nanoseconds]` since the epoch, as [`FileAttributes::render`]
+/// writes it.
+fn parse_mtime(value: &str) -> Option<SystemTime> {
+    let (seconds, nanos) = value.split_once('.').unwrap_or((value, "0"));
+    if nanos.is_empty() || nanos.len() > 9 {
+        return None;
+    }
+    let nanos = nanos.parse::<u32>().ok()? * 10u32.pow(9 - nanos.len() as u32);
+    SystemTime::UNIX_EPOCH.checked_add(Duration::new(seconds.parse().ok()?, nanos))
+}
+
+impl TrashInfo {
+    /// The [`FileAttributes`] trash-put stored, when all of them are there
+    /// and valid.
+    pub fn attributes(&self) -> Option<FileAttributes> {
+        let value = |wanted: &str| {
+            self.extra.iter().find_map(|line| match line.split_once('=') {
+                Some((key, value)) if key.trim() == wanted => Some(value.trim()),
+                _ => None,
+            })
+        };
+        Some(FileAttributes {
+            mode: u32::from_str_radix(value(MODE_KEY)?, 8).ok().filter(|mode| *mode <= 0o7777)?,
+            uid: value(UID_KEY)?.parse().ok()?,
+            gid: value(GID_KEY)?.parse().ok()?,
+            modified: parse_mtime(value(MTIME_KEY)?)?,
+        })
+    }
+}
+
+/// The keys of a trashinfo, as [`TrashInfo::parse`] reads them.
+struct Keys<'a> {
+    /// The line number and raw value of `Path=`.
+    path: Option<(usize, &'a str)>,
+    deletion_date: Option<&'a str>,
+    extra: Vec<&'a str>,
+}
+
+impl<'a> Keys<'a> {
+    fn read(content: &'a str) -> Self {
+        let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+        let mut keys = Keys {
+            path: None,
+            deletion_date: None,
+            extra: Vec::new(),
+        };
+        let mut in_trash_info = true;
+        for (index, line) in content.lines().enumerate() {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            match Line::of(line) {
+                Line::Blank => {}
+                Line::Group(group) => {
+                    in_trash_info = group == "Trash Info";
+                    if !in_trash_info {
+                        keys.extra.push(line);
+                    }
+                }
+                Line::Entry("Path", value) if in_trash_info => {
+                    keys.path.get_or_insert((index + 1, value));
+                }
+                Line::Entry("DeletionDate", value) if in_trash_info => {
+                    keys.deletion_date.get_or_insert(value);
+                }
+                Line::Entry(..) | Line::Other => keys.extra.push(line),
+            }
+        }
+        keys
+    }
+}
+
+/// One line of a trashinfo, without its line ending.
+enum Line<'a> {
+    Blank,
+    /// `[NAME]`, a group header.
+    Group(&'a str),
+    /// `KEY=VALUE`, with the spaces around `=` trimmed.
+    Entry(&'a str, &'a str),
+    Other,
+}
+
+impl<'a> Line<'a> {
+    fn of(line: &'a str) -> Self {
+        if line.trim().is_empty() {
+            return Line::Blank;
+        }
+        if let Some(group) = line.trim().strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            return Line::Group(group);
+        }
+        match line.split_once('=') {
+            Some((key, value)) => Line::Entry(key.trim(), value.trim_start()),
+            None => Line::Other,
+        }
+    }
+}
+
+/// Longest line [`TrashInfoKeys::read`] looks at: a `Path=` of `PATH_MAX`
+/// bytes, all percent-encoded, takes 12 KiB. Longer lines are skipped.
+const MAX_LINE_LEN: usize = 64 * 1024;
+
+/// The `Path=` and `DeletionDate=` keys of a trashinfo, all that listing an
+/// item needs.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct TrashInfoKeys {
+    /// The line number and raw value of `Path=`.
+    path: Option<(usize, String)>,
+    /// `DeletionDate=` as written, which may not parse.
+    pub deletion_date: Option<String>,
+}
+
+impl TrashInfoKeys {
+    /// Reads the keys from `reader` line by line, by the rules of
+    /// [`TrashInfo::parse`], and stops as soon as both were found, so the
+    /// rest of a huge or garbage-filled file is never read. In lines that
+    /// are not UTF-8, bytes outside ASCII are percent-encoded, so a raw
+    /// `Path=` value still decodes to the same bytes.
+    pub fn read(mut reader: impl BufRead) -> io::Result<Self> {
+        let mut keys = TrashInfoKeys::default();
+        let mut in_trash_info = true;
+        let mut buffer = Vec::new();
+        let mut number = 0;
+        while keys.path.is_none() || keys.deletion_date.is_none() {
+            if !read_line(&mut reader, &mut buffer)? {
+                break;
+            }
+            number += 1;
+            let line = match std::str::from_utf8(&buffer) {
+                Ok(line) => Cow::Borrowed(line),
+                Err(_) => Cow::Owned(buffer.iter().map(|byte| escape_non_ascii(*byte)).collect()),
+            };
+            let line = match number {
+                1 => line.strip_prefix('\u{feff}').unwrap_or(&line),
+                _ => &line,
+            };
+            match Line::of(line) {
+                Line::Group(group) => in_trash_info = group == "Trash Info",
+                Line::Entry("Path", value) if in_trash_info => {
+                    keys.path.get_or_insert_with(|| (number, value.to_string()));
+                }
+                Line::Entry("DeletionDate", value) if in_trash_info => {
+                    keys.deletion_date.get_or_insert_with(|| value.to_string());
+                }
+                Line::Blank | Line::Entry(..) | Line::Other => {}
+            }
+        }
+        Ok(keys)
+    }
+
+    /// [`TrashInfoKeys::read`] on the trashinfo at `path`.
+    pub fn load(fs: &dyn FileSystem, path: &Path) -> crate::Result<Self> {
+        let file = fs.open(path)?;
+        Self::read(io::BufReader::new(file)).map_err(|err| CoreError::io(path, err))
+    }
+
+    /// The `Path=` value, percent-decoded (see [`TrashInfo::path`]).
+    pub fn original_location(&self) -> Result<PathBuf, TrashInfoError> {
+        match &self.path {
+            None => Err(TrashInfoError::MissingPath),
+            Some((line, encoded)) if encoded.is_empty() => Err(TrashInfoError::EmptyPath(*line)),
+            Some((_, encoded)) => Ok(path_from_bytes(percent_decode(encoded))),
+        }
+    }
+
+    /// The deletion date, when it parses.
+    pub fn deleted_at(&self) -> Option<DateTime<Utc>> {
+        self.deletion_date.as_deref().and_then(parse_trash_datetime)
+    }
+}
+
+fn escape_non_ascii(byte: u8) -> String {
+    match byte.is_ascii() {
+        true => char::from(byte).to_string(),
+        false => format!("%{:02X}", byte),
+    }
+}
+
+/// Reads the next line of `reader` into `buffer`, without its line ending;
+/// a line longer than [`MAX_LINE_LEN`] is consumed but left empty. `false`
+/// at the end of the input.
+fn read_line(reader: &mut impl BufRead, buffer: &mut Vec<u8>) -> io::Result<bool> {
+    buffer.clear();
+    if reader.by_ref().take(MAX_LINE_LEN as u64 + 1).read_until(b'\n', buffer)? == 0 {
+        return Ok(false);
+    }
+    if buffer.last() == Some(&b'\n') {
+        buffer.pop();
+        if buffer.last() == Some(&b'\r') {
+            buffer.pop();
+        }
+        return Ok(true);
+    }
+    if buffer.len() > MAX_LINE_LEN {
+        buffer.clear();
+        loop {
+            let available = reader.fill_buf()?;
+            match available.iter().position(|byte| *byte == b'\n') {
+                Some(end) => {
+                    reader.consume(end + 1);
+                    break;
+                }
+                None if available.is_empty() => break,
+                None => {
+                    let len = available.len();
+                    reader.consume(len);
+                }
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// The `Path=` value of `content`, percent-decoded (see [`TrashInfo`]).
+pub fn parse_original_location(content: &str) -> Option<PathBuf> {
+    TrashInfo::parse(content).ok().map(|info| info.path)
+}
+
+/// The `DeletionDate=` value of `content`, when it parses.
+pub fn parse_deletion_date(content: &str) -> Option<DateTime<Utc>> {
+    Keys::read(content).deletion_date.and_then(parse_trash_datetime)
+}
+
+/// Percent-encodes `path` for the `Path=` key, keeping `/` separators
+/// verbatim (like Python's `quote(path, '/')`).
+pub fn encode_original_location(path: &Path) -> String {
+    percent_encode(&path_bytes(path), b"/")
+}
+
+/// The entry name `NAME` of `info/NAME.trashinfo`, byte for byte; `None`
+/// when `info_path` does not end in `.trashinfo`.
+pub fn entry_name(info_path: &Path) -> Option<OsString> {
+    let file_name = path_bytes(Path::new(info_path.file_name()?));
+    let name = file_name.strip_suffix(TRASHINFO_EXTENSION.as_bytes())?;
+    Some(path_from_bytes(name.to_vec()).into_os_string())
+}
+
+#[cfg(unix)]
+pub(crate) fn path_bytes(path: &Path) -> Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    Cow::Borrowed(path.as_os_str().as_bytes())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn path_bytes(path: &Path) -> Cow<'_, [u8]> {
+    match path.to_string_lossy() {
+        Cow::Borrowed(text) => Cow::Borrowed(text.as_bytes()),
+        Cow::Owned(text) => Cow::Owned(text.into_bytes()),
+    }
+}
+
+#[cfg(unix)]
+pub(crate) fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// A trashinfo file written for an item that is about to be moved into the
+/// trash directory.
+#[derive(Debug, Clone)]
+pub struct ReservedTrashinfo {
+    /// Entry name shared by `files/NAME` and `info/NAME.trashinfo`.
+    pub name: OsString,
+    pub trashinfo_path: PathBuf,
+    /// Destination of the trashed item inside `files/`.
+    pub backup_path: PathBuf,
+}
+
+/// Reserves a unique entry name in `trash_dir` by creating
+/// `info/NAME.trashinfo` with `content` exclusively (`O_CREAT|O_EXCL`),
+/// flushed to disk with `info/` when `sync` is set (see
+/// [`FileSystem::write_new`]).
+///
+/// Candidates are `NAME`, `NAME_1.EXT`, `NAME_2.EXT`, ... (see
+/// [`numbered_basename`]); names whose `files/` entry already exists, or
+/// whose trashinfo another writer created first, are skipped.
+pub fn create_trashinfo(
+    fs: &dyn FileSystem,
+    trash_dir: &TrashDirectory,
+    basename: &OsStr,
+    content: &str,
+    sync: bool,
+) -> crate::Result<ReservedTrashinfo> {
+    for index in 0u64.. {
+        let name = numbered_basename(basename, index);
+        let mut trashinfo_name = name.clone();
+        trashinfo_name.push(TRASHINFO_EXTENSION);
+
+        let trashinfo_path = trash_dir.info_dir.join(&trashinfo_name);
+        let backup_path = trash_dir.files_dir.join(&name);
+        if fs.symlink_metadata(&backup_path).is_ok() {
+            continue;
+        }
+        match fs.write_new(&trashinfo_path, content.as_bytes(), sync) {
+            Ok(()) => {
+                return Ok(ReservedTrashinfo {
+                    name,
+                    trashinfo_path,
+                    backup_path,
+                })
+            }
+            Err(CoreError::Io(_, err)) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    unreachable!("the candidate index space is unbounded")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(content: &str) -> TrashInfoKeys {
+        TrashInfoKeys::read(content.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn accepts_a_missing_deletion_date() {
+        let content = "[Trash Info]\nPath=/home/me/notes.txt\n";
+        let info = TrashInfo::parse(content).unwrap();
+        assert_eq!(info.path, Path::new("/home/me/notes.txt"));
+        assert_eq!(info.deletion_date, None);
+        assert_eq!(info.deleted_at(), None);
+        assert_eq!(keys(content).original_location(), Ok(PathBuf::from("/home/me/notes.txt")));
+        assert_eq!(keys(content).deleted_at(), None);
+    }
+
+    #[test]
+    fn resolves_a_relative_path_against_the_volume() {
+        let content = "[Trash Info]\nPath=docs/a%20b.txt\nDeletionDate=2024-05-01T10:11:12\n";
+        let info = TrashInfo::parse(content).unwrap();
+        assert_eq!(info.path, Path::new("docs/a b.txt"));
+        assert_eq!(resolve_original_location(Path::new("/mnt/data"), &info.path), Path::new("/mnt/data/docs/a b.txt"));
+        assert_eq!(keys(content).original_location(), Ok(PathBuf::from("docs/a b.txt")));
+    }
+
+    #[test]
+    fn keeps_extra_keys_and_other_groups() {
+        let content = "[Trash Info]\nX-Origin=nautilus\nPath=/a\nDeletionDate=2024-05-01T10:11:12\n\
+                       [Other]\nPath=/elsewhere\n";
+        let info = TrashInfo::parse(content).unwrap();
+        assert_eq!(info.path, Path::new("/a"));
+        assert_eq!(info.extra, ["X-Origin=nautilus", "[Other]", "Path=/elsewhere"]);
+        assert_eq!(TrashInfo::parse(&info.render()), Ok(info));
+        assert_eq!(keys(content).original_location(), Ok(PathBuf::from("/a")));
+    }
+
+    #[test]
+    fn accepts_crlf_line_endings() {
+        let content = "[Trash Info]\r\nPath=/a%20b\r\nDeletionDate=2024-05-01T10:11:12\r\n";
+        let info = TrashInfo::parse(content).unwrap();
+        assert_eq!(info.path, Path::new("/a b"));
+        assert_eq!(info.deletion_date.as_deref(), Some("2024-05-01T10:11:12"));
+        assert_eq!(keys(content).original_location(), Ok(PathBuf::from("/a b")));
+        assert_eq!(keys(content).deletion_date.as_deref(), Some("2024-05-01T10:11:12"));
+    }
+
+    #[test]
+    fn accepts_a_missing_header() {
+        let content = "Path=/a\nDeletionDate=2024-05-01T10:11:12\n";
+        let info = TrashInfo::parse(content).unwrap();
+        assert_eq!(info.path, Path::new("/a"));
+        assert!(info.deleted_at().is_some());
+        assert_eq!(keys(content).original_location(), Ok(PathBuf::from("/a")));
+    }
+
+    #[test]
+    fn rejects_a_missing_or_empty_path() {
+        let no_path = "[Trash Info]\nDeletionDate=2024-05-01T10:11:12\n";
+        assert_eq!(TrashInfo::parse(no_path), Err(TrashInfoError::MissingPath));
+        assert_eq!(TrashInfo::parse("[Trash Info]\nPath=\n"), Err(TrashInfoError::EmptyPath(2)));
+        assert_eq!(keys("[Trash Info]\nPath=\n").original_location(), Err(TrashInfoError::EmptyPath(2)));
+    }
+}
@@ -0,0 +1,167 @@
+//! Enumeration of users, used by `--all-users` modes.
+//!
+//! Users come from the system user database through `getpwent(3)`, so
+//! accounts served by NSS (LDAP, NIS, systemd-homed, ...) are found as well
+//! as the ones listed in `/etc/passwd`.
+
+use std::path::PathBuf;
+
+/// Password database read on platforms without `getpwent(3)`.
+pub const PASSWD_FILE: &str = "/etc/passwd";
+
+/// The subset of a passwd entry trash commands care about.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UserInfo {
+    pub name: String,
+    pub uid: u32,
+    pub home: PathBuf,
+}
+
+/// A source of users; the `--all-users` scans take one so that tests can
+/// substitute a fixed list for the system database.
+pub trait UserDatabase {
+    fn users(&self) -> Vec<UserInfo>;
+}
+
+/// The user database of the system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemUsers;
+
+impl UserDatabase for SystemUsers {
+    #[cfg(unix)]
+    fn users(&self) -> Vec<UserInfo> {
+        pwent::all_users()
+    }
+
+    #[cfg(not(unix))]
+    fn users(&self) -> Vec<UserInfo> {
+        std::fs::read_to_string(PASSWD_FILE)
+            .map(|content| parse_passwd(&content))
+            .unwrap_or_default()
+    }
+}
+
+impl UserDatabase for [UserInfo] {
+    fn users(&self) -> Vec<UserInfo> {
+        self.to_vec()
+    }
+}
+
+/// Lists the users of the system database.
+pub fn list_users() -> Vec<UserInfo> {
+    SystemUsers.users()
+}
+
+/// Parses `/etc/passwd`-formatted content, skipping malformed lines.
+pub fn parse_passwd(content: &str) -> Vec<UserInfo> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.len() < 7 {
+                return None;
+            }
+            Some(UserInfo {
+                name: fields[0].to_string(),
+                uid: fields[2].parse().ok()?,
+                home: PathBuf::from(fields[5]),
+            })
+        })
+        .collect()
+}
+
+/// Reads the NSS passwd database.
+///
+/// The `setpwent`/`getpwent`/`endpwent` cursor is global to the process,
+/// so the whole walk holds a lock.
+#[cfg(unix)]
+mod pwent {
+    use super::UserInfo;
+    use std::ffi::{CStr, OsStr};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::PathBuf;
+    use std::sync::{Mutex, PoisonError};
+
+    static CURSOR: Mutex<()> = Mutex::new(());
+
+    pub(super) fn all_users() -> Vec<UserInfo> {
+        let _cursor = CURSOR.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut users: Vec<UserInfo> = Vec::new();
+        let mut entries = Entries::new();
+        // SAFETY: the cursor is only moved while the lock is held.
+        unsafe { libc::setpwent() };
+        while let Some(user) = entries.next_entry() {
+            // A user listed by several NSS sources is reported once.
+            if !users.contains(&user) {
+                users.push(user);
+            }
+        }
+        // SAFETY: as above.
+        unsafe { libc::endpwent() };
+        users
+    }
+
+    struct Entries {
+        #[cfg(all(target_os = "linux", target_env = "gnu"))]
+        buffer: Vec<libc::c_char>,
+    }
+
+    impl Entries {
+        fn new() -> Self {
+            Self {
+                #[cfg(all(target_os = "linux", target_env = "gnu"))]
+                buffer: vec![0; 1024],
+            }
+        }
+
+        /// The next entry, growing the string buffer as needed.
+        #[cfg(all(target_os = "linux", target_env = "gnu"))]
+        fn next_entry(&mut self) -> Option<UserInfo> {
+            loop {
+                let mut entry = std::mem::MaybeUninit::<libc::passwd>::uninit();
+                let mut result = std::ptr::null_mut();
+                // SAFETY: the buffers outlive the call and their sizes are
+                // the ones passed.
+                let status = unsafe {
+                    libc::getpwent_r(entry.as_mut_ptr(), self.buffer.as_mut_ptr(), self.buffer.len(), &mut result)
+                };
+                match status {
+                    // SAFETY: on success `result` points to `entry`, whose
+                    // strings live in `self.buffer`.
+                    0 if !result.is_null() => return Some(unsafe { user_info(&*result) }),
+                    libc::ERANGE if self.buffer.len() < 1 << 20 => {
+                        let len = self.buffer.len() * 2;
+                        self.buffer.resize(len, 0);
+                    }
+                    _ => return None,
+                }
+            }
+        }
+
+        #[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+        fn next_entry(&mut self) -> Option<UserInfo> {
+            // SAFETY: the returned entry is copied before the next call.
+            let entry = unsafe { libc::getpwent() };
+            // SAFETY: a non-null entry points to a valid passwd.
+            (!entry.is_null()).then(|| unsafe { user_info(&*entry) })
+        }
+    }
+
+    /// Copies the fields of `entry`.
+    ///
+    /// # Safety
+    ///
+    /// The strings of `entry` must be valid C strings or null.
+    unsafe fn user_info(entry: &libc::passwd) -> UserInfo {
+        let string = |pointer: *const libc::c_char| match pointer.is_null() {
+            true => &[][..],
+            // SAFETY: guaranteed by the caller.
+            false => unsafe { CStr::from_ptr(pointer) }.to_bytes(),
+        };
+        UserInfo {
+            name: String::from_utf8_lossy(string(entry.pw_name)).into_owned(),
+            uid: entry.pw_uid,
+            home: PathBuf::from(OsStr::from_bytes(string(entry.pw_dir))),
+        }
+    }
+}
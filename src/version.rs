@@ -0,0 +1,14 @@
+//! What `--version` prints. Every command reports the version of the Python
+//! trash-cli it is compatible with, which is what scripts check, followed
+//! by the version of this crate.
+
+/// Version of the Python trash-cli whose behavior the commands follow.
+pub const COMPAT_VERSION: &str = "0.24.5.26";
+
+/// Version of this crate, from its Cargo manifest.
+pub const CORE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The `--version` line of `program`, like `trash-put 0.24.5.26 (core 0.1.0)`.
+pub fn version_line(program: &str) -> String {
+    format!("{} {} (core {})", program, COMPAT_VERSION, CORE_VERSION)
+}
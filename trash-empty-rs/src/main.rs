@@ -1,6 +1,27 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
 use std::env;
-use std::path::Path;
+use std::ffi::OsString;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::process::{Command, ExitStatus};
+use trash_cli_core::{
+    describe_environment, entry_name, format_datetime, format_size, list_mount_points, list_volumes, notify,
+    parse_deletion_date, parse_human_duration, parse_size, parse_trash_datetime, printable_path, recursive_size,
+    resolve_original_location, scan_trash_dirs, stderr_progress, tr, tr_format, version_line, AuditIssue,
+    CancellationToken, CommandKind, Config, CoreError, DirectorySizes, DisplayZone, Environment, FileSystem,
+    GlobPattern, Journal, JournalItem, MatchOptions, NetworkFilter, NoProgress, Operation, Progress, RealEnvironment,
+    RealFileSystem, Record, SizeFormat, TrashAudit, TrashDirectory, TrashIndex, TrashInfo, TRASHINFO_EXTENSION,
+};
+use trash_cli_core::exit_codes;
+use trash_cli_core::log;
+use trash_cli_core::cli::{
+    load_config, parse_block_size, trash_dir_arg, Arg, Complete, Nargs, Parsed, Parser, Shell, UsageError,
+    BLOCK_SIZE_ARG, BYTES_ARG, DEBUG_ENV_ARG, IGNORE_CASE_ARG, PRINT_COMPLETION_ARG, SI_ARG, VERSION_ARG,
+};
+use trash_cli_core::trash_dirs::{scan_all_users_trash_dirs, user_specified_trash_dirs};
+use trash_cli_core::environment::TRASH_EMPTY_WRAPPER_NAME;
+use trash_cli_core::users::SystemUsers;
 
 const PYTHON_BOOTSTRAP: &str = r#"import os
 import sys
@@ -12,7 +33,7 @@ raise SystemExit(main())
 const PYTHON_EXECUTABLES: [&str; 2] = ["python3", "python"];
 
 fn find_interpreter() -> Option<String> {
-    if let Ok(explicit) = env::var("TRASH_EMPTY_PYTHON_EXECUTABLE") {
+    if let Some(explicit) = RealEnvironment.python_executable(CommandKind::Empty) {
         if is_python_interpreter(&explicit) {
             return Some(explicit);
         }
@@ -49,24 +70,720 @@ fn run_python_backend() -> std::io::Result<ExitStatus> {
     let status = command
         .arg("-c")
         .arg(PYTHON_BOOTSTRAP)
-        .env("TRASH_EMPTY_WRAPPER_NAME", wrapper_name)
+        .env(TRASH_EMPTY_WRAPPER_NAME.name, wrapper_name)
         .args(args)
         .status()?;
     Ok(status)
 }
 
-fn main() {
+const ARGS: &[Arg] = &[
+    PRINT_COMPLETION_ARG,
+    DEBUG_ENV_ARG,
+    VERSION_ARG,
+    Arg::flag("verbose", &["-v", "--verbose"], "list files that will be deleted"),
+    Arg::option("trash_dir", &["--trash-dir"], "TRASH_DIR", "specify the trash directory to use")
+        .complete(Complete::Directory),
+    Arg::flag("print_time", &["--print-time"], "").hidden(),
+    Arg::flag("all_users", &["--all-users"], "empty all trashcan of all the users"),
+    Arg::flag(
+        "include_network",
+        &["--include-network"],
+        "also scan network filesystems (NFS, CIFS, sshfs, ...) for trash dirs",
+    ),
+    Arg::flag("interactive", &["-i", "--interactive"], "ask before emptying trash directories"),
+    Arg::flag("force", &["-f"], "don't ask before emptying trash directories"),
+    Arg::flag("dry_run", &["--dry-run"], "show which files would have been removed"),
+    Arg::option(
+        "free",
+        &["--free"],
+        "SIZE",
+        "remove the oldest items until SIZE bytes (like 10G, 1.5GiB or 500MB) are available on each volume",
+    ),
+    Arg::flag(
+        "gc_orphans",
+        &["--gc-orphans"],
+        "only remove backups without trashinfo and trashinfo files without backup",
+    ),
+    Arg::option(
+        "keep",
+        &["--keep"],
+        "N",
+        "keep the N most recently trashed items of each trash directory and remove the others",
+    ),
+    Arg::option(
+        "match",
+        &["--match"],
+        "GLOB",
+        "only remove items whose original path matches GLOB (a basename unless it starts with '/'); may be repeated",
+    ),
+    IGNORE_CASE_ARG,
+    SI_ARG,
+    BYTES_ARG,
+    BLOCK_SIZE_ARG,
+    Arg::positional("days", Nargs::Optional, ""),
+];
+
+fn parser() -> Parser {
+    Parser::new("trash-empty", "Purge trashed files.", ARGS)
+        .with_epilog("Report bugs to https://github.com/andreafrancia/trash-cli/issues")
+}
+
+/// Options understood by the native implementation.
+#[derive(Debug, Default)]
+struct EmptyOptions {
+    verbose: u32,
+    trash_dirs: Vec<PathBuf>,
+    all_users: bool,
+    interactive: Option<bool>,
+    dry_run: bool,
+    /// Only items trashed longer ago than this (the `days` argument).
+    max_age: Option<Duration>,
+    free: Option<u64>,
+    keep: Option<usize>,
+    /// `--match`: only items whose original path matches one of them.
+    matches: Vec<GlobPattern>,
+    /// Sizes of the prompt and the notification (`--si`, `-b`,
+    /// `--block-size`).
+    size_format: SizeFormat,
+    /// Only remove the leftovers of broken items, see [`TrashAudit`].
+    gc_orphans: bool,
+    network: NetworkFilter,
+}
+
+#[derive(Debug)]
+enum Invocation {
+    Help,
+    PrintCompletion(Shell),
+    PrintVersion,
+    DebugEnv,
+    Native(EmptyOptions),
+    /// Features not ported yet are still served by the Python backend.
+    Python,
+}
+
+fn parse_args(config: &Config, args: &[OsString]) -> Result<Invocation, UsageError> {
+    let matches = match parser().parse_with_config(config, CommandKind::Empty, args)? {
+        Parsed::Help => return Ok(Invocation::Help),
+        Parsed::Args(matches) => matches,
+    };
+    if let Some(found) = matches.iter().find(|found| found.dest == "print_completion") {
+        let shell = Shell::parse(&found.text()).expect("checked against the choices");
+        return Ok(Invocation::PrintCompletion(shell));
+    }
+    if matches.iter().any(|found| found.dest == "version") {
+        return Ok(Invocation::PrintVersion);
+    }
+    if matches.iter().any(|found| found.dest == "debug_env") {
+        return Ok(Invocation::DebugEnv);
+    }
+    let mut options = EmptyOptions::default();
+    let mut patterns = Vec::new();
+    let mut match_options = MatchOptions::default();
+    for found in matches {
+        let value = found.text();
+        match found.dest {
+            "print_time" => return Ok(Invocation::Python),
+            "verbose" => options.verbose += 1,
+            "trash_dir" => options.trash_dirs.extend(found.value.map(trash_dir_arg)),
+            "all_users" => options.all_users = true,
+            "interactive" => options.interactive = Some(true),
+            "force" => options.interactive = Some(false),
+            "dry_run" => options.dry_run = true,
+            "gc_orphans" => options.gc_orphans = true,
+            "include_network" => options.network.include_all = true,
+            "free" => options.free = Some(parse_free_size(&value)?),
+            "keep" => options.keep = Some(parse_keep(&value)?),
+            "match" => patterns.push(value),
+            "ignore_case" => match_options.casefold = true,
+            "days" => options.max_age = Some(parse_age(&value)?),
+            "si" => options.size_format = SizeFormat::Si,
+            "bytes" => options.size_format = SizeFormat::Bytes,
+            "block_size" => options.size_format = parse_block_size(&value)?,
+            _ => {}
+        }
+    }
+    options.matches = patterns.iter().map(|pattern| GlobPattern::new(pattern, match_options)).collect();
+
+    if options.trash_dirs.is_empty() {
+        options.trash_dirs = config.trash_dirs.clone();
+    }
+    options.network.fstypes = config.include_network.clone();
+    options.max_age = options.max_age.or(config.purge.days.map(Duration::days));
+    options.keep = options.keep.or(config.purge.keep);
+    if let (None, Some(free)) = (options.free, &config.purge.free) {
+        let file = config.path.as_ref().map(|path| path.display().to_string()).unwrap_or_default();
+        let invalid = || UsageError(tr_format("{} [purge]: invalid size value: '{}'", &[&file, &free]));
+        options.free = Some(parse_free_size(free).map_err(|_| invalid())?);
+    }
+    if options.interactive.is_none() {
+        options.interactive = Some(is_input_interactive());
+    }
+    Ok(Invocation::Native(options))
+}
+
+/// Parses a size like `10G` or `1.5GiB`, see [`parse_size`].
+fn parse_free_size(value: &str) -> Result<u64, UsageError> {
+    parse_size(value).ok_or_else(|| UsageError(tr_format("argument --free: invalid size value: '{}'", &[&value])))
+}
+
+/// A number of days, possibly negative as Python accepts, or a human
+/// duration like `36h` or `2w`.
+fn parse_age(value: &str) -> Result<Duration, UsageError> {
+    if let Ok(days) = value.parse::<i64>() {
+        return Ok(Duration::days(days));
+    }
+    parse_human_duration(value)
+        .and_then(|age| Duration::from_std(age).ok())
+        .ok_or_else(|| UsageError(tr_format("argument days: invalid duration value: '{}'", &[&value])))
+}
+
+fn parse_keep(value: &str) -> Result<usize, UsageError> {
+    value
+        .parse::<usize>()
+        .map_err(|_| UsageError(tr_format("argument --keep: invalid int value: '{}'", &[&value])))
+}
+
+fn is_input_interactive() -> bool {
+    io::stdin().is_terminal()
+}
+
+/// Current time, overridable through `TRASH_DATE` like the Python clock.
+fn now(fs: &dyn FileSystem, environ: &dyn Environment) -> DateTime<Utc> {
+    if let Some(value) = environ.trash_date() {
+        match parse_trash_datetime(&value) {
+            Some(date) => return date,
+            None => eprintln!("{}", tr_format("trash-empty: invalid TRASH_DATE: {}", &[&value])),
+        }
+    }
+    DateTime::<Utc>::from(fs.now())
+}
+
+fn ok_to_delete(deletion_date: Option<DateTime<Utc>>, max_age: Option<Duration>, now: DateTime<Utc>) -> bool {
+    match (max_age, deletion_date) {
+        (None, _) => true,
+        (Some(max_age), Some(date)) => date < now - max_age,
+        (Some(_), None) => false,
+    }
+}
+
+/// A trashed item selected for purging; orphans have no trashinfo.
+#[derive(Debug)]
+struct Candidate {
+    backup: PathBuf,
+    trashinfo: Option<PathBuf>,
+    /// Where the item was trashed from, when its trashinfo tells.
+    original_path: Option<PathBuf>,
+    deleted_at: Option<DateTime<Utc>>,
+}
+
+impl Candidate {
+    /// Paths in deletion order: the backup copy first, then its trashinfo.
+    fn paths(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.backup).chain(self.trashinfo.iter())
+    }
+}
+
+/// Items to purge in `trash_dir`: those selected by `max_age` and `--match`
+/// (and not among the `keep` most recently trashed), followed by orphaned
+/// backup copies without any trashinfo unless `--match` is given.
+fn candidates(
+    fs: &dyn FileSystem,
+    trash_dir: &TrashDirectory,
+    options: &EmptyOptions,
+    now: DateTime<Utc>,
+    diagnostics: &Diagnostics,
+) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = items(fs, trash_dir, diagnostics).collect();
+    if let Some(keep) = options.keep {
+        retain_all_but_newest(&mut candidates, keep);
+    }
+    candidates.retain(|candidate| is_selected(candidate, options, now));
+    candidates
+}
+
+/// Whether `candidate` passes both `--match` and the age filter.
+fn is_selected(candidate: &Candidate, options: &EmptyOptions, now: DateTime<Utc>) -> bool {
+    matches_any(candidate, &options.matches) && is_old_enough(candidate, options.max_age, now)
+}
+
+/// Whether the original path of `candidate` matches one of `patterns`, if
+/// any; orphans have none and never match.
+fn matches_any(candidate: &Candidate, patterns: &[GlobPattern]) -> bool {
+    if patterns.is_empty() {
+        return true;
+    }
+    let Some(original_path) = &candidate.original_path else {
+        log::debug("empty", format_args!("keeping {}: no original path", candidate.backup.display()));
+        return false;
+    };
+    let matched = patterns.iter().any(|pattern| pattern.matches_original_path(original_path));
+    if !matched {
+        log::trace("empty", format_args!("keeping {}: matches no pattern", original_path.display()));
+    }
+    matched
+}
+
+/// Whether `candidate` was trashed more than `max_age` ago; orphans always
+/// are.
+fn is_old_enough(candidate: &Candidate, max_age: Option<Duration>, now: DateTime<Utc>) -> bool {
+    if candidate.trashinfo.is_none() || ok_to_delete(candidate.deleted_at, max_age, now) {
+        return true;
+    }
+    match candidate.deleted_at {
+        Some(date) => log::debug(
+            "empty",
+            format_args!(
+                "keeping {}: trashed on {}",
+                candidate.backup.display(),
+                format_datetime(date, DisplayZone::Local)
+            ),
+        ),
+        None => log::debug("empty", format_args!("keeping {}: no deletion date", candidate.backup.display())),
+    }
+    false
+}
+
+/// Drops the `keep` most recently trashed items from `candidates`; items
+/// without a parseable date count as oldest, orphans are never kept.
+fn retain_all_but_newest(candidates: &mut Vec<Candidate>, keep: usize) {
+    let mut dated: Vec<(Option<DateTime<Utc>>, PathBuf)> = candidates
+        .iter()
+        .filter(|candidate| candidate.trashinfo.is_some())
+        .map(|candidate| (candidate.deleted_at, candidate.backup.clone()))
+        .collect();
+    dated.sort_by_key(|(deleted_at, _)| std::cmp::Reverse(*deleted_at));
+    let kept: Vec<PathBuf> = dated.into_iter().take(keep).map(|(_, backup)| backup).collect();
+    for backup in &kept {
+        log::debug("empty", format_args!("keeping {}: one of the {} newest", backup.display(), keep));
+    }
+    candidates.retain(|candidate| !kept.contains(&candidate.backup));
+}
+
+/// Every item of `trash_dir`: trashinfo-backed items, then orphans.
+///
+/// Entries are streamed; `files/` is only read once the trashinfo-backed
+/// items have been consumed, so purging them while iterating is safe.
+fn items<'a>(
+    fs: &'a dyn FileSystem,
+    trash_dir: &'a TrashDirectory,
+    diagnostics: &'a Diagnostics,
+) -> impl Iterator<Item = Candidate> + 'a {
+    let volume = trash_dir.mount_point.clone().unwrap_or_else(|| PathBuf::from("/"));
+    let trashinfos = diagnostics.read_dir(fs, &trash_dir.info_dir).filter_map(move |trashinfo| {
+        let basename = entry_name(&trashinfo)?;
+        let content = fs.read_to_string(&trashinfo).unwrap_or_default();
+        Some(Candidate {
+            backup: trash_dir.files_dir.join(basename),
+            original_path: TrashInfo::parse(&content)
+                .ok()
+                .map(|info| resolve_original_location(&volume, &info.path)),
+            deleted_at: parse_deletion_date(&content),
+            trashinfo: Some(trashinfo),
+        })
+    });
+    let orphans = std::iter::once(()).flat_map(move |()| {
+        diagnostics.read_dir(fs, &trash_dir.files_dir).filter_map(move |backup| {
+            let mut trashinfo_name = backup.file_name()?.to_os_string();
+            trashinfo_name.push(TRASHINFO_EXTENSION);
+            if fs.exists(&trash_dir.info_dir.join(trashinfo_name)) {
+                return None;
+            }
+            Some(Candidate {
+                backup,
+                trashinfo: None,
+                original_path: None,
+                deleted_at: None,
+            })
+        })
+    });
+    trashinfos.chain(orphans)
+}
+
+/// Asks whether `trash_dir` should be emptied; only a reply starting with
+/// `y`/`Y` proceeds.
+fn confirm_empty(
+    fs: &dyn FileSystem,
+    trash_dir: &TrashDirectory,
+    candidates: &[Candidate],
+    size_format: SizeFormat,
+) -> bool {
+    let total = candidates
+        .iter()
+        .filter_map(|candidate| recursive_size(fs, &candidate.backup).ok())
+        .sum::<u64>();
+    print!(
+        "{}",
+        tr_format(
+            "Would empty trash dir {} containing {} items totalling {}, proceed? [y/N] ",
+            &[&trash_dir.path.display(), &candidates.len(), &format_size(total, size_format)],
+        )
+    );
+    let _ = io::stdout().flush();
+
+    let mut reply = String::new();
+    if io::stdin().lock().read_line(&mut reply).is_err() {
+        return false;
+    }
+    reply.trim_start().chars().next().is_some_and(|ch| ch.eq_ignore_ascii_case(&'y'))
+}
+
+/// Error reporting that explains permission failures when an unprivileged
+/// user runs `--all-users`.
+struct Diagnostics {
+    all_users: bool,
+    privileged: bool,
+}
+
+impl Diagnostics {
+    fn new(all_users: bool, uid: u32) -> Self {
+        let diagnostics = Diagnostics {
+            all_users,
+            privileged: uid == 0,
+        };
+        if diagnostics.needs_hint() {
+            eprintln!(
+                "{}",
+                tr("trash-empty: warning: --all-users without root privileges; \
+                    other users' trash dirs will probably not be emptied")
+            );
+        }
+        diagnostics
+    }
+
+    fn needs_hint(&self) -> bool {
+        self.all_users && !self.privileged
+    }
+
+    fn hint(&self, kind: io::ErrorKind) -> &'static str {
+        if kind == io::ErrorKind::PermissionDenied && self.needs_hint() {
+            tr(" (permission denied; run as root to empty other users' trash)")
+        } else {
+            ""
+        }
+    }
+
+    /// Streams the entries of `dir`, treating a missing directory as empty
+    /// but reporting unreadable ones.
+    fn read_dir(&self, fs: &dyn FileSystem, dir: &Path) -> impl Iterator<Item = PathBuf> {
+        let entries = match fs.read_dir_iter(dir) {
+            Ok(entries) => Some(entries),
+            Err(CoreError::Io(_, err)) if err.kind() == io::ErrorKind::PermissionDenied => {
+                let shown = printable_path(dir, &io::stderr());
+                let hint = self.hint(err.kind());
+                eprintln!("{}", tr_format("trash-empty: cannot read {}{}", &[&shown.display(), &hint]));
+                None
+            }
+            Err(_) => None,
+        };
+        entries.into_iter().flatten().filter_map(|entry| entry.ok()).map(|entry| entry.path)
+    }
+
+    fn cannot_remove(&self, path: &Path, err: &io::Error) {
+        let shown = printable_path(path, &io::stderr());
+        eprintln!("{}", tr_format("trash-empty: cannot remove {}{}", &[&shown.display(), &self.hint(err.kind())]));
+    }
+}
+
+/// Removes `path`, a file or a whole tree, never following symlinks out of
+/// the trash dir.
+fn remove_file_if_exists(fs: &dyn FileSystem, path: &Path) -> io::Result<()> {
+    match fs.remove_dir_all_no_follow(path) {
+        Err(CoreError::Io(_, err)) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result.map_err(io::Error::from),
+    }
+}
+
+fn run_native(options: EmptyOptions) -> i32 {
+    log::init("trash-empty", options.verbose);
+    let fs = RealFileSystem;
+    let environ = RealEnvironment;
+    let uid = environ.uid();
+    let now = now(&fs, &environ);
+
+    let diagnostics = Diagnostics::new(options.all_users, uid);
+
+    let events = if options.all_users {
+        scan_all_users_trash_dirs(&fs, &SystemUsers, &list_volumes(&environ, &options.network))
+    } else if options.trash_dirs.is_empty() {
+        scan_trash_dirs(&fs, &environ, uid, &list_volumes(&environ, &options.network))
+    } else {
+        user_specified_trash_dirs(&options.trash_dirs, &list_mount_points())
+    };
+
+    let interactive = options.interactive.unwrap_or(false);
+    // A progress bar would be garbled by the listing of each removed path.
+    let progress = match options.dry_run || options.verbose > 0 {
+        true => Box::new(NoProgress),
+        false => stderr_progress("trash-empty", "freed"),
+    };
+    // Prompts would not notice an interrupt, so -i keeps the default action.
+    let cancel = match interactive {
+        true => CancellationToken::new(),
+        false => CancellationToken::on_signals(),
+    };
+    let mut purger = Purger {
+        fs: &fs,
+        options: &options,
+        diagnostics: &diagnostics,
+        progress,
+        journal: Journal::open(&environ),
+        notify: !options.dry_run && notify::is_enabled(&environ),
+        cancel: cancel.clone(),
+        removed: 0,
+        freed: 0,
+    };
+    if options.gc_orphans {
+        for trash_dir in events.iter().filter_map(|event| event.found()) {
+            purger.collect_orphans(trash_dir);
+        }
+        return 0;
+    }
+    if !interactive && options.keep.is_none() && options.free.is_none() {
+        // Nothing needs the whole listing up front: purge while streaming.
+        purger.progress.start(None, None);
+        for trash_dir in events.iter().filter_map(|event| event.found()) {
+            log::info("empty", format_args!("emptying {}", trash_dir.path.display()));
+            let selected =
+                items(&fs, trash_dir, &diagnostics).filter(|candidate| is_selected(candidate, &options, now));
+            for candidate in selected.take_while(|_| !cancel.is_cancelled()) {
+                purger.purge(&candidate);
+            }
+            purger.finish(trash_dir);
+        }
+        purger.progress.finish();
+        purger.notify_emptied(&environ);
+        return purger.exit_code();
+    }
+
+    let mut selected: Vec<(&TrashDirectory, Vec<Candidate>)> = Vec::new();
+    for trash_dir in events.iter().filter_map(|event| event.found()) {
+        log::info("empty", format_args!("emptying {}", trash_dir.path.display()));
+        let candidates = candidates(&fs, trash_dir, &options, now, &diagnostics);
+        if candidates.is_empty() {
+            continue;
+        }
+        if interactive && !confirm_empty(&fs, trash_dir, &candidates, options.size_format) {
+            continue;
+        }
+        selected.push((trash_dir, candidates));
+    }
+
+    match options.free {
+        Some(target) => {
+            purger.progress.start(None, None);
+            purger.purge_until_free(selected, target);
+        }
+        None => {
+            let total = selected.iter().map(|(_, candidates)| candidates.len() as u64).sum();
+            purger.progress.start(Some(total), None);
+            for (trash_dir, candidates) in &selected {
+                for candidate in candidates.iter().take_while(|_| !cancel.is_cancelled()) {
+                    purger.purge(candidate);
+                }
+                purger.finish(trash_dir);
+            }
+        }
+    }
+    purger.progress.finish();
+    purger.notify_emptied(&environ);
+    purger.exit_code()
+}
+
+struct Purger<'a> {
+    fs: &'a dyn FileSystem,
+    options: &'a EmptyOptions,
+    diagnostics: &'a Diagnostics,
+    progress: Box<dyn Progress>,
+    journal: Journal,
+    /// Measure what each item frees, for the desktop notification.
+    notify: bool,
+    /// Stops the purge between items.
+    cancel: CancellationToken,
+    removed: u64,
+    freed: u64,
+}
+
+impl Purger<'_> {
+    fn purge(&mut self, candidate: &Candidate) {
+        let size = match self.progress.is_visible() || self.notify {
+            true => recursive_size(self.fs, &candidate.backup).unwrap_or(0),
+            false => 0,
+        };
+        let trashinfo = match (&candidate.trashinfo, self.options.dry_run) {
+            (Some(trashinfo), false) => self.fs.read_to_string(trashinfo).unwrap_or_default(),
+            _ => String::new(),
+        };
+        let mut removed = !self.options.dry_run;
+        for path in candidate.paths() {
+            if self.options.dry_run {
+                println!("would remove {}", printable_path(path, &io::stdout()).display());
+                continue;
+            }
+            if self.options.verbose > 0 {
+                println!("removing {}", printable_path(path, &io::stdout()).display());
+            }
+            if let Err(err) = remove_file_if_exists(self.fs, path) {
+                self.diagnostics.cannot_remove(path, &err);
+                removed = false;
+            }
+        }
+        if removed {
+            self.removed += 1;
+            self.freed += size;
+            let files_dir = candidate.backup.parent().unwrap_or(Path::new("/"));
+            let trash_dir = files_dir.parent().unwrap_or(files_dir);
+            self.journal.record(
+                self.fs,
+                &Record::Item(JournalItem {
+                    operation: Operation::Empty,
+                    trash_dir: std::path::absolute(trash_dir).unwrap_or_else(|_| trash_dir.to_path_buf()),
+                    name: candidate.backup.file_name().unwrap_or_default().to_os_string(),
+                    path: PathBuf::new(),
+                    trashinfo,
+                }),
+            );
+        }
+        self.progress.advance(1, size);
+    }
+
+    /// Removes the halves of broken items in `trash_dir`: backups without
+    /// trashinfo and trashinfo files whose backup is gone. Malformed
+    /// trashinfo files are left alone, their backup may still be restored.
+    fn collect_orphans(&mut self, trash_dir: &TrashDirectory) {
+        log::info("empty", format_args!("collecting orphans in {}", trash_dir.path.display()));
+        let audit = match TrashAudit::run(self.fs, &trash_dir.path) {
+            Ok(audit) => audit,
+            Err(err) => {
+                eprintln!("trash-empty: {}", err);
+                return;
+            }
+        };
+        for issue in &audit.issues {
+            let path = match issue {
+                AuditIssue::OrphanBackup { backup } => backup,
+                AuditIssue::MissingBackup { trashinfo, .. } => trashinfo,
+                AuditIssue::Malformed { .. } => continue,
+            };
+            if self.options.dry_run {
+                println!("would remove {}", printable_path(path, &io::stdout()).display());
+                continue;
+            }
+            if self.options.verbose > 0 {
+                println!("removing {}", printable_path(path, &io::stdout()).display());
+            }
+            if let Err(err) = remove_file_if_exists(self.fs, path) {
+                self.diagnostics.cannot_remove(path, &err);
+            }
+        }
+        self.finish(trash_dir);
+    }
+
+    /// 0, or [`exit_codes::INTERRUPTED`] once reported when the purge was
+    /// cancelled.
+    fn exit_code(&self) -> i32 {
+        if !self.cancel.is_cancelled() {
+            return 0;
+        }
+        eprintln!("{}", tr("trash-empty: interrupted"));
+        exit_codes::INTERRUPTED.into()
+    }
+
+    /// Tells the desktop how much was freed, when anything was removed.
+    fn notify_emptied(&self, environ: &dyn Environment) {
+        if self.notify && self.removed > 0 {
+            let freed = format_size(self.freed, self.options.size_format);
+            notify(environ, &tr_format("Trash emptied: {} freed", &[&freed]));
+        }
+    }
+
+    fn finish(&self, trash_dir: &TrashDirectory) {
+        if !self.options.dry_run {
+            prune_directory_sizes(self.fs, &trash_dir.path);
+            TrashIndex::update(self.fs, &trash_dir.path, |index| index.retain_existing(self.fs, &trash_dir.path));
+        }
+    }
+
+    /// Purges candidates oldest first (orphans, which have no date, go
+    /// first) until every affected volume has `target` bytes available.
+    ///
+    /// Free space is re-queried after each removal; in dry-run mode it is
+    /// estimated from the sizes of the items that would be removed.
+    fn purge_until_free(&mut self, selected: Vec<(&TrashDirectory, Vec<Candidate>)>, target: u64) {
+        let mut queue: Vec<(&TrashDirectory, Candidate)> = selected
+            .into_iter()
+            .flat_map(|(trash_dir, candidates)| candidates.into_iter().map(move |candidate| (trash_dir, candidate)))
+            .collect();
+        queue.sort_by_key(|(_, candidate)| candidate.deleted_at);
+
+        let mut estimated_freed: HashMap<PathBuf, u64> = HashMap::new();
+        let mut touched: Vec<&TrashDirectory> = Vec::new();
+        for (trash_dir, candidate) in queue {
+            if self.cancel.is_cancelled() {
+                break;
+            }
+            let available = match self.fs.available_space(&trash_dir.path) {
+                Ok(available) => available,
+                Err(err) => {
+                    eprintln!("trash-empty: {}", err);
+                    continue;
+                }
+            };
+            let freed = estimated_freed.get(&trash_dir.path).copied().unwrap_or(0);
+            if available.saturating_add(freed) >= target {
+                continue;
+            }
+            if self.options.dry_run {
+                let size = recursive_size(self.fs, &candidate.backup).unwrap_or(0);
+                *estimated_freed.entry(trash_dir.path.clone()).or_insert(0) += size;
+            }
+            self.purge(&candidate);
+            if !touched.iter().any(|dir| dir.path == trash_dir.path) {
+                touched.push(trash_dir);
+            }
+        }
+        for trash_dir in touched {
+            self.finish(trash_dir);
+        }
+    }
+}
+
+/// Drops `directorysizes` entries for directories that were just purged.
+fn prune_directory_sizes(fs: &dyn FileSystem, trash_dir: &Path) {
+    if let Ok(mut sizes) = DirectorySizes::load(fs, trash_dir) {
+        let before = sizes.len();
+        sizes.retain_existing(fs, trash_dir);
+        if sizes.len() != before {
+            let _ = sizes.save(fs, trash_dir);
+        }
+    }
+}
+
+fn exit_with_python_backend() -> ! {
     match run_python_backend() {
         Ok(status) => {
             if let Some(code) = status.code() {
                 std::process::exit(code);
             }
             // Python exited by signal; propagate a generic failure code.
-            std::process::exit(1);
+            std::process::exit(exit_codes::FAILURE.into());
         }
         Err(err) => {
-            eprintln!("trash-empty: failed to invoke Python backend: {}", err);
-            std::process::exit(1);
+            eprintln!("{}", tr_format("trash-empty: failed to invoke Python backend: {}", &[&err]));
+            std::process::exit(exit_codes::FAILURE.into());
         }
     }
 }
+
+fn main() {
+    let args: Vec<OsString> = env::args_os().skip(1).collect();
+    let config = load_config("trash-empty");
+    match parse_args(&config, &args) {
+        Ok(Invocation::Help) => println!("{}", parser().format_help()),
+        Ok(Invocation::PrintCompletion(shell)) => print!("{}", parser().format_completion(shell)),
+        Ok(Invocation::PrintVersion) => println!("{}", version_line("trash-empty")),
+        Ok(Invocation::DebugEnv) => println!("{}", describe_environment(&RealEnvironment, CommandKind::Empty)),
+        Ok(Invocation::Native(options)) => std::process::exit(run_native(options)),
+        Ok(Invocation::Python) => exit_with_python_backend(),
+        Err(error) => parser().exit_with_error(&error.0),
+    }
+}
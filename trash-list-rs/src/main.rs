@@ -1,26 +1,42 @@
-use chrono::NaiveDateTime;
-use std::collections::{HashMap, HashSet};
+use chrono::{DateTime, Utc};
+use std::cell::OnceCell;
 use std::env;
-use std::fmt::{self, Display, Formatter};
+use std::ffi::{OsStr, OsString};
 use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::os::unix::ffi::OsStrExt;
 use std::path::{Path, PathBuf};
-use std::process::Command;
-use urlencoding::decode as url_decode;
+use trash_cli_core::cli::{
+    load_config, parse_block_size, trash_dir_arg, Arg, Complete, Parsed, Parser, Shell, UsageError, BLOCK_SIZE_ARG,
+    BYTES_ARG, COLOR_ARG, DEBUG_ENV_ARG, IGNORE_CASE_ARG, PRINT_COMPLETION_ARG, SI_ARG, USAGE_EXIT_CODE, VERSION_ARG,
+};
+use trash_cli_core::log;
+use trash_cli_core::output::describe_fields;
+use trash_cli_core::trash_dirs::{scan_all_users_trash_dirs, user_specified_trash_dirs};
+use trash_cli_core::{
+    describe_environment, entry_name, format_datetime, format_row, format_size, list_mount_entries, list_mount_points,
+    list_volumes, parse_fields, recursive_size, resolve_original_location, scan_trash_dirs, sort_items_by,
+    storage_volumes, terminal_path, version_line, AuditIssue, ColorChoice, CommandKind, Config, CoreError,
+    DirectorySizes, DisplayZone, Environment, Field, FileSystem, GlobPattern, MatchOptions, MountEntry, NetworkFilter,
+    Painter, RealEnvironment, RealFileSystem, Role, SizeFormat, SortKey, SystemUsers, Theme, TrashAudit, TrashDirEvent,
+    TrashDirectory, TrashIndex, TrashInfoKeys, TrashStats, TrashedItem,
+};
 
-#[cfg(unix)]
-use std::os::unix::fs::MetadataExt;
-
-const VERSION: &str = "0.24.5.26";
 const UNKNOWN_DELETION_DATE: &str = "????-??-?? ??:??:??";
-const BUG_REPORT_URL: &str = "https://github.com/andreafrancia/trash-cli/issues";
+const EPILOG: &str = "Report bugs to https://github.com/andreafrancia/trash-cli/issues";
 
 #[derive(Debug, Clone, Copy)]
 enum Action {
+    PrintCompletion(Shell),
     PrintVersion,
     DebugVolumes,
+    DebugEnv,
     ListVolumes,
     ListTrashDirs,
     ListTrash,
+    Stats,
+    Check,
+    ListFields,
     PrintPythonExecutable,
 }
 
@@ -36,420 +52,257 @@ struct ListConfig {
     attribute_to_print: Attribute,
     show_files: bool,
     all_users: bool,
-    trash_dirs: Vec<String>,
-}
-
-#[derive(Debug)]
-enum Event {
-    Found(TrashDir),
-    SkipNotSticky(PathBuf),
-    SkipSymlink(PathBuf),
-}
-
-#[derive(Debug)]
-struct TrashDir {
-    path: PathBuf,
-    volume: String,
-}
-
-#[derive(Debug)]
-enum ScanEvent {
-    Found(TrashDir),
-    SkippedNotSticky(PathBuf),
-    SkippedSymlink(PathBuf),
-    // Mirrors Python implementation behavior, kept for completeness.
-    SkippedMissing,
-}
-
-#[derive(Debug)]
-struct CliError(String);
-
-impl Display for CliError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
-    }
-}
-
-fn print_help(prog: &str) {
-    println!(
-        "\
-usage: {prog} [-h] [--print-completion {{bash,zsh,tcsh}}] [--version] \
-[--volumes] [--trash-dirs] [--trash-dir TRASH_DIRS] [--all-users]
-
-List trashed files
-
-options:
-  -h, --help            show this help message and exit
-  --print-completion {{bash,zsh,tcsh}}
-                        print shell completion script
-  --version             show program's version number and exit
-  --volumes             list volumes
-  --trash-dirs          list trash dirs
-  --trash-dir TRASH_DIRS
-                        specify the trash directory to use
-  --all-users           list trashcans of all the users
-
-Report bugs to {url}
-",
-        prog = prog,
-        url = BUG_REPORT_URL
-    );
-}
-
-fn parse_args(args: &[String]) -> Result<ListConfig, CliError> {
+    trash_dirs: Vec<PathBuf>,
+    verbose: u32,
+    /// Read the trashinfo files through the index of each trash dir.
+    use_index: bool,
+    network: NetworkFilter,
+    /// Fields of `--output`, replacing the date (or size) and path.
+    output: Option<Vec<Field>>,
+    /// Time zone of the deletion dates printed (`--utc`, `--local`).
+    zone: DisplayZone,
+    /// Sizes of `--stats` (`--si`, `-b`, `--block-size`).
+    size_format: SizeFormat,
+    /// Keep the home directory in full instead of writing it `~`.
+    no_abbrev: bool,
+    /// `--volumes` also lists pseudo and read-only filesystems.
+    all_volumes: bool,
+    /// `--match`: only items whose original path matches one of them.
+    matches: Vec<GlobPattern>,
+    /// `--sort`, or `none` for a bare `--reverse`: items are then collected
+    /// from every trash dir before any is printed.
+    sort: Option<SortKey>,
+    reverse: bool,
+    /// `--skip`: items left out before the first one printed.
+    skip: usize,
+    /// `-n`/`--limit`: most items printed.
+    limit: Option<usize>,
+    /// `--color`, or `color` in the config file.
+    color: ColorChoice,
+    theme: Theme,
+}
+
+const ARGS: &[Arg] = &[
+    PRINT_COMPLETION_ARG,
+    VERSION_ARG,
+    Arg::flag("debug_volumes", &["--debug-volumes"], "").hidden(),
+    DEBUG_ENV_ARG,
+    Arg::flag("volumes", &["--volumes"], "list volumes, without pseudo and read-only filesystems"),
+    Arg::flag("all_volumes", &["--all-volumes"], "list every volume, /proc and read-only images included"),
+    Arg::flag("trash_dirs", &["--trash-dirs"], "list trash dirs"),
+    Arg::option("trash_dir", &["--trash-dir"], "TRASH_DIRS", "specify the trash directory to use")
+        .complete(Complete::Directory),
+    Arg::flag("size", &["--size"], "").hidden(),
+    Arg::flag("files", &["--files"], "").hidden(),
+    Arg::flag(
+        "show_trash_dir",
+        &["--show-trash-dir"],
+        "also print the trash directory holding each item (the trashdir field of --output)",
+    ),
+    Arg::option(
+        "output",
+        &["--output"],
+        "FIELDS",
+        "print these comma-separated fields (date, size, path, trashdir, file) in this order; 'help' describes them",
+    ),
+    Arg::option(
+        "match",
+        &["--match"],
+        "GLOB",
+        "only list items whose original path matches GLOB (a basename unless it starts with '/'); may be repeated",
+    ),
+    IGNORE_CASE_ARG,
+    COLOR_ARG,
+    Arg::option(
+        "sort",
+        &["--sort"],
+        "KEY",
+        "print items sorted by date, path or size (smallest first), or as found with none",
+    )
+    .choices(&SortKey::NAMES),
+    Arg::flag("reverse", &["-r", "--reverse"], "reverse the order of --sort, e.g. largest first"),
+    Arg::option("skip", &["--skip"], "N", "leave out the first N items"),
+    Arg::option(
+        "limit",
+        &["-n", "--limit"],
+        "N",
+        "print at most N items, after --skip; with --sort size -r, the N largest",
+    ),
+    Arg::flag("utc", &["--utc"], "print deletion dates in UTC"),
+    Arg::flag("local", &["--local"], "print deletion dates in local time (the default)"),
+    SI_ARG,
+    BYTES_ARG,
+    BLOCK_SIZE_ARG,
+    Arg::flag(
+        "no_abbrev",
+        &["--no-abbrev"],
+        "print paths below the home directory in full, not as ~/..., even on a terminal",
+    ),
+    Arg::flag("all_users", &["--all-users"], "list trashcans of all the users"),
+    Arg::flag(
+        "include_network",
+        &["--include-network"],
+        "also scan network filesystems (NFS, CIFS, sshfs, ...) for trash dirs",
+    ),
+    Arg::flag(
+        "verbose",
+        &["-v", "--verbose"],
+        "report the trash dirs scanned and the entries skipped; repeat for more detail",
+    ),
+    Arg::flag("python", &["--python"], "").hidden(),
+    Arg::flag(
+        "stats",
+        &["--stats"],
+        "print item count, total size and deletion date range per trash dir and overall",
+    ),
+    Arg::flag(
+        "check",
+        &["--check"],
+        "report backups without trashinfo, trashinfo without backup and malformed trashinfo files",
+    ),
+];
+
+fn parser(prog: &str) -> Parser {
+    Parser::new(prog, "List trashed files", ARGS).with_epilog(EPILOG)
+}
+
+/// `None` when `--help` was given.
+fn parse_args(prog: &str, user_config: &Config, args: &[OsString]) -> Result<Option<ListConfig>, UsageError> {
+    let matches = match parser(prog).parse_with_config(user_config, CommandKind::List, args)? {
+        Parsed::Help => return Ok(None),
+        Parsed::Args(matches) => matches,
+    };
     let mut config = ListConfig {
         action: Action::ListTrash,
         attribute_to_print: Attribute::DeletionDate,
         show_files: false,
         all_users: false,
         trash_dirs: Vec::new(),
+        verbose: 0,
+        use_index: user_config.index,
+        network: NetworkFilter {
+            include_all: false,
+            fstypes: user_config.include_network.clone(),
+        },
+        output: None,
+        zone: DisplayZone::Local,
+        size_format: SizeFormat::Binary,
+        no_abbrev: false,
+        all_volumes: false,
+        matches: Vec::new(),
+        sort: None,
+        reverse: false,
+        skip: 0,
+        limit: None,
+        color: user_config.color,
+        theme: user_config.theme.clone(),
     };
-
-    let mut i = 0;
-    while i < args.len() {
-        let arg = &args[i];
-        match arg.as_str() {
-            "--help" | "-h" => {
-                return Err(CliError("help".to_string()));
+    let mut show_trash_dir = false;
+    let mut patterns = Vec::new();
+    let mut match_options = MatchOptions::default();
+    for found in matches {
+        match found.dest {
+            "print_completion" => {
+                let shell = Shell::parse(&found.text()).expect("checked against the choices");
+                config.action = Action::PrintCompletion(shell);
             }
-            "--version" => config.action = Action::PrintVersion,
-            "--debug-volumes" => config.action = Action::DebugVolumes,
-            "--volumes" => config.action = Action::ListVolumes,
-            "--trash-dirs" => config.action = Action::ListTrashDirs,
-            "--size" => config.attribute_to_print = Attribute::Size,
-            "--files" => config.show_files = true,
-            "--all-users" => config.all_users = true,
-            "--python" => config.action = Action::PrintPythonExecutable,
-            "--print-completion" => {
-                let shell = if i + 1 < args.len() {
-                    i += 1;
-                    args[i].as_str()
-                } else {
-                    return Err(CliError("missing completion shell".to_string()));
-                };
-                if shell == "bash" || shell == "zsh" || shell == "tcsh" {
-                    println!("Please install shtab firstly!");
-                    return Err(CliError("complete".to_string()));
-                }
-                return Err(CliError(format!("unsupported shell for completion: {}", shell)));
-            }
-            _ if arg.starts_with("--print-completion=") => {
-                let shell = &arg["--print-completion=".len()..];
-                if shell == "bash" || shell == "zsh" || shell == "tcsh" {
-                    println!("Please install shtab firstly!");
-                    return Err(CliError("complete".to_string()));
-                }
-                return Err(CliError(format!("unsupported shell for completion: {}", shell)));
-            }
-            _ if arg == "--trash-dir" => {
-                if i + 1 >= args.len() {
-                    return Err(CliError("missing value for --trash-dir".to_string()));
-                }
-                config.trash_dirs.push(args[i + 1].clone());
-                i += 1;
-            }
-            _ if arg.starts_with("--trash-dir=") => {
-                config.trash_dirs.push(arg["--trash-dir=".len()..].to_string());
+            "version" => config.action = Action::PrintVersion,
+            "debug_volumes" => config.action = Action::DebugVolumes,
+            "debug_env" => config.action = Action::DebugEnv,
+            "volumes" => config.action = Action::ListVolumes,
+            "all_volumes" => {
+                config.action = Action::ListVolumes;
+                config.all_volumes = true;
             }
-            _ if arg.starts_with('-') => {
-                return Err(CliError(format!("unrecognized arguments: {}", arg)));
-            }
-            _ => {
-                return Err(CliError(format!("unrecognized arguments: {}", arg)));
-            }
-        }
-        i += 1;
-    }
-
-    Ok(config)
-}
-
-fn list_mount_points() -> Vec<String> {
-    let content = match fs::read_to_string("/proc/mounts") {
-        Ok(content) => content,
-        Err(_) => return Vec::new(),
-    };
-
-    let mut points = Vec::new();
-    for line in content.lines() {
-        let mut fields = line.split_whitespace();
-        let _device = fields.next();
-        let mountpoint = match fields.next() {
-            Some(mp) => mp,
-            None => continue,
-        };
-        let mountpoint = unescape_mountpoint(mountpoint);
-        if !points.contains(&mountpoint) {
-            points.push(mountpoint);
-        }
-    }
-    points
-}
-
-fn unescape_mountpoint(value: &str) -> String {
-    let mut decoded = String::new();
-    let mut chars = value.chars().peekable();
-    while let Some(ch) = chars.next() {
-        if ch == '\\' && chars.peek().is_some() {
-            let d1 = chars.next().unwrap();
-            let d2 = chars.next().unwrap_or('0');
-            let d3 = chars.next().unwrap_or('0');
-            if let (Some(o1), Some(o2), Some(o3)) = (to_octal_digit(d1), to_octal_digit(d2), to_octal_digit(d3)) {
-                let byte = (o1 << 6) | (o2 << 3) | o3;
-                decoded.push(byte as char);
-            } else {
-                decoded.push('\\');
-                decoded.push(d1);
-                if d2 != '0' {
-                    decoded.push(d2);
-                }
-                if d3 != '0' {
-                    decoded.push(d3);
-                }
+            "trash_dirs" => config.action = Action::ListTrashDirs,
+            "stats" => config.action = Action::Stats,
+            "check" => config.action = Action::Check,
+            "python" => config.action = Action::PrintPythonExecutable,
+            "size" => config.attribute_to_print = Attribute::Size,
+            "files" => config.show_files = true,
+            "show_trash_dir" => show_trash_dir = true,
+            "output" if found.text() == "help" => config.action = Action::ListFields,
+            "output" => {
+                let fields = parse_fields(&found.text());
+                config.output = Some(fields.map_err(|err| UsageError(format!("argument --output: {}", err)))?);
             }
-        } else {
-            decoded.push(ch);
-        }
-    }
-    decoded
-}
-
-fn to_octal_digit(ch: char) -> Option<u8> {
-    match ch {
-        '0'..='7' => Some(ch as u8 - b'0'),
-        _ => None,
-    }
-}
-
-fn list_volumes(environ: &HashMap<String, String>) -> Vec<String> {
-    if let Some(volumes) = environ.get("TRASH_VOLUMES") {
-        if !volumes.is_empty() {
-            return volumes
-                .split(':')
-                .filter(|v| !v.is_empty())
-                .map(str::to_string)
-                .collect();
+            "match" => patterns.push(found.text()),
+            "ignore_case" => match_options.casefold = true,
+            "sort" => config.sort = SortKey::parse(&found.text()),
+            "reverse" => config.reverse = true,
+            // The parser only accepts the listed choices.
+            "color" => config.color = ColorChoice::parse(&found.text()).unwrap_or_default(),
+            "skip" => config.skip = parse_count("--skip", &found.text())?,
+            "limit" => config.limit = Some(parse_count("--limit", &found.text())?),
+            "utc" => config.zone = DisplayZone::Utc,
+            "local" => config.zone = DisplayZone::Local,
+            "si" => config.size_format = SizeFormat::Si,
+            "bytes" => config.size_format = SizeFormat::Bytes,
+            "no_abbrev" => config.no_abbrev = true,
+            "block_size" => config.size_format = parse_block_size(&found.text())?,
+            "all_users" => config.all_users = true,
+            "include_network" => config.network.include_all = true,
+            "trash_dir" => config.trash_dirs.extend(found.value.map(trash_dir_arg)),
+            "verbose" => config.verbose += 1,
+            _ => {}
         }
     }
-    list_mount_points()
-}
-
-fn list_trash_volumes(environ: &HashMap<String, String>) {
-    for volume in list_volumes(environ) {
-        println!("{}", volume);
+    config.matches = patterns.iter().map(|pattern| GlobPattern::new(pattern, match_options)).collect();
+    if config.reverse && config.sort.is_none() {
+        config.sort = Some(SortKey::None);
     }
-}
-
-fn is_sticky_dir(path: &Path) -> bool {
-    let metadata = match fs::metadata(path) {
-        Ok(metadata) => metadata,
-        Err(_) => return false,
-    };
-    if !metadata.is_dir() {
-        return false;
-    }
-    #[cfg(unix)]
-    {
-        (metadata.mode() & 0o1000) == 0o1000
-    }
-    #[cfg(not(unix))]
-    {
-        false
-    }
-}
-
-fn is_symlink(path: &Path) -> bool {
-    match fs::symlink_metadata(path) {
-        Ok(metadata) => metadata.file_type().is_symlink(),
-        Err(_) => false,
-    }
-}
-
-fn is_dir(path: &Path) -> bool {
-    match fs::metadata(path) {
-        Ok(metadata) => metadata.is_dir(),
-        Err(_) => false,
-    }
-}
-
-fn volume_of(path: &Path, mount_points: &HashSet<String>) -> String {
-    let mut current = if path.is_absolute() {
-        path.to_path_buf()
-    } else {
-        match env::current_dir() {
-            Ok(cwd) => cwd.join(path),
-            Err(_) => PathBuf::from(path),
-        }
-    };
-
-    loop {
-        let current_s = current.to_string_lossy().to_string();
-        if mount_points.contains(&current_s) {
-            return current_s;
-        }
-        match current.parent() {
-            Some(parent) => {
-                let parent_s = parent.to_string_lossy().to_string();
-                if parent_s == current_s {
-                    return current_s;
-                }
-                current = parent.to_path_buf();
-            }
-            None => return current_s,
+    if show_trash_dir {
+        let attribute = match config.attribute_to_print {
+            Attribute::DeletionDate => Field::Date,
+            Attribute::Size => Field::Size,
+        };
+        let fields = config.output.get_or_insert_with(|| match config.show_files {
+            true => vec![attribute, Field::Path, Field::File],
+            false => vec![attribute, Field::Path],
+        });
+        if !fields.contains(&Field::TrashDir) {
+            fields.push(Field::TrashDir);
         }
     }
-}
-
-fn current_uid() -> String {
-    if let Ok(uid) = env::var("UID") {
-        if !uid.is_empty() {
-            return uid;
-        }
+    if config.trash_dirs.is_empty() {
+        config.trash_dirs = user_config.trash_dirs.clone();
     }
-    "0".to_string()
+    Ok(Some(config))
 }
 
-fn home_trash_dirs_from_environ(environ: &HashMap<String, String>) -> Vec<String> {
-    if let Some(xdg_data_home) = environ.get("XDG_DATA_HOME") {
-        return vec![format!("{}/Trash", xdg_data_home)];
-    }
-    if let Some(home) = environ.get("HOME") {
-        return vec![format!("{}/.local/share/Trash", home)];
-    }
-    Vec::new()
+fn parse_count(option: &str, value: &str) -> Result<usize, UsageError> {
+    value
+        .parse::<usize>()
+        .map_err(|_| UsageError(format!("argument {}: invalid int value: '{}'", option, value)))
 }
 
-fn list_users() -> Vec<(String, String)> {
-    let passwd = match fs::read_to_string("/etc/passwd") {
-        Ok(content) => content,
-        Err(_) => return Vec::new(),
+fn list_trash_volumes(config: &ListConfig, environ: &dyn Environment) {
+    let volumes = list_volumes(environ, &config.network);
+    let volumes = match config.all_volumes {
+        true => volumes,
+        false => storage_volumes(volumes),
     };
-    let mut users = Vec::new();
-    for line in passwd.lines() {
-        let fields: Vec<&str> = line.split(':').collect();
-        if fields.len() < 7 {
-            continue;
-        }
-        let uid = fields[2];
-        let home = fields[5];
-        users.push((uid.to_string(), home.to_string()));
-    }
-    users
-}
-
-fn scan_trash_dirs_for_current_user(
-    environ: &HashMap<String, String>,
-    volumes: &[String],
-    mount_points: &HashSet<String>,
-) -> Vec<Event> {
-    let mut events = Vec::new();
-
-    for path in home_trash_dirs_from_environ(environ) {
-        let path = PathBuf::from(path);
-        let uid = current_uid();
-        events.push(Event::Found(TrashDir {
-            path,
-            volume: volume_of(&path, mount_points),
-        }));
-    }
-
     for volume in volumes {
-        scan_top_trash_dir(volume, &current_uid(), mount_points, &mut events);
-    }
-    events
-}
-
-fn scan_top_trash_dir(
-    volume: &str,
-    uid: &str,
-    mount_points: &HashSet<String>,
-    out: &mut Vec<Event>,
-) {
-    let top = Path::new(volume).join(".Trash").join(uid);
-    match validate_top_trash_dir(&top) {
-        ScanEvent::Found(_) => out.push(Event::Found(TrashDir {
-            path: top,
-            volume: volume.to_string(),
-        })),
-        ScanEvent::SkippedNotSticky(path) => out.push(Event::SkipNotSticky(path)),
-        ScanEvent::SkippedSymlink(path) => out.push(Event::SkipSymlink(path)),
-        ScanEvent::SkippedMissing => {}
-    }
-
-    let alt = Path::new(volume).join(format!(".Trash-{}", uid));
-    if is_dir(&alt) {
-        out.push(Event::Found(TrashDir {
-            path: alt,
-            volume: volume.to_string(),
-        }));
+        print_line(volume.as_os_str());
     }
 }
 
-fn validate_top_trash_dir(path: &Path) -> ScanEvent {
-    if !path.exists() {
-        return ScanEvent::SkippedMissing;
-    }
-    let parent = match path.parent() {
-        Some(p) => p,
-        None => return ScanEvent::SkippedMissing,
-    };
-    if !is_sticky_dir(parent) {
-        return ScanEvent::SkippedNotSticky(path.to_path_buf());
-    }
-    if is_symlink(parent) {
-        return ScanEvent::SkippedSymlink(path.to_path_buf());
-    }
-    ScanEvent::Found(TrashDir {
-        path: path.to_path_buf(),
-        volume: String::new(),
-    })
-}
-
-fn scan_all_users_volumes(
-    volumes: &[String],
-    mount_points: &HashSet<String>,
-) -> Vec<Event> {
-    let mut events = Vec::new();
-    for (_uid, home) in list_users() {
-        let _ = _uid;
-        let path = Path::new(&home).join(".local/share/Trash");
-        events.push(Event::Found(TrashDir {
-            path,
-            volume: volume_of(&PathBuf::from(home).join(".local/share/Trash"), mount_points),
-        }));
-        for volume in volumes {
-            scan_top_trash_dir(volume, &_uid, mount_points, &mut events);
-        }
-    }
-    events
+/// Prints `line` byte for byte, so that non-UTF-8 paths survive.
+fn print_line(line: &OsStr) {
+    let mut stdout = io::stdout().lock();
+    let _ = stdout.write_all(line.as_bytes()).and_then(|()| stdout.write_all(b"\n"));
 }
 
-fn select_trash_dirs(
-    all_users: bool,
-    user_specified_dirs: &[String],
-    environ: &HashMap<String, String>,
-    mount_points: &HashSet<String>,
-) -> Vec<Event> {
-    let volumes = list_volumes(environ);
-    let mut events = Vec::new();
-
-    if all_users {
-        events.extend(scan_all_users_volumes(&volumes, mount_points));
-        return events;
-    }
-
-    if user_specified_dirs.is_empty() {
-        events.extend(scan_trash_dirs_for_current_user(environ, &volumes, mount_points));
+/// The trash dirs to read: those of `--trash-dir`, of every user with
+/// `--all-users`, or the current user's.
+fn select_trash_dirs(config: &ListConfig, environ: &dyn Environment, mount_points: &[PathBuf]) -> Vec<TrashDirEvent> {
+    if config.all_users {
+        scan_all_users_trash_dirs(&RealFileSystem, &SystemUsers, &list_volumes(environ, &config.network))
+    } else if config.trash_dirs.is_empty() {
+        scan_trash_dirs(&RealFileSystem, environ, environ.uid(), &list_volumes(environ, &config.network))
     } else {
-        for item in user_specified_dirs {
-            events.push(Event::Found(TrashDir {
-                path: PathBuf::from(item),
-                volume: volume_of(Path::new(item), mount_points),
-            }));
-        }
+        user_specified_trash_dirs(&config.trash_dirs, mount_points)
     }
-
-    events
 }
 
 fn file_size(path: &Path) -> Result<u64, String> {
@@ -470,110 +323,267 @@ fn file_size(path: &Path) -> Result<u64, String> {
 
 fn backup_copy_path(trashinfo_path: &Path) -> PathBuf {
     let trash_dir = trashinfo_path.parent().and_then(|p| p.parent()).unwrap_or_else(|| Path::new(""));
-    let basename = trashinfo_path.file_name().and_then(|v| v.to_str()).unwrap_or("");
-    let backup_name = basename.strip_suffix(".trashinfo").unwrap_or(basename);
+    let backup_name = entry_name(trashinfo_path).unwrap_or_default();
     trash_dir.join("files").join(backup_name)
 }
 
-fn extract_deletion_date(contents: &str) -> String {
-    let mut date = UNKNOWN_DELETION_DATE.to_string();
-    for line in contents.lines() {
-        if line.starts_with("DeletionDate=") && date == UNKNOWN_DELETION_DATE {
-            let value = &line["DeletionDate=".len()..];
-            let parsed = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S");
-            match parsed {
-                Ok(value) => date = value.format("%Y-%m-%d %H:%M:%S").to_string(),
-                Err(_) => date = UNKNOWN_DELETION_DATE.to_string(),
-            }
-            break;
-        }
-    }
-    date
+fn extract_deletion_date(keys: &TrashInfoKeys, zone: DisplayZone) -> String {
+    format_date(keys.deleted_at(), zone)
 }
 
-fn parse_path(contents: &str) -> Result<String, String> {
-    for line in contents.lines() {
-        if line.starts_with("Path=") {
-            let encoded = &line["Path=".len()..];
-            return url_decode(encoded)
-                .map(|decoded| decoded.into_owned())
-                .map_err(|error| error.to_string());
+fn format_date(date: Option<DateTime<Utc>>, zone: DisplayZone) -> String {
+    date.map_or_else(|| UNKNOWN_DELETION_DATE.to_string(), |date| format_datetime(date, zone))
+}
+
+/// Sizes the trashed copy of the item of the trashinfo `path` from a single
+/// [`FileSystem::size_stat`]: a directory takes its cached size or is
+/// walked, a symlink counts as its target (0 when broken), anything else as
+/// itself.
+fn item_size(path: &Path, sizes: &DirectorySizes) -> Result<u64, String> {
+    let backup_copy = backup_copy_path(path);
+    let stat = RealFileSystem.size_stat(&backup_copy, None).map_err(|err| io::Error::from(err).to_string())?;
+    if stat.is_symlink {
+        return file_size(&backup_copy);
+    }
+    if stat.is_dir {
+        let cached = backup_copy.file_name().and_then(|name| sizes.fresh_size(&RealFileSystem, name, path));
+        if let Some(size) = cached {
+            return Ok(size);
         }
+        return recursive_size(&RealFileSystem, &backup_copy).map_err(|err| err.to_string());
     }
-    Err("Unable to parse Path.".to_string())
+    Ok(stat.size)
 }
 
-fn extract_attribute(path: &Path, contents: &str, attribute: Attribute) -> Result<String, String> {
+fn extract_attribute(
+    keys: &TrashInfoKeys,
+    attribute: Attribute,
+    size: impl FnOnce() -> Result<u64, String>,
+    zone: DisplayZone,
+) -> Result<String, String> {
     match attribute {
-        Attribute::DeletionDate => Ok(extract_deletion_date(contents)),
-        Attribute::Size => {
-            let backup_copy = backup_copy_path(path);
-            let size = file_size(&backup_copy)?;
-            Ok(size.to_string())
+        Attribute::DeletionDate => Ok(extract_deletion_date(keys, zone)),
+        Attribute::Size => size().map(|size| size.to_string()),
+    }
+}
+
+fn format_line(attr: &str, original_location: &Path) -> OsString {
+    let mut line = OsString::from(attr);
+    line.push(" ");
+    line.push(original_location);
+    line
+}
+
+fn format_line2(attr: &str, original_location: &Path, original_file: &Path) -> OsString {
+    let mut line = format_line(attr, original_location);
+    line.push(" -> ");
+    line.push(original_file);
+    line
+}
+
+/// Accepts both absolute `Path=` values and ones relative to the volume
+/// of a top directory trash.
+fn compose_original_location(volume: &Path, stored_location: &Path) -> PathBuf {
+    let volume = if volume.as_os_str().is_empty() { Path::new("/") } else { volume };
+    resolve_original_location(volume, stored_location)
+}
+
+/// The `.trashinfo` files of `trash_dir` with their keys, through its index
+/// when enabled and straight from `info/` otherwise, where only the lines up
+/// to the last key needed are read.
+fn trashinfos(trash_dir: &Path, use_index: bool) -> trash_cli_core::Result<Box<dyn Iterator<Item = TrashinfoEntry>>> {
+    if use_index {
+        let indexed = TrashIndex::refresh(&RealFileSystem, trash_dir)?;
+        return Ok(Box::new(indexed.into_iter().map(|item| {
+            let keys = item.content.and_then(|content| {
+                TrashInfoKeys::read(content.as_bytes()).map_err(|err| CoreError::io(&item.path, err))
+            });
+            Ok((item.path, keys))
+        })));
+    }
+    let entries = RealFileSystem.read_dir_iter(&trash_dir.join("info"))?;
+    Ok(Box::new(entries.filter_map(|entry| {
+        let path = match entry {
+            Ok(entry) => entry.path,
+            Err(err) => return Some(Err(err)),
+        };
+        let is_trashinfo = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext == "trashinfo");
+        if !is_trashinfo {
+            log::debug("list", format_args!("skipping {}: not a .trashinfo file", path.display()));
+            return None;
         }
-    }
+        let keys = TrashInfoKeys::load(&RealFileSystem, &path);
+        Some(Ok((path, keys)))
+    })))
 }
 
-fn format_line(attr: &str, original_location: &str) -> String {
-    format!("{} {}", attr, original_location)
-}
+/// A `.trashinfo` file and its keys, or the error listing `info/`.
+type TrashinfoEntry = trash_cli_core::Result<(PathBuf, trash_cli_core::Result<TrashInfoKeys>)>;
 
-fn format_line2(attr: &str, original_location: &str, original_file: &Path) -> String {
-    format!("{} {} -> {}", attr, original_location, original_file.display())
+/// Where the lines of `trash-list` go: printed as they are found, within
+/// `--skip` and `--limit`, or collected to be sorted first.
+enum Listing {
+    Streamed { skip: usize, left: Option<usize> },
+    Sorted(Vec<(TrashedItem, OsString)>),
 }
 
-fn compose_original_location(volume: &str, relative_location: &str) -> String {
-    if Path::new(relative_location).is_absolute() {
-        return relative_location.to_string();
+impl Listing {
+    fn new(config: &ListConfig) -> Self {
+        match config.sort {
+            Some(_) => Listing::Sorted(Vec::new()),
+            None => Listing::Streamed {
+                skip: config.skip,
+                left: config.limit,
+            },
+        }
     }
-    let volume = volume.trim_end_matches('/');
-    if volume.is_empty() || volume == "/" {
-        format!("/{}", relative_location.trim_start_matches('/'))
-    } else {
-        format!("{}/{}", volume, relative_location.trim_start_matches('/'))
+
+    /// Whether the next item is left out by `--skip`, which streamed
+    /// listings know before its line is built.
+    fn skips_next(&mut self) -> bool {
+        match self {
+            Listing::Streamed { skip, .. } if *skip > 0 => {
+                *skip -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn push(&mut self, item: TrashedItem, line: OsString) {
+        match self {
+            Listing::Streamed { left, .. } => {
+                print_line(&line);
+                if let Some(left) = left {
+                    *left = left.saturating_sub(1);
+                }
+            }
+            Listing::Sorted(lines) => lines.push((item, line)),
+        }
+    }
+
+    /// Whether `--limit` was reached, so the remaining trash dirs need not
+    /// be read.
+    fn is_done(&self) -> bool {
+        matches!(self, Listing::Streamed { left: Some(0), .. })
+    }
+
+    /// Prints the collected lines in order, within `--skip` and `--limit`.
+    fn finish(self, config: &ListConfig) {
+        let Listing::Sorted(mut lines) = self else {
+            return;
+        };
+        sort_items_by(&mut lines, config.sort.unwrap_or_default(), |(item, _)| item);
+        if config.reverse {
+            lines.reverse();
+        }
+        for (_, line) in lines.into_iter().skip(config.skip).take(config.limit.unwrap_or(usize::MAX)) {
+            print_line(&line);
+        }
     }
 }
 
+/// Lists the items of `trash_dir` into `listing`; on a `terminal`, the
+/// default lines write `home` as `~` and escape control characters,
+/// `--output` rows keep paths as they are. `painter` colors dates, sizes and
+/// the paths of items whose trashed copy is missing.
 fn list_trash_for_dir(
-    trash_dir: &TrashDir,
+    trash_dir: &TrashDirectory,
     config: &ListConfig,
+    listing: &mut Listing,
+    painter: &Painter,
+    terminal: bool,
+    home: Option<&Path>,
 ) -> Result<(), String> {
-    let info_dir = trash_dir.path.join("info");
-    let entries = match fs::read_dir(&info_dir) {
+    let shown = |path: &Path| match terminal {
+        true => terminal_path(path, home),
+        false => path.to_path_buf(),
+    };
+    let volume = trash_dir.mount_point.as_deref().unwrap_or(Path::new("/"));
+    let entries = match trashinfos(&trash_dir.path, config.use_index) {
         Ok(entries) => entries,
-        Err(_) => return Ok(()),
+        Err(err) => {
+            log::debug("list", format_args!("cannot read {}: {}", trash_dir.path.join("info").display(), err));
+            return Ok(());
+        }
+    };
+    let sorts_by_size = config.sort.is_some_and(|sort| sort.needs_size());
+    let needs_sizes = sorts_by_size
+        || match &config.output {
+            Some(fields) => fields.contains(&Field::Size),
+            None => matches!(config.attribute_to_print, Attribute::Size),
+        };
+    let sizes = match needs_sizes {
+        true => DirectorySizes::load(&RealFileSystem, &trash_dir.path).unwrap_or_default(),
+        false => DirectorySizes::default(),
     };
 
     for entry in entries {
-        let entry = entry.map_err(|e| e.to_string())?;
-        let path = entry.path();
-        let is_trashinfo = path
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .map_or(false, |ext| ext == "trashinfo");
-        if !is_trashinfo {
-            continue;
+        if listing.is_done() {
+            break;
         }
-
-        match fs::read_to_string(&path) {
-            Ok(contents) => {
-                let relative = match parse_path(&contents) {
+        let (path, keys) = entry.map_err(|e| e.to_string())?;
+        match keys {
+            Ok(keys) => {
+                let relative = match keys.original_location() {
                     Ok(value) => value,
-                    Err(_) => {
-                        eprintln!("Parse Error: {}: Unable to parse Path.", path.display());
+                    Err(reason) => {
+                        eprintln!("Parse Error: {}: {}.", path.display(), reason);
                         continue;
                     }
                 };
-                let attribute = match extract_attribute(&path, &contents, config.attribute_to_print) {
-                    Ok(value) => value,
-                    Err(error) => return Err(error),
+                let original = compose_original_location(volume, &relative);
+                let matched = config.matches.iter().any(|glob| glob.matches_original_path(&original));
+                if !(config.matches.is_empty() || matched) || listing.skips_next() {
+                    continue;
+                }
+                let cached_size = OnceCell::new();
+                let size = || cached_size.get_or_init(|| item_size(&path, &sizes)).clone();
+                let mut item = TrashedItem::new(
+                    original.clone(),
+                    backup_copy_path(&path),
+                    path.clone(),
+                    trash_dir.path.clone(),
+                );
+                item.deleted_at = keys.deleted_at();
+                if sorts_by_size {
+                    item.size_bytes = size().ok();
+                }
+                let missing = painter.is_enabled() && fs::symlink_metadata(&item.trashed_path).is_err();
+                let painted = |path: &Path| match missing {
+                    true => PathBuf::from(painter.paint_os(Role::Missing, path.as_os_str())),
+                    false => path.to_path_buf(),
                 };
-                let original = compose_original_location(&trash_dir.volume, &relative);
+                if let Some(fields) = &config.output {
+                    let row = format_row(fields, |field| match field {
+                        Field::Date => {
+                            let date = extract_deletion_date(&keys, config.zone);
+                            Ok(painter.paint(Role::Date, &date).into_owned().into())
+                        }
+                        Field::Size => {
+                            size().map(|size| painter.paint(Role::Size, &size.to_string()).into_owned().into())
+                        }
+                        Field::Path => Ok(painted(&original).into_os_string()),
+                        Field::TrashDir => Ok(trash_dir.path.clone().into_os_string()),
+                        Field::File => Ok(backup_copy_path(&path).into_os_string()),
+                    })?;
+                    listing.push(item, row);
+                    continue;
+                }
+                let attribute = extract_attribute(&keys, config.attribute_to_print, size, config.zone)?;
+                let attribute = match config.attribute_to_print {
+                    Attribute::DeletionDate => painter.paint(Role::Date, &attribute),
+                    Attribute::Size => painter.paint(Role::Size, &attribute),
+                };
+                let original = painted(&shown(&original));
                 if config.show_files {
-                    let original_file = backup_copy_path(&path);
-                    println!("{}", format_line2(&attribute, &original, &original_file));
+                    let original_file = shown(&backup_copy_path(&path));
+                    listing.push(item, format_line2(&attribute, &original, &original_file));
                 } else {
-                    println!("{}", format_line(&attribute, &original));
+                    listing.push(item, format_line(&attribute, &original));
                 }
             }
             Err(err) => {
@@ -584,103 +594,229 @@ fn list_trash_for_dir(
     Ok(())
 }
 
-fn list_trash_dirs(config: &ListConfig, environ: &HashMap<String, String>, mount_points: &HashSet<String>) {
-    let events = select_trash_dirs(
-        config.all_users,
-        &config.trash_dirs,
-        environ,
-        mount_points,
-    );
+fn list_trash_dirs(config: &ListConfig, environ: &dyn Environment, mount_points: &[PathBuf]) {
+    let events = select_trash_dirs(config, environ, mount_points);
     for event in events {
         match event {
-            Event::Found(trash_dir) => println!("{}", trash_dir.path.display()),
-            Event::SkipNotSticky(path) => println!("parent_not_sticky: {}", path.display()),
-            Event::SkipSymlink(path) => println!("parent_is_symlink: {}", path.display()),
+            TrashDirEvent::Found(trash_dir) => print_line(trash_dir.path.as_os_str()),
+            TrashDirEvent::Skipped(path, problem) => println!("{}: {}", problem.as_str(), path.display()),
         }
     }
 }
 
-fn list_trash(config: &ListConfig, environ: &HashMap<String, String>, mount_points: &HashSet<String>) {
-    let events = select_trash_dirs(
-        config.all_users,
-        &config.trash_dirs,
-        environ,
-        mount_points,
-    );
+fn list_trash(config: &ListConfig, environ: &dyn Environment, mount_points: &[PathBuf]) {
+    let events = select_trash_dirs(config, environ, mount_points);
+    // Piped output is read by scripts, which expect full paths.
+    let terminal = io::stdout().is_terminal();
+    let home = environ.home().filter(|_| !config.no_abbrev && terminal);
 
+    let painter = Painter::new(config.color, &io::stdout(), environ, &config.theme);
+    let mut listing = Listing::new(config);
     for event in events {
+        if listing.is_done() {
+            break;
+        }
         match event {
-            Event::Found(trash_dir) => {
-                if let Err(err) = list_trash_for_dir(&trash_dir, config) {
+            TrashDirEvent::Found(trash_dir) => {
+                log::info("list", format_args!("listing {}", trash_dir.path.display()));
+                let listed = list_trash_for_dir(&trash_dir, config, &mut listing, &painter, terminal, home.as_deref());
+                if let Err(err) = listed {
                     eprintln!("{}", err);
                 }
             }
-            Event::SkipNotSticky(path) => eprintln!(
-                "TrashDir skipped because parent not sticky: {}",
-                path.display()
-            ),
-            Event::SkipSymlink(path) => eprintln!(
-                "TrashDir skipped because parent is symlink: {}",
-                path.display()
-            ),
+            TrashDirEvent::Skipped(path, problem) => {
+                eprintln!("TrashDir skipped because {}: {}", problem, path.display())
+            }
         }
     }
+    listing.finish(config);
+}
+
+fn stats_for_dir(trash_dir: &TrashDirectory, use_index: bool) -> TrashStats {
+    let mut stats = TrashStats::default();
+    let entries = match trashinfos(&trash_dir.path, use_index) {
+        Ok(entries) => entries,
+        Err(_) => return stats,
+    };
+    let sizes = DirectorySizes::load(&RealFileSystem, &trash_dir.path).unwrap_or_default();
+
+    for (path, keys) in entries.flatten() {
+        let keys = match keys {
+            Ok(keys) => keys,
+            Err(_) => continue,
+        };
+        stats.record(item_size(&path, &sizes).ok(), keys.deleted_at());
+    }
+    stats
+}
+
+fn format_stats(stats: &TrashStats, config: &ListConfig) -> Vec<String> {
+    let mut size = format_size(stats.total_size, config.size_format);
+    if stats.unknown_size_count > 0 {
+        size.push_str(&format!(" ({} unknown)", stats.unknown_size_count));
+    }
+    vec![
+        format!("  items:  {}", stats.item_count),
+        format!("  size:   {}", size),
+        format!("  oldest: {}", format_date(stats.oldest, config.zone)),
+        format!("  newest: {}", format_date(stats.newest, config.zone)),
+    ]
+}
+
+fn print_stats(config: &ListConfig, environ: &dyn Environment, mount_points: &[PathBuf]) {
+    let events = select_trash_dirs(config, environ, mount_points);
+
+    let mut total = TrashStats::default();
+    for event in events {
+        if let TrashDirEvent::Found(trash_dir) = event {
+            let stats = stats_for_dir(&trash_dir, config.use_index);
+            if stats.is_empty() {
+                continue;
+            }
+            println!("{}", trash_dir.path.display());
+            for line in format_stats(&stats, config) {
+                println!("{}", line);
+            }
+            total.merge(&stats);
+        }
+    }
+    println!("total");
+    for line in format_stats(&total, config) {
+        println!("{}", line);
+    }
+}
+
+/// Audits every trash dir, printing one `KIND: PATH: PROBLEM` line per
+/// issue; 1 when any was found.
+fn check_trash(config: &ListConfig, environ: &dyn Environment, mount_points: &[PathBuf]) -> i32 {
+    let painter = Painter::new(config.color, &io::stdout(), environ, &config.theme);
+    let events = select_trash_dirs(config, environ, mount_points);
+    let mut found = false;
+    for event in events {
+        let TrashDirEvent::Found(trash_dir) = event else {
+            continue;
+        };
+        log::info("list", format_args!("checking {}", trash_dir.path.display()));
+        match TrashAudit::run(&RealFileSystem, &trash_dir.path) {
+            Ok(audit) => {
+                for issue in &audit.issues {
+                    let line = format!("{}: {}", issue.kind(), issue);
+                    match issue {
+                        AuditIssue::OrphanBackup { .. } => println!("{}", painter.paint(Role::Orphan, &line)),
+                        AuditIssue::MissingBackup { .. } => println!("{}", painter.paint(Role::Missing, &line)),
+                        AuditIssue::Malformed { .. } => println!("{}", line),
+                    }
+                }
+                found |= !audit.is_clean();
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                found = true;
+            }
+        }
+    }
+    i32::from(found)
 }
 
 fn debug_volumes() {
     let mounts = list_mount_points();
     let mut physical = mounts.clone();
     physical.sort();
-    let virtual = Vec::<String>::new();
     println!("physical ->");
     println!("{:#?}", physical);
-    println!("virtual ->");
-    println!("{:#?}", virtual);
-    if let Ok(output) = Command::new("df").args(["-P"]).output() {
-        print!("{}", String::from_utf8_lossy(&output.stdout));
+    print_space_usage(&list_mount_entries());
+}
+
+/// A `df`-like table of `mounts`, read with `statvfs`; sizes that cannot
+/// be read are `-`.
+fn print_space_usage(mounts: &[MountEntry]) {
+    let mut rows = vec![["Device", "Type", "Size", "Used", "Avail", "Mounted on"].map(String::from)];
+    for mount in mounts {
+        let sizes = match RealFileSystem.space_usage(&mount.target) {
+            Ok(usage) => [usage.size, usage.used, usage.available].map(|size| format_size(size, SizeFormat::Binary)),
+            Err(_) => ["-", "-", "-"].map(String::from),
+        };
+        let [size, used, available] = sizes;
+        let target = mount.target.display().to_string();
+        rows.push([mount.source.clone(), mount.fstype.clone(), size, used, available, target]);
+    }
+    let mut widths = [0; 6];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for [source, fstype, size, used, available, target] in &rows {
+        println!(
+            "{:w0$}  {:w1$}  {:>w2$}  {:>w3$}  {:>w4$}  {}",
+            source,
+            fstype,
+            size,
+            used,
+            available,
+            target,
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3],
+            w4 = widths[4],
+        );
     }
 }
 
 fn print_version() {
-    println!("trash-list {}", VERSION);
+    println!("{}", version_line("trash-list"));
 }
 
 fn print_python_executable() {
     println!(
         "{}",
-        env::var("TRASH_LIST_PYTHON_EXECUTABLE").unwrap_or_else(|_| "python".to_string())
+        RealEnvironment
+            .python_executable(CommandKind::List)
+            .unwrap_or_else(|| "python".to_string())
     );
 }
 
 fn run() -> i32 {
-    let args: Vec<String> = env::args().skip(1).collect();
-    let program = env::args().next().unwrap_or_else(|| "trash-list".to_string());
-    let environ: HashMap<String, String> = env::vars().collect();
-
-    let config = match parse_args(&args) {
-        Ok(config) => config,
-        Err(error) if error.0 == "help" => {
-            print_help(&program);
+    let args: Vec<OsString> = env::args_os().skip(1).collect();
+    // argparse's default prog: the basename of argv[0].
+    let program = env::args_os()
+        .next()
+        .and_then(|arg0| Path::new(&arg0).file_name().map(|name| name.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "trash-list".to_string());
+    let environ = RealEnvironment;
+    let user_config = load_config(&program);
+
+    let config = match parse_args(&program, &user_config, &args) {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            println!("{}", parser(&program).format_help());
             return 0;
         }
-        Err(error) if error.0 == "complete" => return 0,
         Err(error) => {
-            eprintln!("trash-list: {}", error);
-            return 2;
+            eprintln!("{}", parser(&program).format_error(&error.0));
+            return USAGE_EXIT_CODE;
         }
     };
 
-    let mount_points: HashSet<String> = list_mount_points().into_iter().collect();
+    log::init(&program, config.verbose);
+    let mount_points = list_mount_points();
 
     match config.action {
+        Action::PrintCompletion(shell) => {
+            print!("{}", parser(&program).format_completion(shell));
+        }
         Action::PrintVersion => {
             print_version();
         }
         Action::DebugVolumes => {
             debug_volumes();
         }
+        Action::DebugEnv => {
+            println!("{}", describe_environment(&environ, CommandKind::List));
+        }
         Action::ListVolumes => {
-            list_trash_volumes(&environ);
+            list_trash_volumes(&config, &environ);
         }
         Action::ListTrashDirs => {
             list_trash_dirs(&config, &environ, &mount_points);
@@ -688,6 +824,15 @@ fn run() -> i32 {
         Action::ListTrash => {
             list_trash(&config, &environ, &mount_points);
         }
+        Action::Stats => {
+            print_stats(&config, &environ, &mount_points);
+        }
+        Action::Check => {
+            return check_trash(&config, &environ, &mount_points);
+        }
+        Action::ListFields => {
+            println!("{}", describe_fields());
+        }
         Action::PrintPythonExecutable => {
             print_python_executable();
         }
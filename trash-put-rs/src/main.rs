@@ -1,53 +1,302 @@
 use std::env;
-use std::path::Path;
-use std::process::{Command, ExitStatus};
-
-const PYTHON_BOOTSTRAP: &str = "import os, sys\nfrom trashcli.put.main import main as main\n\nsys.argv[0] = os.environ.get('TRASH_PUT_WRAPPER_NAME', 'trash-put')\nraise SystemExit(main())";
-
-const PYTHON_EXECUTABLES: [&str; 2] = ["python3", "python"];
-
-fn find_interpreter() -> Option<&'static str> {
-    PYTHON_EXECUTABLES.iter().find(|candidate| {
-        Command::new(candidate)
-            .arg("-c")
-            .arg("pass")
-            .status()
-            .map(|status| status.success())
-            .unwrap_or(false)
-    }).copied()
-}
-
-fn run_python_backend() -> std::io::Result<ExitStatus> {
-    let mut args = env::args_os();
-    let wrapper_arg = args.next().unwrap_or_else(|| "trash-put".into());
-    let wrapper_name = Path::new(&wrapper_arg)
-        .file_name()
-        .and_then(|name| name.to_str())
-        .unwrap_or("trash-put");
-
-    let python = find_interpreter().unwrap_or("python3");
-    let mut command = Command::new(python);
-    let status = command
-        .arg("-c")
-        .arg(PYTHON_BOOTSTRAP)
-        .env("TRASH_PUT_WRAPPER_NAME", wrapper_name)
-        .args(args)
-        .status()?;
-    Ok(status)
+use std::ffi::OsString;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use trash_cli_core::cli::{
+    files_from, load_config, trash_dir_arg, Arg, Complete, Nargs, Parsed, Parser, Shell, UsageError, DEBUG_ENV_ARG,
+    FILES_FROM_ARG, NULL_ARG, PRINT_COMPLETION_ARG, VERSION_ARG,
+};
+use trash_cli_core::exit_codes;
+use trash_cli_core::log;
+use trash_cli_core::put::describe;
+use trash_cli_core::{
+    describe_environment, notify, printable_path, tr, tr_format, version_line, CancellationToken, CommandKind,
+    CommandOutcome, Config, OperationReport, RealEnvironment, RealFileSystem, TrashOptions, Trasher,
+};
+
+const ARGS: &[Arg] = &[
+    PRINT_COMPLETION_ARG,
+    DEBUG_ENV_ARG,
+    Arg::flag("directory", &["-d", "--directory"], "ignored (for GNU rm compatibility)"),
+    Arg::flag(
+        "force",
+        &["-f", "--force"],
+        "ignore nonexistent files and never prompt, as rm -f does (protected paths stay refused)",
+    ),
+    Arg::flag(
+        "force_protected",
+        &["--force-protected"],
+        "allow trashing protected paths (/, home, mount points, trash dirs and the configured protected_paths)",
+    ),
+    Arg::flag("interactive", &["-i", "--interactive"], "prompt before every removal"),
+    Arg::flag("recursive", &["-r", "-R", "--recursive"], "ignored (for GNU rm compatibility)"),
+    Arg::option("trash_dir", &["--trash-dir"], "TRASHDIR", "use TRASHDIR as trash folder")
+        .complete(Complete::Directory),
+    Arg::flag("verbose", &["-v", "--verbose"], "explain what is being done"),
+    Arg::flag(
+        "print",
+        &["--print"],
+        "print the path each file was trashed as, one per line, and nothing else on stdout",
+    ),
+    Arg::option(
+        "forced_volume",
+        &["--force-volume"],
+        "VOLUME",
+        "treat every file as living on VOLUME when choosing (and creating) its top directory trash",
+    )
+    .complete(Complete::Directory),
+    Arg::flag(
+        "home_fallback",
+        &["--home-fallback"],
+        "when no trash dir of the file's volume is usable, copy it into the home trash and delete the original \
+         (also enabled by TRASH_ENABLE_HOME_FALLBACK=1)",
+    ),
+    Arg::flag(
+        "no_abbrev",
+        &["--no-abbrev"],
+        "with -v, print paths below the home directory in full, not as ~/..., even on a terminal",
+    ),
+    Arg::flag(
+        "allow_special",
+        &["--allow-special"],
+        "also trash named pipes, sockets and device nodes, which are refused by default",
+    ),
+    Arg::flag(
+        "fsync",
+        &["--fsync"],
+        "flush each trashinfo file and its directory to disk before moving the file, so a crash cannot lose it",
+    ),
+    FILES_FROM_ARG,
+    NULL_ARG,
+    VERSION_ARG,
+    Arg::positional("files", Nargs::ZeroOrMore, "").complete(Complete::File),
+];
+
+const EPILOG: &str = "\
+all trash-cli commands:
+  trash-put             trash files and directories.
+  trash-empty           empty the trashcan(s).
+  trash-list            list trashed files.
+  trash-restore         restore a trashed file.
+  trash-rm              remove individual files from the trashcan
+
+To remove a file whose name starts with a '-', for example '-foo',
+use one of these commands:
+
+    trash -- -foo
+
+    trash ./-foo
+
+Report bugs to https://github.com/andreafrancia/trash-cli/issues";
+
+fn parser() -> Parser {
+    Parser::new("trash-put", "Put files in trash", ARGS)
+        .with_usage("[OPTION]... FILE...")
+        .with_epilog(EPILOG)
 }
 
-fn main() {
-    match run_python_backend() {
-        Ok(status) => {
-            if let Some(code) = status.code() {
-                std::process::exit(code);
-            }
-            // Python exited by signal; propagate a generic failure code.
-            std::process::exit(1);
+/// What to do about prompting and missing files; the last of `-i`/`-f`
+/// wins, as with GNU rm.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+enum Mode {
+    #[default]
+    Unspecified,
+    /// `-i`: ask before trashing each file.
+    Interactive,
+    /// `-f`: silently skip nonexistent files, cancelling an earlier `-i`.
+    Force,
+}
+
+/// Options understood by the native implementation.
+#[derive(Debug, Default)]
+struct PutOptions {
+    files: Vec<PathBuf>,
+    /// `--files-from`: more files, read while trashing.
+    files_from: Option<PathBuf>,
+    /// `-0`: the `--files-from` list is NUL-separated.
+    null: bool,
+    trash: TrashOptions,
+    mode: Mode,
+    /// rm flags accepted for compatibility but without any effect.
+    ignored_flags: Vec<String>,
+}
+
+#[derive(Debug)]
+enum Invocation {
+    Help,
+    PrintCompletion(Shell),
+    PrintVersion,
+    DebugEnv,
+    Native(PutOptions),
+}
+
+fn parse_args(config: &Config, args: &[OsString]) -> Result<Invocation, UsageError> {
+    let matches = match parser().parse_with_config(config, CommandKind::Put, args)? {
+        Parsed::Help => return Ok(Invocation::Help),
+        Parsed::Args(matches) => matches,
+    };
+    if let Some(found) = matches.iter().find(|found| found.dest == "print_completion") {
+        let shell = Shell::parse(&found.text()).expect("checked against the choices");
+        return Ok(Invocation::PrintCompletion(shell));
+    }
+    if matches.iter().any(|found| found.dest == "version") {
+        return Ok(Invocation::PrintVersion);
+    }
+    if matches.iter().any(|found| found.dest == "debug_env") {
+        return Ok(Invocation::DebugEnv);
+    }
+    let mut options = PutOptions::default();
+    let mut no_abbrev = false;
+    for found in matches {
+        match found.dest {
+            "directory" | "recursive" => options.ignore_flag(found.option),
+            "force" => options.mode = Mode::Force,
+            "force_protected" => options.trash.force_protected = true,
+            "interactive" => options.mode = Mode::Interactive,
+            "trash_dir" => options.trash.trash_dir = found.value.map(trash_dir_arg),
+            "verbose" => options.trash.verbose += 1,
+            "print" => options.trash.print = true,
+            "forced_volume" => options.trash.forced_volume = found.value.map(PathBuf::from),
+            "home_fallback" => options.trash.home_fallback = true,
+            "fsync" => options.trash.fsync = true,
+            "allow_special" => options.trash.allow_special = true,
+            "no_abbrev" => no_abbrev = true,
+            "files" => options.files.extend(found.value.map(PathBuf::from)),
+            "files_from" => options.files_from = found.value.map(PathBuf::from),
+            "null" => options.null = true,
+            _ => {}
         }
-        Err(err) => {
-            eprintln!("trash-put: failed to invoke Python backend: {}", err);
-            std::process::exit(1);
+    }
+
+    if options.files.is_empty() && options.files_from.is_none() {
+        return Err(UsageError("Please specify the files to trash.".to_string()));
+    }
+    if options.null && options.files_from.is_none() {
+        return Err(UsageError("-0/--null only applies to --files-from".to_string()));
+    }
+    if options.mode == Mode::Interactive && options.files_from.as_deref() == Some(Path::new("-")) {
+        return Err(UsageError("--interactive cannot be used with --files-from -".to_string()));
+    }
+    options.trash.force = options.mode == Mode::Force;
+    options.trash.protected_paths = config.protected_paths.clone();
+    options.trash.quota = config.quota;
+    options.trash.progress = true;
+    options.trash.abbreviate_home = !no_abbrev && io::stderr().is_terminal();
+    options.trash.escape_control = io::stderr().is_terminal();
+    Ok(Invocation::Native(options))
+}
+
+impl PutOptions {
+    fn ignore_flag(&mut self, flag: &str) {
+        if !self.ignored_flags.iter().any(|ignored| ignored == flag) {
+            self.ignored_flags.push(flag.to_string());
+        }
+    }
+}
+
+/// Asks whether `path` should be trashed; only a reply starting with
+/// `y`/`Y` proceeds.
+fn confirm_trash(path: &Path) -> bool {
+    let shown = printable_path(path, &io::stdout());
+    print!("{}", tr_format("trash-put: trash {} '{}'? ", &[&describe(&RealFileSystem, path), &shown.display()]));
+    let _ = io::stdout().flush();
+
+    let mut reply = String::new();
+    if io::stdin().lock().read_line(&mut reply).is_err() {
+        return false;
+    }
+    reply.trim_start().chars().next().is_some_and(|ch| ch.eq_ignore_ascii_case(&'y'))
+}
+
+fn run_native(options: PutOptions) -> i32 {
+    log::init("trash-put", options.trash.verbose);
+    let environ = RealEnvironment;
+    // A prompt waiting for its answer would not notice an interrupt, so
+    // with -i the signals keep their default action.
+    let cancel = match options.mode {
+        Mode::Interactive => CancellationToken::new(),
+        _ => CancellationToken::on_signals(),
+    };
+    let mut trasher = Trasher::new(&RealFileSystem, &environ, &options.trash).with_cancel(cancel.clone());
+    if options.mode == Mode::Interactive {
+        trasher = trasher.with_confirm(&confirm_trash);
+    }
+
+    if !options.ignored_flags.is_empty() {
+        eprintln!(
+            "{}",
+            tr_format(
+                "trash-put: warning: ignoring {} (directories are always trashed with their contents)",
+                &[&options.ignored_flags.join(", ")],
+            )
+        );
+    }
+    // Like rm, a file that cannot be trashed is reported and the next one
+    // is tried; the exit code is the worst of all the outcomes.
+    let mut report = OperationReport::new(CommandKind::Put);
+    // Fails with `Interrupted` instead of starting on a file once cancelled.
+    let mut trash = |path: &Path| {
+        cancel.check()?;
+        let outcome = trasher.trash_outcome(path);
+        if let CommandOutcome::Completed(output) = &outcome {
+            let _ = output.write_to(&mut io::stdout(), &mut io::stderr());
         }
+        report.record(outcome);
+        Ok(())
+    };
+    let mut stopped = options.files.iter().try_for_each(|path| trash(path)).err();
+    if let (None, Some(source)) = (&stopped, &options.files_from) {
+        let separator = if options.null { b'\0' } else { b'\n' };
+        stopped = match files_from(source, separator) {
+            Ok(mut paths) => paths.try_for_each(|path| trash(&path?)).err(),
+            Err(err) => Some(err),
+        };
+        if let Some(err) = stopped.as_ref().filter(|_| !cancel.is_cancelled()) {
+            eprintln!("{}", tr_format("trash-put: cannot read {}: {}", &[&source.display(), &err]));
+            report.record(CommandOutcome::Failed {
+                command: CommandKind::Put,
+                reason: err.to_string(),
+            });
+        }
+    }
+    match trasher.trashed() {
+        0 => {}
+        1 => notify(&environ, tr("1 file moved to trash")),
+        trashed => notify(&environ, &tr_format("{} files moved to trash", &[&trashed])),
+    }
+    if cancel.is_cancelled() {
+        eprintln!("{}", tr("trash-put: interrupted"));
+        return exit_codes::INTERRUPTED.into();
+    }
+    for purged in trasher.enforce_quota() {
+        match purged {
+            Ok(item) if options.trash.verbose > 0 => {
+                let original = printable_path(&item.original_path, &io::stderr());
+                let original = original.display();
+                eprintln!("{}", tr_format("trash-put: purged '{}' to stay within the trash quota", &[&original]))
+            }
+            Ok(_) => {}
+            Err(err) => {
+                eprintln!("{}", tr_format("trash-put: cannot enforce the trash quota: {}", &[&err]));
+                report.record(CommandOutcome::Failed {
+                    command: CommandKind::Put,
+                    reason: err.to_string(),
+                });
+            }
+        }
+    }
+    i32::from(report.exit_code())
+}
+
+fn main() {
+    let args: Vec<OsString> = env::args_os().skip(1).collect();
+    let config = load_config("trash-put");
+    match parse_args(&config, &args) {
+        Ok(Invocation::Help) => println!("{}", parser().format_help()),
+        Ok(Invocation::PrintCompletion(shell)) => print!("{}", parser().format_completion(shell)),
+        Ok(Invocation::PrintVersion) => println!("{}", version_line("trash-put")),
+        Ok(Invocation::DebugEnv) => println!("{}", describe_environment(&RealEnvironment, CommandKind::Put)),
+        Ok(Invocation::Native(options)) => std::process::exit(run_native(options)),
+        Err(error) => parser().exit_with_error(&error.0),
     }
 }
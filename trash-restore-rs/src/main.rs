@@ -1,49 +1,461 @@
+mod picker;
+
+use std::collections::HashMap;
 use std::env;
-use std::path::{Path, PathBuf};
-use std::process::{exit, Command, Stdio};
+use std::ffi::OsString;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::exit;
+use trash_cli_core::cli::{
+    load_config, trash_dir_arg, Arg, Complete, Nargs, Parsed, Parser, Shell, UsageError, COLOR_ARG, DEBUG_ENV_ARG,
+    VERSION_ARG,
+};
+use trash_cli_core::exit_codes;
+use trash_cli_core::log;
+use trash_cli_core::restore::{self, Conflict, Restored, Restorer};
+use trash_cli_core::trash_dirs::user_specified_trash_dirs;
+use trash_cli_core::{
+    describe_environment, entry_name, format_datetime, list_mount_points, list_volumes, recursive_size,
+    resolve_original_location, scan_trash_dirs, sort_items, terminal_path, tr, tr_format, version_line, ColorChoice,
+    CommandKind, Config, CoreError, DisplayZone, Environment, FileSystem, NetworkFilter, Painter, RealEnvironment,
+    RealFileSystem, Role, SortKey, Theme, TrashDirEvent, TrashIndex, TrashInfo, TrashedItem,
+};
 
-const PYTHON_EXECUTABLES: [&str; 2] = ["python3", "python"];
+const ARGS: &[Arg] = &[
+    Arg::option("print_completion", &["--print-completion"], "SHELL", "print shell completion script (default: None)")
+        .choices(&Shell::NAMES),
+    DEBUG_ENV_ARG,
+    Arg::positional(
+        "path",
+        Nargs::Optional,
+        "Restore files from given path instead of current directory; when it is the exact original path of a \
+         trashed file, restore its most recently trashed version without asking (default: )",
+    )
+    .complete(Complete::File),
+    Arg::option("sort", &["--sort"], "SORT", "Sort list of restore candidates by given field (default: date)")
+        .choices(&SortKey::NAMES),
+    COLOR_ARG,
+    Arg::option("trash_dir", &["--trash-dir"], "TRASH_DIR", "").hidden(),
+    VERSION_ARG,
+    Arg::flag(
+        "overwrite",
+        &["--overwrite"],
+        "Overwrite existing files with files coming out of the trash (default: False)",
+    )
+    .group(0),
+    Arg::flag("backup", &["--backup"], "rename an existing file to NAME~ before restoring").group(0),
+    Arg::flag(
+        "skip_existing",
+        &["--skip-existing"],
+        "leave files whose original path exists in the trash and go on with the others",
+    )
+    .group(0),
+    Arg::option(
+        "to",
+        &["--to"],
+        "DIR",
+        "restore into DIR (keeping the original names) instead of the original parent directories",
+    )
+    .complete(Complete::Directory),
+    Arg::flag(
+        "last",
+        &["--last"],
+        "restore the most recently trashed file (below path, when given) without asking",
+    ),
+    Arg::flag(
+        "no_abbrev",
+        &["--no-abbrev"],
+        "show paths below the home directory in full, not as ~/..., when asking what to restore",
+    ),
+    Arg::flag(
+        "include_network",
+        &["--include-network"],
+        "also scan network filesystems (NFS, CIFS, sshfs, ...) for trash dirs",
+    ),
+    Arg::flag(
+        "verbose",
+        &["-v", "--verbose"],
+        "report the trash dirs scanned and the entries skipped; repeat for more detail",
+    ),
+];
 
-fn main() {
-    let python = find_python_interpreter();
-    let mut command = Command::new(python);
+const EPILOG: &str = "\
+When both stdin and stdout are terminals the candidates are shown in a
+full-screen picker: type to fuzzy-search the original paths, move with the
+arrow keys, select several items with space and restore them with enter.";
+
+fn parser() -> Parser {
+    Parser::new("trash-restore", "Restores from trash chosen file", ARGS).with_epilog(EPILOG)
+}
+
+/// Options understood by the native implementation.
+#[derive(Debug, Default)]
+struct RestoreOptions {
+    path: Option<PathBuf>,
+    sort: SortKey,
+    /// Trash dirs to restore from instead of the usual ones.
+    trash_dirs: Vec<PathBuf>,
+    conflict: Conflict,
+    to: Option<PathBuf>,
+    last: bool,
+    /// Keep the home directory in full instead of writing it `~`.
+    no_abbrev: bool,
+    verbose: u32,
+    /// Read the trashinfo files through the index of each trash dir.
+    use_index: bool,
+    network: NetworkFilter,
+    /// `--color`, or `color` in the config file.
+    color: ColorChoice,
+    theme: Theme,
+}
+
+#[derive(Debug)]
+enum Invocation {
+    Help,
+    PrintCompletion(Shell),
+    PrintVersion,
+    DebugEnv,
+    Native(Box<RestoreOptions>),
+}
 
-    let mut args: Vec<String> = env::args().collect();
-    if !args.is_empty() {
-        args.remove(0);
+fn parse_args(config: &Config, args: &[OsString]) -> Result<Invocation, UsageError> {
+    let matches = match parser().parse_with_config(config, CommandKind::Restore, args)? {
+        Parsed::Help => return Ok(Invocation::Help),
+        Parsed::Args(matches) => matches,
+    };
+    if let Some(found) = matches.iter().find(|found| found.dest == "print_completion") {
+        let shell = Shell::parse(&found.text()).expect("checked against the choices");
+        return Ok(Invocation::PrintCompletion(shell));
     }
+    if matches.iter().any(|found| found.dest == "version") {
+        return Ok(Invocation::PrintVersion);
+    }
+    if matches.iter().any(|found| found.dest == "debug_env") {
+        return Ok(Invocation::DebugEnv);
+    }
+    let mut options = RestoreOptions {
+        color: config.color,
+        theme: config.theme.clone(),
+        ..RestoreOptions::default()
+    };
+    for found in matches {
+        match found.dest {
+            "overwrite" => options.conflict = Conflict::Overwrite,
+            "backup" => options.conflict = Conflict::Backup,
+            "skip_existing" => options.conflict = Conflict::Skip,
+            "last" => options.last = true,
+            "no_abbrev" => options.no_abbrev = true,
+            "verbose" => options.verbose += 1,
+            "include_network" => options.network.include_all = true,
+            // The parser only accepts the listed choices.
+            "sort" => options.sort = SortKey::parse(&found.text()).unwrap_or_default(),
+            "color" => options.color = ColorChoice::parse(&found.text()).unwrap_or_default(),
+            "to" => options.to = found.value.map(PathBuf::from),
+            "trash_dir" => options.trash_dirs = found.value.map(trash_dir_arg).into_iter().collect(),
+            "path" => options.path = found.value.map(PathBuf::from),
+            _ => {}
+        }
+    }
+    if options.trash_dirs.is_empty() {
+        options.trash_dirs = config.trash_dirs.clone();
+    }
+    options.use_index = config.index;
+    options.network.fstypes = config.include_network.clone();
+    Ok(Invocation::Native(Box::new(options)))
+}
 
-    let status = command
-        .arg("-m")
-        .arg("trashcli.restore.main")
-        .args(args)
-        .stdin(Stdio::inherit())
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .status();
+/// The deletion date as Python's `str(datetime)` renders it.
+fn deletion_date(item: &TrashedItem) -> String {
+    item.deleted_at
+        .map(|date| format_datetime(date, DisplayZone::Local))
+        .unwrap_or_else(|| "None".to_string())
+}
 
-    match status {
-        Ok(status) => {
-            let code = status.code().unwrap_or(1);
-            exit(code);
+/// Every trashinfo of the found trash dirs, warning about unparsable ones.
+fn trashed_files(fs: &dyn FileSystem, events: &[TrashDirEvent], use_index: bool) -> Vec<TrashedItem> {
+    let mut trashed_files = Vec::new();
+    for trash_dir in events.iter().filter_map(|event| event.found()) {
+        log::info("restore", format_args!("scanning {}", trash_dir.path.display()));
+        let volume = trash_dir.mount_point.clone().unwrap_or_else(|| PathBuf::from("/"));
+        let mut indexed: HashMap<PathBuf, trash_cli_core::Result<String>> = match use_index {
+            true => TrashIndex::refresh(fs, &trash_dir.path)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|item| (item.path, item.content))
+                .collect(),
+            false => HashMap::new(),
+        };
+        for trashinfo_path in fs.list_dir(&trash_dir.info_dir).unwrap_or_default() {
+            let Some(basename) = entry_name(&trashinfo_path) else {
+                eprintln!("{}", tr("trash-restore: Non .trashinfo file in info dir"));
+                continue;
+            };
+            let backup_path = trash_dir.files_dir.join(basename);
+            let contents = indexed
+                .remove(&trashinfo_path)
+                .unwrap_or_else(|| fs.read_to_string(&trashinfo_path));
+            let contents = match contents {
+                Ok(contents) => contents,
+                Err(err) => {
+                    eprintln!("trash-restore: {}", err);
+                    continue;
+                }
+            };
+            let info = match TrashInfo::parse(&contents) {
+                Ok(info) => info,
+                Err(err) => {
+                    let message = tr_format(
+                        "trash-restore: Non parsable trashinfo file: {}, because {}",
+                        &[&trashinfo_path.display(), &err],
+                    );
+                    eprintln!("{}", message);
+                    continue;
+                }
+            };
+            let deleted_at = info.deleted_at();
+            let mut item = TrashedItem::new(
+                resolve_original_location(&volume, &info.path),
+                backup_path,
+                trashinfo_path,
+                trash_dir.path.clone(),
+            );
+            item.deleted_at = deleted_at;
+            log::trace("restore", format_args!("found '{}'", item.original_path.display()));
+            trashed_files.push(item);
         }
-        Err(err) => {
-            eprintln!("failed to start Python restore backend: {}", err);
-            exit(1);
+    }
+    trashed_files
+}
+
+/// Whether `original_location` is `path` or lies below it.
+fn matches_path(original_location: &Path, path: &Path) -> bool {
+    path == Path::new("/") || original_location.starts_with(path)
+}
+
+/// The most recently trashed of `files`; undated ones count as oldest.
+fn most_recent<'a>(files: impl Iterator<Item = &'a TrashedItem>) -> Option<&'a TrashedItem> {
+    files.max_by_key(|file| file.deleted_at)
+}
+
+/// `path` made absolute against `cwd` with `.` and `..` resolved
+/// lexically, like `os.path.normpath`.
+fn normalize(cwd: &Path, path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::from("/");
+    for component in cwd.join(path).components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::Normal(segment) => normalized.push(segment),
+            Component::RootDir | Component::CurDir | Component::Prefix(_) => {}
         }
     }
+    normalized
 }
 
-fn find_python_interpreter() -> PathBuf {
-    if let Ok(py) = env::var("TRASH_RESTORE_PYTHON") {
-        return Path::new(&py).to_path_buf();
+/// Restores `files` in order, stopping at the first failure.
+fn restore_all<'f>(restorer: &Restorer, files: impl IntoIterator<Item = &'f TrashedItem>) -> i32 {
+    for file in files {
+        match restorer.restore(file) {
+            Ok(Restored::Done(_)) => {}
+            Ok(Restored::Skipped(destination)) => {
+                eprintln!("{}", tr_format("trash-restore: skipped '{}': it already exists", &[&destination.display()]))
+            }
+            Err(CoreError::Conflict(message)) => {
+                eprintln!("{}", message);
+                return exit_codes::FAILURE.into();
+            }
+            Err(err) => {
+                eprintln!("trash-restore: {}", err);
+                return exit_codes::FAILURE.into();
+            }
+        }
     }
+    0
+}
+
+/// The classic flow: print the numbered candidates, on a `terminal` with
+/// `home` written `~` and control characters escaped, and read a selection.
+fn restore_asking_the_user(
+    restorer: &Restorer,
+    trashed_files: &[TrashedItem],
+    painter: &Painter,
+    terminal: bool,
+    home: Option<&Path>,
+) -> i32 {
+    for (index, file) in trashed_files.iter().enumerate() {
+        let path = match terminal {
+            true => terminal_path(&file.original_path, home),
+            false => file.original_path.clone(),
+        };
+        let path = match painter.is_enabled() && file.trashed_path.symlink_metadata().is_err() {
+            true => PathBuf::from(painter.paint_os(Role::Missing, path.as_os_str())),
+            false => path,
+        };
+        println!("{:4} {} {}", index, painter.paint(Role::Date, &deletion_date(file)), path.display());
+    }
+    print!("{}", tr_format("What file to restore [0..{}]: ", &[&(trashed_files.len() - 1)]));
+    let _ = io::stdout().flush();
 
-    for candidate in PYTHON_EXECUTABLES.iter() {
-        if let Ok(py) = which::which(candidate) {
-            return py;
+    let mut reply = String::new();
+    match io::stdin().lock().read_line(&mut reply) {
+        Ok(0) | Err(_) => {
+            eprintln!();
+            return exit_codes::FAILURE.into();
         }
+        Ok(_) => {}
+    }
+    let reply = reply.trim_end_matches(['\n', '\r']);
+    if reply.is_empty() {
+        println!("{}", tr("No files were restored"));
+        return 0;
     }
+    let indexes = match parse_indexes(reply, trashed_files.len()) {
+        Ok(indexes) => indexes,
+        Err(message) => {
+            eprintln!("{}", tr_format("Invalid entry: {}", &[&message]));
+            return exit_codes::FAILURE.into();
+        }
+    };
+    restore_all(restorer, indexes.iter().map(|index| &trashed_files[*index]))
+}
 
-    Path::new("python").to_path_buf()
+/// Parses a selection like `0-3,7,12` (ranges are inclusive) into indexes
+/// in the order given, each at most once.
+fn parse_indexes(reply: &str, len: usize) -> Result<Vec<usize>, String> {
+    let mut indexes = Vec::new();
+    for entry in reply.split(',') {
+        let (first, last) = match entry.split_once('-') {
+            Some(("", _)) | Some((_, "")) => return Err(format!("open interval: {}", entry)),
+            Some((first, last)) => match (parse_int_index(first)?, parse_int_index(last)?) {
+                (first, last) if first > last => return Err(format!("reversed interval: {}", entry)),
+                range => range,
+            },
+            None => {
+                let index = parse_int_index(entry)?;
+                (index, index)
+            }
+        };
+        for index in first..=last {
+            if index >= len {
+                return Err(format!("out of range 0..{}: {}", len - 1, index));
+            }
+            if !indexes.contains(&index) {
+                indexes.push(index);
+            }
+        }
+    }
+    Ok(indexes)
+}
+
+fn parse_int_index(text: &str) -> Result<usize, String> {
+    text.trim()
+        .parse::<usize>()
+        .map_err(|_| format!("not an index: {}", text))
+}
+
+fn run_native(options: RestoreOptions) -> i32 {
+    log::init("trash-restore", options.verbose);
+    let fs = RealFileSystem;
+    let environ = RealEnvironment;
+    let uid = environ.uid();
+    let cwd = env::current_dir()
+        .and_then(std::fs::canonicalize)
+        .unwrap_or_else(|_| PathBuf::from("/"));
+    let path = normalize(&cwd, options.path.as_deref().unwrap_or(Path::new("")));
+
+    let events = match options.trash_dirs.is_empty() {
+        true => scan_trash_dirs(&fs, &environ, uid, &list_volumes(&environ, &options.network)),
+        false => user_specified_trash_dirs(&options.trash_dirs, &list_mount_points()),
+    };
+    let all_files = trashed_files(&fs, &events, options.use_index);
+    let mut restore_options = restore::RestoreOptions::default();
+    restore_options.conflict = options.conflict;
+    restore_options.to = options.to.as_deref().map(|to| normalize(&cwd, to));
+    let restorer = Restorer::new(&fs, &environ, &restore_options);
+
+    if options.last {
+        let below = |file: &&TrashedItem| options.path.is_none() || matches_path(&file.original_path, &path);
+        return match most_recent(all_files.iter().filter(below)) {
+            Some(file) => restore_all(&restorer, [file]),
+            None => {
+                eprintln!("{}", tr("trash-restore: no trashed files to restore"));
+                exit_codes::FAILURE.into()
+            }
+        };
+    }
+    if options.path.is_some() {
+        let exact = all_files.iter().filter(|file| file.original_path == path);
+        if let Some(file) = most_recent(exact) {
+            return restore_all(&restorer, [file]);
+        }
+    }
+
+    let mut candidates: Vec<TrashedItem> = all_files
+        .into_iter()
+        .filter(|file| matches_path(&file.original_path, &path))
+        .collect();
+    if options.sort.needs_size() {
+        for item in &mut candidates {
+            item.size_bytes = recursive_size(&fs, &item.trashed_path).ok();
+        }
+    }
+    sort_items(&mut candidates, options.sort);
+
+    if candidates.is_empty() {
+        println!("{}", tr_format("No files trashed from current dir ('{}')", &[&cwd.display()]));
+        return 0;
+    }
+    // Piped output is read by scripts, which expect full paths.
+    let terminal = io::stdout().is_terminal();
+    let home = environ.home().filter(|_| !options.no_abbrev && terminal);
+    if !(io::stdin().is_terminal() && terminal) {
+        let painter = Painter::new(options.color, &io::stdout(), &environ, &options.theme);
+        return restore_asking_the_user(&restorer, &candidates, &painter, terminal, home.as_deref());
+    }
+    match picker::pick(&fs, &candidates, home.as_deref()) {
+        Ok(Some(indexes)) => restore_all(&restorer, indexes.iter().map(|index| &candidates[*index])),
+        Ok(None) => {
+            println!("{}", tr("No files were restored"));
+            0
+        }
+        Err(err) => {
+            eprintln!("trash-restore: {}", err);
+            exit_codes::FAILURE.into()
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<OsString> = env::args_os().skip(1).collect();
+    let config = load_config("trash-restore");
+    match parse_args(&config, &args) {
+        Ok(Invocation::Help) => println!("{}", parser().format_help()),
+        Ok(Invocation::PrintCompletion(shell)) => print!("{}", parser().format_completion(shell)),
+        Ok(Invocation::PrintVersion) => println!("{}", version_line("trash-restore")),
+        Ok(Invocation::DebugEnv) => println!("{}", describe_environment(&RealEnvironment, CommandKind::Restore)),
+        Ok(Invocation::Native(options)) => exit(run_native(*options)),
+        Err(error) => parser().exit_with_error(&error.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_indexes_and_intervals() {
+        assert_eq!(parse_indexes("9", 10), Ok(vec![9]));
+        assert_eq!(parse_indexes("1-5,7", 10), Ok(vec![1, 2, 3, 4, 5, 7]));
+        assert_eq!(parse_indexes("2, 1-3", 10), Ok(vec![2, 1, 3]));
+    }
+
+    #[test]
+    fn rejects_invalid_entries() {
+        assert_eq!(parse_indexes("a", 10), Err("not an index: a".to_string()));
+        assert_eq!(parse_indexes("10", 10), Err("out of range 0..9: 10".to_string()));
+        assert_eq!(parse_indexes("-1", 10), Err("open interval: -1".to_string()));
+        assert_eq!(parse_indexes("1-", 10), Err("open interval: 1-".to_string()));
+        assert_eq!(parse_indexes("5-2", 10), Err("reversed interval: 5-2".to_string()));
+    }
 }
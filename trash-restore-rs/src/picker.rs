@@ -0,0 +1,300 @@
+//! Full-screen picker for restore candidates: incremental fuzzy search over
+//! the original paths, multi-selection and a preview of the highlighted
+//! item's deletion date and size.
+
+use crate::deletion_date;
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, Write};
+use std::path::Path;
+use trash_cli_core::{print_size, recursive_size, terminal_path, FileSystem, TrashedItem};
+
+/// Lines used by the search prompt at the top and the preview and key help
+/// at the bottom.
+const CHROME_LINES: u16 = 4;
+
+/// Lets the user pick candidates, shown with `home` written `~` and control
+/// characters escaped; returns their indexes in list order, or `None` when
+/// the picker was cancelled.
+pub fn pick(fs: &dyn FileSystem, files: &[TrashedItem], home: Option<&Path>) -> io::Result<Option<Vec<usize>>> {
+    let _screen = Screen::enter()?;
+    let mut picker = Picker::new(fs, files, home);
+    let mut stdout = io::stdout();
+    loop {
+        picker.draw(&mut stdout)?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind == KeyEventKind::Release {
+            continue;
+        }
+        if let Some(outcome) = picker.handle(key) {
+            return Ok(outcome);
+        }
+    }
+}
+
+/// Raw mode on the alternate screen, undone on drop so the terminal is
+/// restored on every exit path.
+struct Screen;
+
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        if let Err(err) = execute!(io::stdout(), EnterAlternateScreen, Hide) {
+            let _ = terminal::disable_raw_mode();
+            return Err(err);
+        }
+        Ok(Screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// A candidate accepted by the current query.
+struct Hit {
+    index: usize,
+    positions: Vec<usize>,
+}
+
+struct Picker<'a> {
+    fs: &'a dyn FileSystem,
+    files: &'a [TrashedItem],
+    paths: Vec<String>,
+    query: String,
+    hits: Vec<Hit>,
+    cursor: usize,
+    scroll: usize,
+    selected: BTreeSet<usize>,
+    sizes: HashMap<usize, Option<u64>>,
+}
+
+impl<'a> Picker<'a> {
+    fn new(fs: &'a dyn FileSystem, files: &'a [TrashedItem], home: Option<&Path>) -> Self {
+        let mut picker = Picker {
+            fs,
+            files,
+            paths: files
+                .iter()
+                .map(|file| terminal_path(&file.original_path, home).to_string_lossy().into_owned())
+                .collect(),
+            query: String::new(),
+            hits: Vec::new(),
+            cursor: 0,
+            scroll: 0,
+            selected: BTreeSet::new(),
+            sizes: HashMap::new(),
+        };
+        picker.refilter();
+        picker
+    }
+
+    /// Re-ranks the candidates for the current query; the best match goes
+    /// first and ties keep the listing order.
+    fn refilter(&mut self) {
+        let mut scored: Vec<(i64, Hit)> = self
+            .paths
+            .iter()
+            .enumerate()
+            .filter_map(|(index, path)| {
+                fuzzy_match(&self.query, path).map(|(score, positions)| (score, Hit { index, positions }))
+            })
+            .collect();
+        if !self.query.is_empty() {
+            scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        }
+        self.hits = scored.into_iter().map(|(_, hit)| hit).collect();
+        self.cursor = 0;
+        self.scroll = 0;
+    }
+
+    /// Applies a key press; `Some` ends the picker.
+    fn handle(&mut self, key: KeyEvent) -> Option<Option<Vec<usize>>> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Some(None),
+            KeyCode::Char('c') | KeyCode::Char('d') if ctrl => return Some(None),
+            KeyCode::Enter => {
+                if !self.selected.is_empty() {
+                    return Some(Some(self.selected.iter().copied().collect()));
+                }
+                return self.hits.get(self.cursor).map(|hit| Some(vec![hit.index]));
+            }
+            KeyCode::Up => self.move_cursor(-1),
+            KeyCode::Char('p') if ctrl => self.move_cursor(-1),
+            KeyCode::Down => self.move_cursor(1),
+            KeyCode::Char('n') if ctrl => self.move_cursor(1),
+            KeyCode::PageUp => self.move_cursor(-(self.page_height() as isize)),
+            KeyCode::PageDown => self.move_cursor(self.page_height() as isize),
+            KeyCode::Char(' ') => {
+                if let Some(hit) = self.hits.get(self.cursor) {
+                    if !self.selected.remove(&hit.index) {
+                        self.selected.insert(hit.index);
+                    }
+                    self.move_cursor(1);
+                }
+            }
+            KeyCode::Char('u') if ctrl => {
+                self.query.clear();
+                self.refilter();
+            }
+            KeyCode::Backspace if !self.query.is_empty() => {
+                self.query.pop();
+                self.refilter();
+            }
+            KeyCode::Char(ch) if !ctrl => {
+                self.query.push(ch);
+                self.refilter();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn move_cursor(&mut self, delta: isize) {
+        let last = self.hits.len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(delta).min(last);
+    }
+
+    fn page_height(&self) -> usize {
+        let (_, rows) = terminal::size().unwrap_or((80, 24));
+        rows.saturating_sub(CHROME_LINES).max(1) as usize
+    }
+
+    fn draw(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let (columns, _) = terminal::size().unwrap_or((80, 24));
+        let width = columns as usize;
+        let height = self.page_height();
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if self.cursor >= self.scroll + height {
+            self.scroll = self.cursor + 1 - height;
+        }
+
+        queue!(out, Clear(ClearType::All), MoveTo(0, 0))?;
+        let counter = format!("  {}/{} ({} selected)", self.hits.len(), self.files.len(), self.selected.len());
+        queue!(out, Print(truncate(&format!("> {}{}", self.query, counter), width)))?;
+
+        for (row, hit) in self.hits.iter().enumerate().skip(self.scroll).take(height) {
+            let file = &self.files[hit.index];
+            let current = row == self.cursor;
+            let mark = if self.selected.contains(&hit.index) { "[x]" } else { "[ ]" };
+            queue!(out, MoveTo(0, (row - self.scroll + 1) as u16))?;
+            if current {
+                queue!(out, SetAttribute(Attribute::Reverse))?;
+            }
+            let prefix = format!("{} {} ", mark, deletion_date(file));
+            queue!(out, Print(truncate(&prefix, width)))?;
+            let room = width.saturating_sub(prefix.chars().count());
+            for (position, ch) in self.paths[hit.index].chars().take(room).enumerate() {
+                if hit.positions.binary_search(&position).is_ok() {
+                    queue!(out, SetAttribute(Attribute::Bold), Print(ch), SetAttribute(Attribute::NormalIntensity))?;
+                } else {
+                    queue!(out, Print(ch))?;
+                }
+            }
+            queue!(out, SetAttribute(Attribute::Reset))?;
+        }
+
+        let preview_row = height as u16 + 1;
+        queue!(out, MoveTo(0, preview_row), Print(truncate(&self.preview(), width)))?;
+        queue!(
+            out,
+            MoveTo(0, preview_row + 1),
+            Print(truncate("up/down: move  space: select  enter: restore  esc: cancel", width))
+        )?;
+        out.flush()
+    }
+
+    /// Deletion date, size and backup path of the highlighted item; sizes
+    /// are computed once per item, on first display.
+    fn preview(&mut self) -> String {
+        let Some(index) = self.hits.get(self.cursor).map(|hit| hit.index) else {
+            return "no matches".to_string();
+        };
+        let file = &self.files[index];
+        let fs = self.fs;
+        let size = *self
+            .sizes
+            .entry(index)
+            .or_insert_with(|| file.size_bytes.or_else(|| recursive_size(fs, &file.trashed_path).ok()));
+        format!(
+            "deleted {}  size {}  in {}",
+            deletion_date(file),
+            size.map(print_size).unwrap_or_else(|| "?".to_string()),
+            file.trashed_path.display()
+        )
+    }
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+/// Scores `candidate` against `query` when every query character occurs in
+/// it in order; returns the score and the matched character positions.
+///
+/// Matching is case-insensitive unless the query contains an uppercase
+/// letter. Consecutive matches, matches at the start of a path segment or
+/// word, and matches in the basename score higher; gaps cost a little.
+fn fuzzy_match(query: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+    let case_sensitive = query.chars().any(char::is_uppercase);
+    let fold = |ch: char| if case_sensitive { ch } else { ch.to_ascii_lowercase() };
+    let query: Vec<char> = query.chars().map(fold).collect();
+    let chars: Vec<char> = candidate.chars().collect();
+    let basename_start = chars.iter().rposition(|ch| *ch == '/').map_or(0, |slash| slash + 1);
+
+    // Find the end of the first full match, then walk back from it to get
+    // the tightest window ending there.
+    let mut next = 0;
+    let mut end = None;
+    for (position, ch) in chars.iter().enumerate() {
+        if fold(*ch) == query[next] {
+            next += 1;
+            if next == query.len() {
+                end = Some(position);
+                break;
+            }
+        }
+    }
+    let end = end?;
+    let mut positions = Vec::with_capacity(query.len());
+    let mut remaining = query.len();
+    for position in (0..=end).rev() {
+        if remaining > 0 && fold(chars[position]) == query[remaining - 1] {
+            remaining -= 1;
+            positions.push(position);
+        }
+    }
+    positions.reverse();
+
+    let mut score = 0i64;
+    for (nth, position) in positions.iter().enumerate() {
+        score += 16;
+        if nth > 0 {
+            let gap = position - positions[nth - 1] - 1;
+            score += if gap == 0 { 12 } else { -(gap.min(8) as i64) };
+        }
+        match position.checked_sub(1).map(|previous| chars[previous]) {
+            None | Some('/') => score += 10,
+            Some('-' | '_' | '.' | ' ') => score += 6,
+            _ => {}
+        }
+        if *position >= basename_start {
+            score += 4;
+        }
+    }
+    Some((score, positions))
+}
@@ -0,0 +1,190 @@
+use std::env;
+use std::ffi::OsString;
+use std::io;
+use std::path::Path;
+use trash_cli_core::cli::{
+    load_config, Arg, Parsed, Parser, Shell, UsageError, DEBUG_ENV_ARG, PRINT_COMPLETION_ARG, VERSION_ARG,
+};
+use trash_cli_core::exit_codes;
+use trash_cli_core::log;
+use trash_cli_core::{
+    copy_tree, describe_environment, version_line, CommandKind, Config, FileSystem, Journal, JournalItem, Operation,
+    RealEnvironment, RealFileSystem, Record, TrashIndex,
+};
+
+const ARGS: &[Arg] = &[
+    PRINT_COMPLETION_ARG,
+    VERSION_ARG,
+    DEBUG_ENV_ARG,
+    Arg::flag("verbose", &["-v", "--verbose"], "report each item put back; repeat for more detail"),
+    Arg::flag("dry_run", &["--dry-run"], "show what would be undone"),
+];
+
+const EPILOG: &str = "\
+Undoing a trash-put restores the files it trashed; undoing a trash-restore
+trashes the restored files again. Each run undoes one command, the most
+recent not undone yet; what trash-empty removed cannot be brought back.";
+
+fn parser() -> Parser {
+    Parser::new("trash-undo", "Undo the last trash-put or trash-restore", ARGS).with_epilog(EPILOG)
+}
+
+#[derive(Debug, Default)]
+struct UndoOptions {
+    verbose: u32,
+    dry_run: bool,
+}
+
+#[derive(Debug)]
+enum Invocation {
+    Help,
+    PrintCompletion(Shell),
+    PrintVersion,
+    DebugEnv,
+    Native(UndoOptions),
+}
+
+fn parse_args(config: &Config, args: &[OsString]) -> Result<Invocation, UsageError> {
+    let matches = match parser().parse_with_config(config, CommandKind::Undo, args)? {
+        Parsed::Help => return Ok(Invocation::Help),
+        Parsed::Args(matches) => matches,
+    };
+    if let Some(found) = matches.iter().find(|found| found.dest == "print_completion") {
+        let shell = Shell::parse(&found.text()).expect("checked against the choices");
+        return Ok(Invocation::PrintCompletion(shell));
+    }
+    if matches.iter().any(|found| found.dest == "version") {
+        return Ok(Invocation::PrintVersion);
+    }
+    if matches.iter().any(|found| found.dest == "debug_env") {
+        return Ok(Invocation::DebugEnv);
+    }
+    let mut options = UndoOptions::default();
+    for found in matches {
+        match found.dest {
+            "verbose" => options.verbose += 1,
+            "dry_run" => options.dry_run = true,
+            _ => {}
+        }
+    }
+    Ok(Invocation::Native(options))
+}
+
+/// Moves `item` from the trash back to its path.
+fn untrash(fs: &dyn FileSystem, item: &JournalItem) -> Result<(), String> {
+    let backup_path = item.backup_path();
+    if fs.symlink_metadata(&backup_path).is_err() {
+        return Err("it is no longer in the trash".to_string());
+    }
+    if fs.symlink_metadata(&item.path).is_ok() {
+        return Err("the path exists again".to_string());
+    }
+    if let Some(parent) = item.path.parent() {
+        fs.create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    move_path(fs, &backup_path, &item.path).map_err(|err| err.to_string())?;
+    fs.remove_file(&item.trashinfo_path()).map_err(|err| err.to_string())?;
+    TrashIndex::update(fs, &item.trash_dir, |index| {
+        index.remove(&item.name);
+    });
+    Ok(())
+}
+
+/// Moves `item` from its path back into the trash, under its former name
+/// and with its former trashinfo.
+fn retrash(fs: &dyn FileSystem, item: &JournalItem) -> Result<(), String> {
+    if fs.symlink_metadata(&item.path).is_err() {
+        return Err("it no longer exists".to_string());
+    }
+    let backup_path = item.backup_path();
+    if fs.symlink_metadata(&backup_path).is_ok() {
+        return Err(format!("{} is taken", backup_path.display()));
+    }
+    // Reserves the name, like trash-put does.
+    let trashinfo_path = item.trashinfo_path();
+    fs.write_new(&trashinfo_path, item.trashinfo.as_bytes(), false)
+        .map_err(|err| err.to_string())?;
+    move_path(fs, &item.path, &backup_path).map_err(|err| {
+        let _ = fs.remove_file(&trashinfo_path);
+        err.to_string()
+    })?;
+    TrashIndex::update(fs, &item.trash_dir, |index| index.insert(item.name.clone(), item.trashinfo.clone()));
+    Ok(())
+}
+
+/// Renames `source` to `dest`, copying and deleting across devices.
+fn move_path(fs: &dyn FileSystem, source: &Path, dest: &Path) -> io::Result<()> {
+    match fs.rename(source, dest).map_err(io::Error::from) {
+        Err(err) if err.raw_os_error() == Some(libc::EXDEV) => {
+            copy_tree(fs, source, dest)?;
+            fs.remove_dir_all(source).map_err(io::Error::from)
+        }
+        result => result,
+    }
+}
+
+fn run_native(options: UndoOptions) -> i32 {
+    log::init("trash-undo", options.verbose);
+    let fs = RealFileSystem;
+    let journal = Journal::open(&RealEnvironment);
+    let batch = match journal.last_batch(&fs, &[Operation::Put, Operation::Restore]) {
+        Ok(Some(batch)) => batch,
+        Ok(None) => {
+            eprintln!("trash-undo: nothing to undo");
+            return exit_codes::FAILURE.into();
+        }
+        Err(err) => {
+            eprintln!("trash-undo: {}", err);
+            return err.exit_code().into();
+        }
+    };
+    log::info("undo", format_args!("undoing batch {}", batch.id));
+
+    let mut undone = 0;
+    let mut failed = 0;
+    // Last in, first out: a directory trashed after its contents comes
+    // back before them.
+    for item in batch.items.iter().rev() {
+        let (verb, result) = match item.operation {
+            Operation::Put if options.dry_run => ("would restore", Ok(())),
+            Operation::Put => ("restored", untrash(&fs, item)),
+            Operation::Restore if options.dry_run => ("would trash again", Ok(())),
+            Operation::Restore => ("trashed again", retrash(&fs, item)),
+            Operation::Empty => continue,
+        };
+        match result {
+            Ok(()) => {
+                undone += 1;
+                if options.dry_run || options.verbose > 0 {
+                    println!("{} '{}'", verb, item.path.display());
+                }
+            }
+            Err(message) => {
+                failed += 1;
+                eprintln!(
+                    "trash-undo: cannot undo {} of '{}': {}",
+                    item.operation.as_str(),
+                    item.path.display(),
+                    message
+                );
+            }
+        }
+    }
+    if !options.dry_run && undone > 0 {
+        journal.record(&fs, &Record::Undo(batch.id));
+    }
+    i32::from(failed > 0)
+}
+
+fn main() {
+    let args: Vec<OsString> = env::args_os().skip(1).collect();
+    let config = load_config("trash-undo");
+    match parse_args(&config, &args) {
+        Ok(Invocation::Help) => println!("{}", parser().format_help()),
+        Ok(Invocation::PrintCompletion(shell)) => print!("{}", parser().format_completion(shell)),
+        Ok(Invocation::PrintVersion) => println!("{}", version_line("trash-undo")),
+        Ok(Invocation::DebugEnv) => println!("{}", describe_environment(&RealEnvironment, CommandKind::Undo)),
+        Ok(Invocation::Native(options)) => std::process::exit(run_native(options)),
+        Err(error) => parser().exit_with_error(&error.0),
+    }
+}